@@ -0,0 +1,179 @@
+//! Connection lifecycle events.
+use std::error::Error;
+use std::io;
+use std::sync::Arc;
+
+use crate::http::{ConnectionInfo, StatusCode, request};
+use crate::log::{debug, error, trace, warning};
+
+/// Shared [`Observer`].
+pub type SharedObserver = Arc<dyn Observer + Send + Sync>;
+
+// ===== Observer =====
+
+/// Observe connection lifecycle events.
+///
+/// The default implementation of each method report the event with the crate logger, which is
+/// enabled with the `log` feature.
+#[allow(unused_variables, reason = "used by logger")]
+pub trait Observer {
+    /// Called when a connection is accepted.
+    fn on_accept(&self, info: &ConnectionInfo) {
+        debug!("connection accepted, peer: {}", Peer(info));
+    }
+
+    /// Called when failed to accept connection.
+    fn on_accept_error(&self, error: &io::Error) {
+        error!("failed to accept connection: {error}");
+    }
+
+    /// Called when a connection is closed.
+    fn on_close(&self, info: &ConnectionInfo) {
+        debug!("connection closed, peer: {}", Peer(info));
+    }
+
+    /// Called when a connection is closed because of an error.
+    fn on_abort(&self, info: &ConnectionInfo, error: &(dyn Error + 'static)) {
+        warning!("connection aborted, peer: {}: {error}", Peer(info));
+    }
+
+    /// Called when the peer violates the protocol.
+    fn on_protocol_error(&self, info: &ConnectionInfo, error: &(dyn Error + 'static)) {
+        debug!("protocol error, peer: {}: {error}", Peer(info));
+    }
+
+    /// Called when the service returns an error.
+    ///
+    /// The error is rendered with the connection [`ErrorHandler`][crate::service::ErrorHandler].
+    fn on_service_error(&self, info: &ConnectionInfo, error: &(dyn Error + 'static)) {
+        error!("service error, peer: {}: {error}", Peer(info));
+    }
+
+    /// Called when a request is received.
+    fn on_request_start(&self, parts: &request::Parts) {
+        debug!("{} {}", parts.method, parts.target);
+    }
+
+    /// Called when the response of a request is completely written.
+    fn on_request_end(&self, info: &ConnectionInfo, status: StatusCode) {
+        debug!("{}, peer: {}", status.as_str(), Peer(info));
+    }
+
+    /// Called when bytes are read from the connection.
+    fn on_read(&self, info: &ConnectionInfo, len: usize) {
+        trace!("read {len} bytes, peer: {}", Peer(info));
+    }
+
+    /// Called when bytes are written to the connection.
+    fn on_write(&self, info: &ConnectionInfo, len: usize) {
+        trace!("write {len} bytes, peer: {}", Peer(info));
+    }
+}
+
+impl<O: Observer + ?Sized> Observer for Arc<O> {
+    fn on_accept(&self, info: &ConnectionInfo) {
+        O::on_accept(self, info);
+    }
+
+    fn on_accept_error(&self, error: &io::Error) {
+        O::on_accept_error(self, error);
+    }
+
+    fn on_close(&self, info: &ConnectionInfo) {
+        O::on_close(self, info);
+    }
+
+    fn on_abort(&self, info: &ConnectionInfo, error: &(dyn Error + 'static)) {
+        O::on_abort(self, info, error);
+    }
+
+    fn on_protocol_error(&self, info: &ConnectionInfo, error: &(dyn Error + 'static)) {
+        O::on_protocol_error(self, info, error);
+    }
+
+    fn on_service_error(&self, info: &ConnectionInfo, error: &(dyn Error + 'static)) {
+        O::on_service_error(self, info, error);
+    }
+
+    fn on_request_start(&self, parts: &request::Parts) {
+        O::on_request_start(self, parts);
+    }
+
+    fn on_request_end(&self, info: &ConnectionInfo, status: StatusCode) {
+        O::on_request_end(self, info, status);
+    }
+
+    fn on_read(&self, info: &ConnectionInfo, len: usize) {
+        O::on_read(self, info, len);
+    }
+
+    fn on_write(&self, info: &ConnectionInfo, len: usize) {
+        O::on_write(self, info, len);
+    }
+}
+
+impl std::fmt::Debug for dyn Observer + Send + Sync {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Observer")
+    }
+}
+
+// ===== LogObserver =====
+
+/// [`Observer`] that report events with the crate logger.
+///
+/// This is the default observer.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LogObserver;
+
+impl Observer for LogObserver { }
+
+impl LogObserver {
+    pub(crate) fn shared() -> SharedObserver {
+        Arc::new(LogObserver)
+    }
+}
+
+// ===== Helpers =====
+
+#[allow(unused, reason = "used by logger")]
+struct Peer<'a>(&'a ConnectionInfo);
+
+impl std::fmt::Display for Peer<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0.peer_addr() {
+            Some(addr) => addr.fmt(f),
+            None => f.write_str("unknown"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use tcio::bytes::Bytes;
+
+    use crate::body::{Full, Incoming};
+    use crate::http::{Request, Response};
+    use crate::server::{Http1Server, MemoryListener, TestClient};
+    use crate::service::from_fn;
+    use crate::testing::{Recorder, block_on, settle};
+
+    #[test]
+    fn test_observer_events() {
+        block_on(async {
+            let recorder = Recorder::default();
+            let service = from_fn(|_: Request<Incoming>| async {
+                Response::from_parts(Default::default(), Full::new(Bytes::from_static(b"hello")))
+            });
+            let (listener, connector) = MemoryListener::new();
+            tokio::spawn(Http1Server::new(service, listener).observer(recorder.clone()));
+
+            let mut client = TestClient::new(connector.connect().unwrap());
+            client.send(Request::<Full<Bytes>>::default()).await.unwrap();
+            drop(client);
+
+            settle().await;
+            assert_eq!(recorder.events(), ["accept", "request_end", "close"]);
+        });
+    }
+}
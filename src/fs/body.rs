@@ -0,0 +1,85 @@
+use std::io;
+use std::pin::Pin;
+use std::task::{Poll, ready};
+use tcio::bytes::Bytes;
+use tokio::fs::File;
+use tokio::io::{AsyncRead, ReadBuf};
+
+use crate::body::Body;
+
+/// Maximum size of each chunk read from the file.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// File contents streamed as [`Body`].
+#[derive(Debug)]
+pub struct FileBody {
+    file: Option<File>,
+    /// remaining bytes to read
+    remaining: u64,
+    buf: Vec<u8>,
+}
+
+impl FileBody {
+    /// Create body that read `len` bytes from the current position of `file`.
+    pub(crate) fn new(file: File, len: u64) -> Self {
+        Self {
+            file: (len != 0).then_some(file),
+            remaining: len,
+            buf: Vec::new(),
+        }
+    }
+
+    /// Create empty body.
+    pub(crate) fn empty() -> Self {
+        Self {
+            file: None,
+            remaining: 0,
+            buf: Vec::new(),
+        }
+    }
+}
+
+impl Body for FileBody {
+    type Data = Bytes;
+
+    type Error = io::Error;
+
+    fn poll_data(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        let me = self.get_mut();
+        let Some(file) = &mut me.file else {
+            return Poll::Ready(None);
+        };
+
+        let len = me.remaining.min(CHUNK_SIZE as u64) as usize;
+        me.buf.resize(len, 0);
+        let mut buf = ReadBuf::new(&mut me.buf);
+        if let Err(err) = ready!(Pin::new(file).poll_read(cx, &mut buf)) {
+            me.file = None;
+            return Poll::Ready(Some(Err(err)));
+        }
+
+        let read = buf.filled().len();
+        if read == 0 {
+            me.file = None;
+            return Poll::Ready(Some(Err(io::ErrorKind::UnexpectedEof.into())));
+        }
+
+        me.remaining -= read as u64;
+        if me.remaining == 0 {
+            me.file = None;
+        }
+        me.buf.truncate(read);
+        Poll::Ready(Some(Ok(Bytes::from(std::mem::take(&mut me.buf)))))
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.remaining == 0
+    }
+
+    fn size_hint(&self) -> (u64, Option<u64>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
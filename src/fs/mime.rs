@@ -0,0 +1,58 @@
+use std::path::Path;
+
+use crate::headers::HeaderValue;
+
+const OCTET_STREAM: &[u8] = b"application/octet-stream";
+
+/// Guess the `Content-Type` from the file extension.
+///
+/// Returns `application/octet-stream` if the extension is unknown.
+pub fn guess(path: &Path) -> HeaderValue {
+    let Some(ext) = path.extension().and_then(|ext| ext.to_str()) else {
+        return HeaderValue::from_static(OCTET_STREAM);
+    };
+    let mime: &'static [u8] = match ext.to_ascii_lowercase().as_str() {
+        // text
+        "html" | "htm" => b"text/html; charset=utf-8",
+        "css" => b"text/css; charset=utf-8",
+        "js" | "mjs" => b"text/javascript; charset=utf-8",
+        "txt" => b"text/plain; charset=utf-8",
+        "csv" => b"text/csv; charset=utf-8",
+        "md" => b"text/markdown; charset=utf-8",
+        "xml" => b"text/xml; charset=utf-8",
+
+        // application
+        "json" | "map" => b"application/json",
+        "wasm" => b"application/wasm",
+        "pdf" => b"application/pdf",
+        "zip" => b"application/zip",
+        "gz" => b"application/gzip",
+        "tar" => b"application/x-tar",
+        "webmanifest" => b"application/manifest+json",
+
+        // image
+        "png" => b"image/png",
+        "jpg" | "jpeg" => b"image/jpeg",
+        "gif" => b"image/gif",
+        "webp" => b"image/webp",
+        "avif" => b"image/avif",
+        "svg" => b"image/svg+xml",
+        "ico" => b"image/vnd.microsoft.icon",
+
+        // font
+        "woff" => b"font/woff",
+        "woff2" => b"font/woff2",
+        "ttf" => b"font/ttf",
+        "otf" => b"font/otf",
+
+        // audio and video
+        "mp3" => b"audio/mpeg",
+        "ogg" => b"audio/ogg",
+        "wav" => b"audio/wav",
+        "mp4" => b"video/mp4",
+        "webm" => b"video/webm",
+
+        _ => OCTET_STREAM,
+    };
+    HeaderValue::from_static(mime)
+}
@@ -0,0 +1,22 @@
+//! Static file serving.
+//!
+//! - [`ServeDir`] serve files under a directory
+//! - [`ServeFile`] serve a single file
+//! - [`FileBody`] file contents streamed with tokio file
+//!
+//! Both services support:
+//!
+//! - `GET` and `HEAD` request, other methods are responded with `405 Method Not Allowed`
+//! - `Content-Type` guessed from the file extension
+//! - `Last-Modified` and `ETag`, with `If-None-Match` and `If-Modified-Since` conditional
+//!   request, responded with `304 Not Modified`
+//! - single `Range` request, responded with `206 Partial Content`, and `If-Range`
+//! - optional precompressed `.br` and `.gz` sibling files, selected by `Accept-Encoding`
+mod path;
+mod mime;
+mod body;
+mod serve;
+mod service;
+
+pub use body::FileBody;
+pub use service::{ServeDir, ServeFile};
@@ -0,0 +1,52 @@
+use std::path::{Component, Path, PathBuf};
+
+use crate::http::percent_decode;
+
+/// Join the request `path` onto `root`.
+///
+/// The path is percent-decoded, and each segment must be a normal path component, thus `..`,
+/// absolute path, and platform specific prefix cannot escape the root.
+///
+/// Returns `None` if the path is invalid.
+pub fn resolve(root: &Path, path: &str) -> Option<PathBuf> {
+    let mut resolved = root.to_path_buf();
+    for segment in path.split('/') {
+        let segment = percent_decode(segment, false)?;
+        if segment.is_empty() || segment == "." {
+            continue;
+        }
+        if segment.contains(['/', '\\', '\0']) {
+            return None;
+        }
+        let mut components = Path::new(&segment).components();
+        match (components.next(), components.next()) {
+            (Some(Component::Normal(_)), None) => resolved.push(segment),
+            _ => return None,
+        }
+    }
+    Some(resolved)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_resolve() {
+        let root = Path::new("/srv");
+        assert_eq!(resolve(root, "/"), Some(PathBuf::from("/srv")));
+        assert_eq!(resolve(root, "/a/b.txt"), Some(PathBuf::from("/srv/a/b.txt")));
+        assert_eq!(resolve(root, "//a/./b.txt"), Some(PathBuf::from("/srv/a/b.txt")));
+        assert_eq!(resolve(root, "/hello%20world"), Some(PathBuf::from("/srv/hello world")));
+        assert_eq!(resolve(root, "/a+b"), Some(PathBuf::from("/srv/a+b")));
+
+        assert_eq!(resolve(root, "/.."), None);
+        assert_eq!(resolve(root, "/a/../../etc/passwd"), None);
+        assert_eq!(resolve(root, "/%2e%2e/etc/passwd"), None);
+        assert_eq!(resolve(root, "/a%2f..%2f..%2fetc"), None);
+        assert_eq!(resolve(root, "/a%5c..%5cetc"), None);
+        assert_eq!(resolve(root, "/a%00"), None);
+        assert_eq!(resolve(root, "/%zz"), None);
+        assert_eq!(resolve(root, "/%FF"), None);
+    }
+}
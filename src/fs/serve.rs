@@ -0,0 +1,342 @@
+use std::fs::Metadata;
+use std::io::{self, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::fs::File;
+use tokio::io::AsyncSeekExt;
+
+use super::{FileBody, mime};
+use crate::headers::{HeaderMap, HeaderValue, standard};
+use crate::http::{Method, Response, StatusCode, httpdate, parse_httpdate};
+
+/// Precompressed siblings to look for.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Precompressed {
+    pub br: bool,
+    pub gzip: bool,
+}
+
+impl Precompressed {
+    fn is_enabled(&self) -> bool {
+        self.br || self.gzip
+    }
+}
+
+/// Request parts required to serve a file.
+#[derive(Debug)]
+pub struct FileRequest {
+    pub method: Method,
+    pub headers: HeaderMap,
+}
+
+/// Serve the file at `path`.
+pub async fn serve(
+    path: PathBuf,
+    request: FileRequest,
+    precompressed: Precompressed,
+) -> io::Result<Response<FileBody>> {
+    let opened = open_precompressed(&path, &request, precompressed).await;
+    let (file, metadata, encoding) = match opened {
+        Some(ok) => ok,
+        None => {
+            let file = match File::open(&path).await {
+                Ok(ok) => ok,
+                Err(err) => return error_response(err),
+            };
+            let metadata = file.metadata().await?;
+            (file, metadata, None)
+        }
+    };
+    if !metadata.is_file() {
+        return Ok(status(StatusCode::NOT_FOUND));
+    }
+
+    let len = metadata.len();
+    let mtime = metadata.modified().ok().and_then(|time| time.duration_since(UNIX_EPOCH).ok());
+    let modified = mtime.map(|mtime| mtime.as_secs());
+    let etag = mtime.map(|mtime| etag(mtime, len));
+
+    let mut headers = HeaderMap::new();
+    if let Some(modified) = modified {
+        let date = httpdate(UNIX_EPOCH + Duration::from_secs(modified));
+        headers.insert(standard::LAST_MODIFIED, header_value(&date[..]));
+    }
+    if let Some(etag) = &etag {
+        headers.insert(standard::ETAG, header_value(etag));
+    }
+    if precompressed.is_enabled() {
+        headers.insert(standard::VARY, HeaderValue::from_static(b"accept-encoding"));
+    }
+
+    if is_not_modified(&request.headers, etag.as_deref(), modified) {
+        let mut response = status(StatusCode::NOT_MODIFIED);
+        *response.headers_mut() = headers;
+        return Ok(response);
+    }
+
+    headers.insert(standard::CONTENT_TYPE, mime::guess(&path));
+    headers.insert(standard::ACCEPT_RANGES, HeaderValue::from_static(b"bytes"));
+    if let Some(encoding) = encoding {
+        headers.insert(standard::CONTENT_ENCODING, HeaderValue::from_static(encoding.as_bytes()));
+    }
+
+    let range = match request.method {
+        Method::GET if is_range_fresh(&request.headers, etag.as_deref(), modified) => request
+            .headers
+            .get(standard::RANGE)
+            .map_or(Range::Full, |value| Range::parse(value.as_bytes(), len)),
+        _ => Range::Full,
+    };
+
+    let mut response = match range {
+        Range::Full => Response::from_parts(Default::default(), FileBody::new(file, len)),
+        Range::Partial(start, end) => {
+            let mut file = file;
+            file.seek(SeekFrom::Start(start)).await?;
+            let content_range = format!("bytes {start}-{end}/{len}");
+            headers.insert(standard::CONTENT_RANGE, header_value(content_range));
+
+            let mut response =
+                Response::from_parts(Default::default(), FileBody::new(file, end - start + 1));
+            *response.status_mut() = StatusCode::PARTIAL_CONTENT;
+            response
+        }
+        Range::Unsatisfiable => {
+            headers.insert(standard::CONTENT_RANGE, header_value(format!("bytes */{len}")));
+            status(StatusCode::RANGE_NOT_SATISFIABLE)
+        }
+    };
+    *response.headers_mut() = headers;
+    Ok(response)
+}
+
+/// Open the precompressed sibling of `path` accepted by the client, if any.
+async fn open_precompressed(
+    path: &Path,
+    request: &FileRequest,
+    precompressed: Precompressed,
+) -> Option<(File, Metadata, Option<&'static str>)> {
+    let candidates = [("br", ".br", precompressed.br), ("gzip", ".gz", precompressed.gzip)];
+    for (encoding, ext, enabled) in candidates {
+        if !enabled || !accepts_encoding(&request.headers, encoding) {
+            continue;
+        }
+        let mut sibling = path.as_os_str().to_owned();
+        sibling.push(ext);
+        let Ok(file) = File::open(&sibling).await else {
+            continue;
+        };
+        match file.metadata().await {
+            Ok(metadata) if metadata.is_file() => return Some((file, metadata, Some(encoding))),
+            _ => continue,
+        }
+    }
+    None
+}
+
+// ===== Conditional Requests =====
+
+/// <https://www.rfc-editor.org/rfc/rfc9110.html#section-13.2.2>
+fn is_not_modified(headers: &HeaderMap, etag: Option<&str>, modified: Option<u64>) -> bool {
+    let name = standard::IF_NONE_MATCH;
+    let mut if_none_match = headers.get_all(&name).peekable();
+    if if_none_match.peek().is_some() {
+        let Some(etag) = etag else {
+            return false;
+        };
+        return if_none_match.any(|value| {
+            let value = value.as_str().trim();
+            value == "*" || value.split(',').any(|tag| weak_eq(tag.trim(), etag))
+        });
+    }
+
+    let since = headers
+        .get(standard::IF_MODIFIED_SINCE)
+        .and_then(|value| parse_httpdate(value.as_bytes()))
+        .and_then(unix_secs);
+    matches!((modified, since), (Some(modified), Some(since)) if modified <= since)
+}
+
+/// Returns `true` if the `Range` should be applied.
+///
+/// <https://www.rfc-editor.org/rfc/rfc9110.html#section-13.1.5>
+fn is_range_fresh(headers: &HeaderMap, etag: Option<&str>, modified: Option<u64>) -> bool {
+    let Some(if_range) = headers.get(standard::IF_RANGE) else {
+        return true;
+    };
+    let value = if_range.as_str().trim();
+    if value.starts_with('"') {
+        // strong comparison
+        return etag == Some(value);
+    }
+    if value.starts_with("W/") {
+        return false;
+    }
+    let date = parse_httpdate(value.as_bytes()).and_then(unix_secs);
+    matches!((modified, date), (Some(modified), Some(date)) if modified == date)
+}
+
+fn weak_eq(a: &str, b: &str) -> bool {
+    a.trim_start_matches("W/") == b.trim_start_matches("W/")
+}
+
+/// Returns `true` if the client accepts the content `coding`.
+fn accepts_encoding(headers: &HeaderMap, coding: &str) -> bool {
+    headers.get_all(&standard::ACCEPT_ENCODING).any(|value| {
+        value.as_str().split(',').any(|item| {
+            let mut params = item.split(';');
+            let name = params.next().unwrap_or_default().trim();
+            if !name.eq_ignore_ascii_case(coding) {
+                return false;
+            }
+            // `q=0` means not acceptable
+            !params.any(|param| {
+                let param = param.trim();
+                param.len() > 2
+                    && param[..2].eq_ignore_ascii_case("q=")
+                    && param[2..].parse::<f32>().is_ok_and(|q| q == 0.0)
+            })
+        })
+    })
+}
+
+// ===== Range =====
+
+#[derive(Debug, PartialEq)]
+enum Range {
+    /// Range is absent or ignored.
+    Full,
+    /// Inclusive byte range.
+    Partial(u64, u64),
+    Unsatisfiable,
+}
+
+impl Range {
+    /// Parse single byte range, multiple ranges and invalid syntax are ignored.
+    ///
+    /// <https://www.rfc-editor.org/rfc/rfc9110.html#section-14.1.2>
+    fn parse(value: &[u8], len: u64) -> Self {
+        let Ok(value) = str::from_utf8(value) else {
+            return Range::Full;
+        };
+        let Some((unit, range)) = value.trim().split_once('=') else {
+            return Range::Full;
+        };
+        if !unit.eq_ignore_ascii_case("bytes") || range.contains(',') {
+            return Range::Full;
+        }
+        let Some((start, end)) = range.trim().split_once('-') else {
+            return Range::Full;
+        };
+
+        let parse = |n: &str| match n.bytes().all(|b| b.is_ascii_digit()) {
+            true => n.parse::<u64>().ok(),
+            false => None,
+        };
+        match (start, end) {
+            ("", "") => Range::Full,
+            // suffix range
+            ("", suffix) => match parse(suffix) {
+                Some(0) => Range::Unsatisfiable,
+                Some(_) if len == 0 => Range::Unsatisfiable,
+                Some(suffix) => Range::Partial(len.saturating_sub(suffix), len - 1),
+                None => Range::Full,
+            },
+            (start, "") => match parse(start) {
+                Some(start) if start >= len => Range::Unsatisfiable,
+                Some(start) => Range::Partial(start, len - 1),
+                None => Range::Full,
+            },
+            (start, end) => match (parse(start), parse(end)) {
+                (Some(start), Some(end)) if start > end => Range::Full,
+                (Some(start), Some(_)) if start >= len => Range::Unsatisfiable,
+                (Some(start), Some(end)) => Range::Partial(start, end.min(len - 1)),
+                _ => Range::Full,
+            },
+        }
+    }
+}
+
+// ===== Response =====
+
+pub fn status(status: StatusCode) -> Response<FileBody> {
+    let mut response = Response::from_parts(Default::default(), FileBody::empty());
+    *response.status_mut() = status;
+    response
+}
+
+/// `405 Method Not Allowed` response.
+pub fn method_not_allowed() -> Response<FileBody> {
+    let mut response = status(StatusCode::METHOD_NOT_ALLOWED);
+    response.headers_mut().insert(standard::ALLOW, HeaderValue::from_static(b"GET, HEAD"));
+    response
+}
+
+/// Map file opening error into response.
+pub fn error_response(err: io::Error) -> io::Result<Response<FileBody>> {
+    match err.kind() {
+        io::ErrorKind::NotFound | io::ErrorKind::NotADirectory => {
+            Ok(status(StatusCode::NOT_FOUND))
+        }
+        io::ErrorKind::PermissionDenied => Ok(status(StatusCode::FORBIDDEN)),
+        _ => Err(err),
+    }
+}
+
+/// Strong validator from modification time and length.
+///
+/// Sub-second precision is included, so a same-length rewrite within the same second still
+/// changes the tag.
+fn etag(mtime: Duration, len: u64) -> String {
+    format!("\"{:x}.{:x}-{len:x}\"", mtime.as_secs(), mtime.subsec_nanos())
+}
+
+fn header_value<A: AsRef<[u8]>>(value: A) -> HeaderValue {
+    HeaderValue::from_slice(value).expect("generated header value is valid")
+}
+
+fn unix_secs(time: SystemTime) -> Option<u64> {
+    time.duration_since(UNIX_EPOCH).ok().map(|duration| duration.as_secs())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_range() {
+        assert_eq!(Range::parse(b"bytes=0-499", 1000), Range::Partial(0, 499));
+        assert_eq!(Range::parse(b"bytes=500-", 1000), Range::Partial(500, 999));
+        assert_eq!(Range::parse(b"bytes=-200", 1000), Range::Partial(800, 999));
+        assert_eq!(Range::parse(b"bytes=-2000", 1000), Range::Partial(0, 999));
+        assert_eq!(Range::parse(b"bytes=900-2000", 1000), Range::Partial(900, 999));
+        assert_eq!(Range::parse(b"Bytes=0-0", 1000), Range::Partial(0, 0));
+
+        assert_eq!(Range::parse(b"bytes=1000-", 1000), Range::Unsatisfiable);
+        assert_eq!(Range::parse(b"bytes=-0", 1000), Range::Unsatisfiable);
+        assert_eq!(Range::parse(b"bytes=0-", 0), Range::Unsatisfiable);
+
+        assert_eq!(Range::parse(b"bytes=0-1,5-6", 1000), Range::Full);
+        assert_eq!(Range::parse(b"bytes=5-1", 1000), Range::Full);
+        assert_eq!(Range::parse(b"bytes=+1-5", 1000), Range::Full);
+        assert_eq!(Range::parse(b"items=0-1", 1000), Range::Full);
+    }
+
+    #[test]
+    fn test_etag() {
+        let mtime = Duration::new(0x10, 0x20);
+        assert_eq!(etag(mtime, 0x30), "\"10.20-30\"");
+        assert_ne!(etag(mtime, 0x30), etag(Duration::new(0x10, 0x21), 0x30));
+    }
+
+    #[test]
+    fn test_accepts_encoding() {
+        let mut headers = HeaderMap::new();
+        assert!(!accepts_encoding(&headers, "gzip"));
+
+        let value = HeaderValue::from_static(b"deflate, GZIP;q=0.5, br;q=0");
+        headers.insert(standard::ACCEPT_ENCODING, value);
+        assert!(accepts_encoding(&headers, "gzip"));
+        assert!(!accepts_encoding(&headers, "br"));
+    }
+}
@@ -0,0 +1,391 @@
+use std::io;
+use std::path::PathBuf;
+
+use super::FileBody;
+use super::path::resolve;
+use super::serve::{FileRequest, Precompressed, method_not_allowed, serve, status};
+use crate::headers::{HeaderValue, standard};
+use crate::http::{Method, Request, Response, StatusCode};
+use crate::service::{BoxFuture, Service};
+
+// ===== ServeDir =====
+
+/// Serve files under a root directory.
+///
+/// The request path is percent-decoded and joined onto the root. Path that attempts to escape
+/// the root, such as containing `..` segment, is responded with `404 Not Found`. Symbolic links
+/// inside the root are followed.
+///
+/// When the path is a directory, `index.html` inside it is served. If the request path does not
+/// end with `/`, the client is redirected to the normalized path with trailing `/` instead, so
+/// relative links in the index resolve correctly.
+///
+/// See the [module level documentation][super] for supported features.
+///
+/// # Examples
+///
+/// ```
+/// use tsue::fs::ServeDir;
+///
+/// let service = ServeDir::new("assets")
+///     .precompressed_br(true)
+///     .precompressed_gzip(true);
+/// ```
+#[derive(Debug, Clone)]
+pub struct ServeDir {
+    root: PathBuf,
+    precompressed: Precompressed,
+}
+
+impl ServeDir {
+    /// Create new [`ServeDir`] that serve files under `root`.
+    #[inline]
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        Self {
+            root: root.into(),
+            precompressed: Precompressed::default(),
+        }
+    }
+
+    /// Serve the `.br` sibling file if it exists and the client accepts it, default to `false`.
+    #[inline]
+    pub fn precompressed_br(mut self, enabled: bool) -> Self {
+        self.precompressed.br = enabled;
+        self
+    }
+
+    /// Serve the `.gz` sibling file if it exists and the client accepts it, default to `false`.
+    ///
+    /// If brotli is also enabled, it is preferred over gzip.
+    #[inline]
+    pub fn precompressed_gzip(mut self, enabled: bool) -> Self {
+        self.precompressed.gzip = enabled;
+        self
+    }
+}
+
+impl<B> Service<Request<B>> for ServeDir {
+    type Response = Response<FileBody>;
+
+    type Error = io::Error;
+
+    type Future = BoxFuture<Response<FileBody>, io::Error>;
+
+    fn call(&self, request: Request<B>) -> Self::Future {
+        let (parts, _) = request.into_parts();
+        if !matches!(parts.method, Method::GET | Method::HEAD) {
+            return Box::pin(std::future::ready(Ok(method_not_allowed())));
+        }
+
+        let request_path = parts.target.path();
+        let Some(path) = resolve(&self.root, request_path) else {
+            return Box::pin(std::future::ready(Ok(status(StatusCode::NOT_FOUND))));
+        };
+        let location = match request_path.ends_with('/') {
+            true => None,
+            false => Some(redirect_location(request_path, parts.target.query())),
+        };
+
+        let request = FileRequest {
+            method: parts.method,
+            headers: parts.headers,
+        };
+        let precompressed = self.precompressed;
+
+        Box::pin(async move {
+            let is_dir = tokio::fs::metadata(&path)
+                .await
+                .is_ok_and(|metadata| metadata.is_dir());
+            if !is_dir {
+                return serve(path, request, precompressed).await;
+            }
+            match location.map(HeaderValue::from_slice) {
+                Some(Ok(location)) => {
+                    let mut response = status(StatusCode::MOVED_PERMANENTLY);
+                    response.headers_mut().insert(standard::LOCATION, location);
+                    Ok(response)
+                }
+                Some(Err(_)) => Ok(status(StatusCode::NOT_FOUND)),
+                None => serve(path.join("index.html"), request, precompressed).await,
+            }
+        })
+    }
+}
+
+/// Normalized `path` with trailing `/`.
+///
+/// Empty and `.` segments are collapsed, so path like `//example.com` cannot redirect to another
+/// host.
+fn redirect_location(path: &str, query: Option<&str>) -> String {
+    let mut location = String::with_capacity(path.len() + 1);
+    for segment in path.split('/').filter(|segment| !segment.is_empty() && *segment != ".") {
+        location.push('/');
+        location.push_str(segment);
+    }
+    location.push('/');
+    if let Some(query) = query {
+        location.push('?');
+        location.push_str(query);
+    }
+    location
+}
+
+// ===== ServeFile =====
+
+/// Serve a single file for every request.
+///
+/// See the [module level documentation][super] for supported features.
+///
+/// # Examples
+///
+/// ```
+/// use tsue::fs::ServeFile;
+///
+/// let service = ServeFile::new("assets/favicon.ico");
+/// ```
+#[derive(Debug, Clone)]
+pub struct ServeFile {
+    path: PathBuf,
+    precompressed: Precompressed,
+}
+
+impl ServeFile {
+    /// Create new [`ServeFile`] that serve the file at `path`.
+    #[inline]
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        Self {
+            path: path.into(),
+            precompressed: Precompressed::default(),
+        }
+    }
+
+    /// Serve the `.br` sibling file if it exists and the client accepts it, default to `false`.
+    #[inline]
+    pub fn precompressed_br(mut self, enabled: bool) -> Self {
+        self.precompressed.br = enabled;
+        self
+    }
+
+    /// Serve the `.gz` sibling file if it exists and the client accepts it, default to `false`.
+    ///
+    /// If brotli is also enabled, it is preferred over gzip.
+    #[inline]
+    pub fn precompressed_gzip(mut self, enabled: bool) -> Self {
+        self.precompressed.gzip = enabled;
+        self
+    }
+}
+
+impl<B> Service<Request<B>> for ServeFile {
+    type Response = Response<FileBody>;
+
+    type Error = io::Error;
+
+    type Future = BoxFuture<Response<FileBody>, io::Error>;
+
+    fn call(&self, request: Request<B>) -> Self::Future {
+        let (parts, _) = request.into_parts();
+        if !matches!(parts.method, Method::GET | Method::HEAD) {
+            return Box::pin(std::future::ready(Ok(method_not_allowed())));
+        }
+
+        let request = FileRequest {
+            method: parts.method,
+            headers: parts.headers,
+        };
+        Box::pin(serve(self.path.clone(), request, self.precompressed))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use tcio::bytes::Bytes;
+
+    use super::*;
+    use crate::body::Body;
+    use crate::http::{Target, request};
+    use crate::testing::block_on;
+
+    /// Temporary directory removed on drop.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("tsue-{name}-{}", std::process::id()));
+            let _ = std::fs::remove_dir_all(&path);
+            std::fs::create_dir_all(path.join("docs")).unwrap();
+            std::fs::write(path.join("hello.txt"), "hello world").unwrap();
+            std::fs::write(path.join("app.js"), "console.log(1)").unwrap();
+            std::fs::write(path.join("app.js.gz"), "gzipped").unwrap();
+            std::fs::write(path.join("app.js.br"), "brotli").unwrap();
+            std::fs::write(path.join("docs/index.html"), "<h1>docs</h1>").unwrap();
+            Self(path)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn request(
+        method: Method,
+        path: &'static str,
+        headers: &[(&'static str, &str)],
+    ) -> Request<()> {
+        let mut parts = request::Parts {
+            method,
+            target: Target::from_static(path.as_bytes()),
+            ..Default::default()
+        };
+        for (name, value) in headers {
+            let name = crate::headers::HeaderName::from_static(name.as_bytes());
+            parts.headers.append(name, HeaderValue::from_slice(value).unwrap());
+        }
+        Request::from_parts(parts, ())
+    }
+
+    fn call<S>(service: &S, request: Request<()>) -> (Response<FileBody>, Vec<u8>)
+    where
+        S: Service<Request<()>, Response = Response<FileBody>, Error = io::Error>,
+    {
+        block_on(async {
+            let mut response = service.call(request).await.unwrap();
+            let mut data = Vec::new();
+            let mut body = std::pin::Pin::new(response.body_mut());
+            while let Some(chunk) = std::future::poll_fn(|cx| body.as_mut().poll_data(cx)).await {
+                let chunk: Bytes = chunk.unwrap();
+                data.extend_from_slice(&chunk);
+            }
+            (response, data)
+        })
+    }
+
+    fn header<'a>(response: &'a Response<FileBody>, name: &'static str) -> Option<&'a str> {
+        let name = crate::headers::HeaderName::from_static(name.as_bytes());
+        response.headers().get(&name).map(HeaderValue::as_str)
+    }
+
+    #[test]
+    fn test_serve_dir() {
+        let dir = TempDir::new("serve-dir");
+        let service = ServeDir::new(&dir.0);
+
+        let (response, body) = call(&service, request(Method::GET, "/hello.txt", &[]));
+        assert_eq!(response.status(), &StatusCode::OK);
+        assert_eq!(body, b"hello world");
+        assert_eq!(header(&response, "content-type"), Some("text/plain; charset=utf-8"));
+        assert_eq!(header(&response, "accept-ranges"), Some("bytes"));
+        assert!(header(&response, "last-modified").is_some());
+        let etag = header(&response, "etag").unwrap().to_owned();
+
+        let (response, _) = call(&service, request(Method::HEAD, "/hello.txt", &[]));
+        assert_eq!(response.status(), &StatusCode::OK);
+        assert_eq!(header(&response, "content-type"), Some("text/plain; charset=utf-8"));
+
+        let (response, _) = call(&service, request(Method::GET, "/missing.txt", &[]));
+        assert_eq!(response.status(), &StatusCode::NOT_FOUND);
+
+        let (response, _) = call(&service, request(Method::GET, "/../hello.txt", &[]));
+        assert_eq!(response.status(), &StatusCode::NOT_FOUND);
+
+        let (response, _) = call(&service, request(Method::GET, "/%2e%2e/hello.txt", &[]));
+        assert_eq!(response.status(), &StatusCode::NOT_FOUND);
+
+        let (response, _) = call(&service, request(Method::POST, "/hello.txt", &[]));
+        assert_eq!(response.status(), &StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(header(&response, "allow"), Some("GET, HEAD"));
+
+        // directory
+        let (response, body) = call(&service, request(Method::GET, "/docs/", &[]));
+        assert_eq!(response.status(), &StatusCode::OK);
+        assert_eq!(body, b"<h1>docs</h1>");
+        assert_eq!(header(&response, "content-type"), Some("text/html; charset=utf-8"));
+
+        let (response, _) = call(&service, request(Method::GET, "/docs?a=1", &[]));
+        assert_eq!(response.status(), &StatusCode::MOVED_PERMANENTLY);
+        assert_eq!(header(&response, "location"), Some("/docs/?a=1"));
+
+        let (response, _) = call(&service, request(Method::GET, "//docs", &[]));
+        assert_eq!(response.status(), &StatusCode::MOVED_PERMANENTLY);
+        assert_eq!(header(&response, "location"), Some("/docs/"));
+
+        let (response, _) = call(&service, request(Method::GET, "/./docs", &[]));
+        assert_eq!(header(&response, "location"), Some("/docs/"));
+
+        // conditional
+        let headers = [("if-none-match", etag.as_str())];
+        let (response, body) = call(&service, request(Method::GET, "/hello.txt", &headers));
+        assert_eq!(response.status(), &StatusCode::NOT_MODIFIED);
+        assert!(body.is_empty());
+        assert_eq!(header(&response, "etag"), Some(etag.as_str()));
+
+        const FUTURE: &str = "Fri, 01 Jan 9999 00:00:00 GMT";
+        let headers = [("if-none-match", "\"other\""), ("if-modified-since", FUTURE)];
+        let (response, _) = call(&service, request(Method::GET, "/hello.txt", &headers));
+        assert_eq!(response.status(), &StatusCode::OK);
+
+        let headers = [("if-modified-since", FUTURE)];
+        let (response, _) = call(&service, request(Method::GET, "/hello.txt", &headers));
+        assert_eq!(response.status(), &StatusCode::NOT_MODIFIED);
+
+        let headers = [("if-modified-since", "Thu, 01 Jan 1970 00:00:00 GMT")];
+        let (response, _) = call(&service, request(Method::GET, "/hello.txt", &headers));
+        assert_eq!(response.status(), &StatusCode::OK);
+    }
+
+    #[test]
+    fn test_serve_range() {
+        let dir = TempDir::new("serve-range");
+        let service = ServeFile::new(dir.0.join("hello.txt"));
+
+        let headers = [("range", "bytes=6-")];
+        let (response, body) = call(&service, request(Method::GET, "/", &headers));
+        assert_eq!(response.status(), &StatusCode::PARTIAL_CONTENT);
+        assert_eq!(body, b"world");
+        assert_eq!(header(&response, "content-range"), Some("bytes 6-10/11"));
+
+        let headers = [("range", "bytes=-5")];
+        let (response, body) = call(&service, request(Method::GET, "/", &headers));
+        assert_eq!(response.status(), &StatusCode::PARTIAL_CONTENT);
+        assert_eq!(body, b"world");
+
+        let headers = [("range", "bytes=20-")];
+        let (response, _) = call(&service, request(Method::GET, "/", &headers));
+        assert_eq!(response.status(), &StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(header(&response, "content-range"), Some("bytes */11"));
+
+        let headers = [("range", "bytes=0-4"), ("if-range", "\"stale\"")];
+        let (response, body) = call(&service, request(Method::GET, "/", &headers));
+        assert_eq!(response.status(), &StatusCode::OK);
+        assert_eq!(body, b"hello world");
+    }
+
+    #[test]
+    fn test_serve_precompressed() {
+        let dir = TempDir::new("serve-precompressed");
+        let service = ServeDir::new(&dir.0).precompressed_gzip(true);
+
+        let headers = [("accept-encoding", "gzip, br")];
+        let (response, body) = call(&service, request(Method::GET, "/app.js", &headers));
+        assert_eq!(body, b"gzipped");
+        assert_eq!(header(&response, "content-encoding"), Some("gzip"));
+        assert_eq!(header(&response, "content-type"), Some("text/javascript; charset=utf-8"));
+        assert_eq!(header(&response, "vary"), Some("accept-encoding"));
+
+        let service = service.precompressed_br(true);
+        let (response, body) = call(&service, request(Method::GET, "/app.js", &headers));
+        assert_eq!(body, b"brotli");
+        assert_eq!(header(&response, "content-encoding"), Some("br"));
+
+        let (response, body) = call(&service, request(Method::GET, "/app.js", &[]));
+        assert_eq!(body, b"console.log(1)");
+        assert_eq!(header(&response, "content-encoding"), None);
+
+        // no precompressed sibling
+        let (response, body) = call(&service, request(Method::GET, "/hello.txt", &headers));
+        assert_eq!(body, b"hello world");
+        assert_eq!(header(&response, "content-encoding"), None);
+    }
+}
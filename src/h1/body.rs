@@ -40,6 +40,11 @@ impl BodyDecoder {
         };
         Self { kind }
     }
+
+    /// Returns `true` if the message body is known to be empty.
+    pub fn is_empty(&self) -> bool {
+        matches!(self.kind, DecoderKind::Length(0))
+    }
 }
 
 impl BodyDecode for BodyDecoder {
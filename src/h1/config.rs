@@ -0,0 +1,117 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::rt::{Timer, TokioTimer};
+use crate::service::{ErrorHandler, InternalError, SharedErrorHandler};
+
+/// HTTP/1.1 connection configuration.
+///
+/// By default, there is no timeout and `h2c` upgrade is disabled.
+#[derive(Clone)]
+pub struct Config {
+    pub(crate) header_read_timeout: Option<Duration>,
+    pub(crate) keep_alive_timeout: Option<Duration>,
+    pub(crate) body_read_timeout: Option<Duration>,
+    pub(crate) write_timeout: Option<Duration>,
+    pub(crate) h2c_upgrade: bool,
+    pub(crate) timer: Arc<dyn Timer + Send + Sync>,
+    pub(crate) error_handler: SharedErrorHandler,
+}
+
+impl Default for Config {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Config {
+    /// Create new default [`Config`].
+    pub fn new() -> Self {
+        Self {
+            header_read_timeout: None,
+            keep_alive_timeout: None,
+            body_read_timeout: None,
+            write_timeout: None,
+            h2c_upgrade: false,
+            timer: Arc::new(TokioTimer),
+            error_handler: InternalError::shared(),
+        }
+    }
+
+    /// Set the maximum duration to receive the full request head.
+    ///
+    /// On expiry, `408 (Request Timeout)` is written if part of the request is received, then the
+    /// connection is closed.
+    pub fn header_read_timeout(mut self, timeout: Duration) -> Self {
+        self.header_read_timeout = Some(timeout);
+        self
+    }
+
+    /// Set the maximum idle duration between requests of a keep-alive connection.
+    ///
+    /// On expiry, the connection is closed.
+    pub fn keep_alive_timeout(mut self, timeout: Duration) -> Self {
+        self.keep_alive_timeout = Some(timeout);
+        self
+    }
+
+    /// Set the maximum duration of request body read that is not making progress.
+    ///
+    /// On expiry, the request body returns [`TimedOut`][std::io::ErrorKind::TimedOut] error, and
+    /// the connection is closed after the response is written.
+    pub fn body_read_timeout(mut self, timeout: Duration) -> Self {
+        self.body_read_timeout = Some(timeout);
+        self
+    }
+
+    /// Set the maximum duration of response write that is not making progress.
+    ///
+    /// On expiry, the connection is aborted.
+    pub fn write_timeout(mut self, timeout: Duration) -> Self {
+        self.write_timeout = Some(timeout);
+        self
+    }
+
+    /// Enable HTTP/2 upgrade via `Upgrade: h2c` header.
+    ///
+    /// When enabled, a request without message body that asks for `h2c` upgrade is responded with
+    /// `101 (Switching Protocols)`, and the connection continues as HTTP/2.
+    pub fn h2c_upgrade(mut self, enable: bool) -> Self {
+        self.h2c_upgrade = enable;
+        self
+    }
+
+    /// Set the [`Timer`] used for timeouts, default to [`TokioTimer`].
+    pub fn timer<T>(mut self, timer: T) -> Self
+    where
+        T: Timer + Send + Sync + 'static,
+    {
+        self.timer = Arc::new(timer);
+        self
+    }
+
+    /// Set the [`ErrorHandler`] that renders service errors, default to [`InternalError`].
+    ///
+    /// The rendered response is written as usual, thus the connection can be reused.
+    pub fn error_handler<H>(mut self, handler: H) -> Self
+    where
+        H: ErrorHandler + Send + Sync + 'static,
+    {
+        self.error_handler = Arc::new(handler);
+        self
+    }
+}
+
+impl std::fmt::Debug for Config {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Config")
+            .field("header_read_timeout", &self.header_read_timeout)
+            .field("keep_alive_timeout", &self.keep_alive_timeout)
+            .field("body_read_timeout", &self.body_read_timeout)
+            .field("write_timeout", &self.write_timeout)
+            .field("h2c_upgrade", &self.h2c_upgrade)
+            .field("error_handler", &self.error_handler)
+            .finish_non_exhaustive()
+    }
+}
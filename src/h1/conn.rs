@@ -1,15 +1,24 @@
+use std::io;
 use std::mem;
 use std::pin::Pin;
+use std::sync::Arc;
 use std::task::Poll::{self, *};
 use std::task::ready;
 use tcio::bytes::{Buf, BytesMut};
 use tcio::io::{AsyncRead, AsyncWrite};
 
 use crate::body::Body;
+use crate::event::SharedObserver;
+use crate::h2;
 use crate::h1::body::{BodyEncoder, LengthEncoder};
 use crate::h1::chunked::{ChunkedCoder, EncodedChunk};
-use crate::h1::proto::{RequestContext, poll_request};
+use crate::h1::config::Config;
+use crate::h1::proto::{
+    RequestContext, h2c_upgrade, poll_request, write_request_timeout, write_switching_protocols,
+};
 use crate::h1::states::Session;
+use crate::h1::timeout::Deadline;
+use crate::http::ConnectionInfo;
 use crate::http::error::UserError;
 use crate::service::HttpService;
 
@@ -17,6 +26,22 @@ type BoxError = Box<dyn std::error::Error + Send + Sync>;
 
 const DEFAULT_BUFFER_CAP: usize = 1024;
 
+/// Poll a write to `io`, abort the connection if it is pending longer than the write timeout.
+macro_rules! poll_write {
+    ($deadline:ident, $config:ident, $cx:ident, $poll:expr) => {
+        match $poll? {
+            Ready(ok) => {
+                $deadline.clear();
+                ok
+            }
+            Pending => {
+                ready!($deadline.poll($config.write_timeout, &*$config.timer, $cx));
+                return Ready(Err(io::Error::from(io::ErrorKind::TimedOut).into()));
+            }
+        }
+    };
+}
+
 /// HTTP/1.1 Connection.
 pub struct Connection<S, IO>
 where
@@ -28,12 +53,19 @@ where
     write_buffer: BytesMut,
     service: S,
     io: IO,
+    config: Arc<Config>,
+    deadline: Deadline,
+    /// the service is ready for the next request
+    is_ready: bool,
+    is_shutdown: bool,
 }
 
 enum Phase<S>
 where
     S: HttpService,
 {
+    /// Waiting for subsequent request of keep-alive connection.
+    Idle,
     Request,
     Service(RequestContext, S::Future),
     Response(
@@ -48,8 +80,13 @@ where
         S::ResBody,
         Option<EncodedChunk<<S::ResBody as Body>::Data>>,
     ),
+    /// Write the response rendered from service error.
+    ErrorResponse(RequestContext),
     Drain(RequestContext),
     Complete,
+    /// Write the remaining buffer, then close the connection.
+    Flush,
+    Upgrade(Box<h2::Core<S>>),
 }
 
 impl<S, IO> Connection<S, IO>
@@ -57,13 +94,68 @@ where
     S: HttpService
 {
     pub fn new(service: S, io: IO) -> Self {
+        Self::with_info(service, io, ConnectionInfo::default())
+    }
+
+    /// Create new connection with given [`ConnectionInfo`].
+    ///
+    /// The connection info is available in each [`Request`][crate::http::Request].
+    pub fn with_info(service: S, io: IO, info: ConnectionInfo) -> Self {
+        Self::with_config(service, io, info, Arc::new(Config::default()))
+    }
+
+    /// Create new connection with given [`ConnectionInfo`] and [`Config`].
+    pub fn with_config(service: S, io: IO, info: ConnectionInfo, config: Arc<Config>) -> Self {
+        Self::with_read_buffer(
+            service,
+            io,
+            info,
+            config,
+            BytesMut::with_capacity(DEFAULT_BUFFER_CAP),
+        )
+    }
+
+    /// Create new connection with bytes that already read from `io`.
+    pub(crate) fn with_read_buffer(
+        service: S,
+        io: IO,
+        info: ConnectionInfo,
+        config: Arc<Config>,
+        read_buffer: BytesMut,
+    ) -> Self {
         Self {
             phase: Phase::Request,
-            session: Session::new(),
-            read_buffer: BytesMut::with_capacity(DEFAULT_BUFFER_CAP),
+            session: Session::new(info),
+            read_buffer,
             write_buffer: BytesMut::with_capacity(DEFAULT_BUFFER_CAP),
             service,
             io,
+            config,
+            deadline: Deadline::new(),
+            is_ready: false,
+            is_shutdown: false,
+        }
+    }
+
+    /// Set the [`Observer`][crate::event::Observer] of connection events.
+    pub fn with_observer(mut self, observer: SharedObserver) -> Self {
+        self.session.observer = observer;
+        self
+    }
+
+    /// Start a graceful shutdown.
+    ///
+    /// The connection will be closed after current response is written, the response contains
+    /// `Connection: close` if its head is not written yet. If there is no request in progress, the
+    /// connection is closed immediately.
+    pub fn graceful_shutdown(self: Pin<&mut Self>) {
+        // SAFETY: no pinned field is moved
+        let me = unsafe { self.get_unchecked_mut() };
+        me.is_shutdown = true;
+        match me.phase {
+            Phase::Idle | Phase::Request if me.read_buffer.is_empty() => me.phase = Phase::Complete,
+            Phase::Upgrade(ref mut core) => core.graceful_shutdown(),
+            _ => {}
         }
     }
 }
@@ -81,49 +173,155 @@ where
             write_buffer,
             service,
             io,
+            config,
+            deadline,
+            is_ready,
+            is_shutdown,
         } = unsafe { self.get_unchecked_mut() };
         // SAFETY: self is pinned
         let mut io = unsafe { Pin::new_unchecked(io) };
 
         loop {
             match phase {
+                Phase::Idle => {
+                    if read_buffer.is_empty() {
+                        // backpressure, the next request is not read until the service is ready
+                        let read = match poll_service_ready(service, is_ready, cx) {
+                            Ready(()) => poll_read(io.as_mut(), read_buffer, session, cx)?,
+                            Pending => Pending,
+                        };
+                        match read {
+                            Ready(0) => return Ready(Ok(())),
+                            Ready(_) => {}
+                            Pending => {
+                                ready!(deadline.poll(config.keep_alive_timeout, &*config.timer, cx));
+                                return Ready(Ok(()));
+                            }
+                        }
+                    }
+                    deadline.clear();
+                    *phase = Phase::Request;
+                }
                 Phase::Request => {
-                    let Ready((parts, mut context)) = poll_request(session, &mut *read_buffer)? else {
-                        let read = ready!(io.as_mut().poll_read(&mut *read_buffer, cx)?);
-                        if read == 0 {
-                            return Ready(Ok(()))
+                    // backpressure, the request is not read until the service is ready, while
+                    // the header read timeout still applies
+                    let parsed = match poll_service_ready(service, is_ready, cx) {
+                        Ready(()) => poll_request(session, &mut *read_buffer),
+                        Pending => Pending,
+                    };
+                    let (parts, mut context) = match parsed {
+                        Ready(Ok(ok)) => ok,
+                        Ready(Err(err)) => {
+                            session.observer.on_protocol_error(&session.info, &err);
+                            return Ready(Err(err.into()));
+                        }
+                        Pending => {
+                            let read = if *is_ready {
+                                poll_read(io.as_mut(), read_buffer, session, cx)?
+                            } else {
+                                Pending
+                            };
+                            match read {
+                                Ready(0) => return Ready(Ok(())),
+                                Ready(_) => {}
+                                Pending => {
+                                    ready!(deadline.poll(config.header_read_timeout, &*config.timer, cx));
+                                    deadline.clear();
+                                    if !read_buffer.is_empty() {
+                                        write_request_timeout(write_buffer);
+                                    }
+                                    *phase = Phase::Flush;
+                                }
+                            }
+                            continue;
                         }
-                        continue;
+                    };
+                    deadline.clear();
+                    // readiness is checked again for the next request
+                    *is_ready = false;
+
+                    let upgrade = if config.h2c_upgrade {
+                        h2c_upgrade(&parts, &context)
+                    } else {
+                        None
                     };
                     let request = context.build_request(parts, session, read_buffer, cx);
+
+                    if let Some(settings) = upgrade {
+                        write_switching_protocols(write_buffer);
+                        let core = h2::Core::upgrade(
+                            session.info.clone(),
+                            session.observer.clone(),
+                            config.error_handler.clone(),
+                            read_buffer.split(),
+                            write_buffer.split(),
+                            &settings,
+                            request,
+                        )?;
+                        *phase = Phase::Upgrade(Box::new(core));
+                        continue;
+                    }
+
+                    // request start of `h2c` upgrade is reported when it is dispatched as stream 1
+                    session.observer.on_request_start(request.parts());
                     *phase = Phase::Service(context, service.call(request));
                 }
                 Phase::Service(context, future) => {
+                    // the response head is not written yet, so it can still announce the close
+                    if *is_shutdown {
+                        session.keep_alive = false;
+                    }
                     // SAFETY: `self` is pinned, thus `self.phase` is also pinned
                     let future = unsafe { Pin::new_unchecked(future) };
                     let response = match future.poll(cx) {
                         Ready(Ok(ok)) => ok,
-                        Ready(Err(err)) => return Ready(Err(err.into())),
+                        Ready(Err(err)) => {
+                            let err: BoxError = err.into();
+                            session.observer.on_service_error(&session.info, &*err);
+                            let response = config.error_handler.render(&*err);
+
+                            let Phase::Service(mut context, _) = mem::replace(phase, Phase::Request) else {
+                                unreachable!()
+                            };
+                            deadline.clear();
+
+                            context.write_buffered_response(response, session, write_buffer);
+                            *phase = Phase::ErrorResponse(context);
+                            continue;
+                        }
                         Pending => {
                             read_buffer.reserve(DEFAULT_BUFFER_CAP);
                             while context.poll_read(session, read_buffer, cx) {
-                                let result = match ready!(io.as_mut().poll_read(&mut *read_buffer, cx)) {
-                                    Ok(0) => Err(std::io::ErrorKind::ConnectionAborted.into()),
-                                    Ok(_) => Ok(()),
-                                    Err(err) => Err(err),
+                                let result = match poll_read(io.as_mut(), read_buffer, session, cx) {
+                                    Ready(Ok(0)) => Err(std::io::ErrorKind::ConnectionAborted.into()),
+                                    Ready(Ok(_)) => {
+                                        deadline.clear();
+                                        Ok(())
+                                    }
+                                    Ready(Err(err)) => Err(err),
+                                    Pending => {
+                                        ready!(deadline.poll(config.body_read_timeout, &*config.timer, cx));
+                                        // the rest of the request body is unknown, thus the
+                                        // connection cannot be reused
+                                        session.keep_alive = false;
+                                        Err(std::io::ErrorKind::TimedOut.into())
+                                    }
                                 };
                                 if let Err(err) = result {
                                     session.shared.set_io_error(err, cx);
                                     break;
                                 }
                             }
-                            continue;
+                            // the service future is woken by the request body if it is polled
+                            // with new data or error
+                            return Pending;
                         }
                     };
 
-                    let Phase::Service(context, _) = mem::replace(phase, Phase::Request) else {
+                    let Phase::Service(mut context, _) = mem::replace(phase, Phase::Request) else {
                         unreachable!()
                     };
+                    deadline.clear();
 
                     let (body, kind) = context.build_response_writer(response, session, write_buffer);
                     *phase = match kind {
@@ -132,11 +330,11 @@ where
                     };
                 }
                 Phase::Response(context, encoder, body, data_mut) => {
-                    ready!(io.as_mut().poll_write_all_buf(&mut *write_buffer, cx)?);
+                    poll_write!(deadline, config, cx, poll_write_all_buf(io.as_mut(), write_buffer, session, cx));
 
                     loop {
                         if let Some(data) = data_mut {
-                            ready!(io.as_mut().poll_write_all_buf(data, cx)?);
+                            poll_write!(deadline, config, cx, poll_write_all_buf(io.as_mut(), data, session, cx));
                             *data_mut = None;
                         }
 
@@ -158,7 +356,10 @@ where
                         };
                     }
 
-                    *phase = if context.needs_drain()? {
+                    if let Some(status) = context.status {
+                        session.observer.on_request_end(&session.info, status);
+                    }
+                    *phase = if session.keep_alive && context.needs_drain()? {
                         let Phase::Response(context, _, _, _) = mem::replace(phase, Phase::Request) else {
                             unreachable!()
                         };
@@ -168,14 +369,19 @@ where
                     };
                 }
                 Phase::ResponseChunked(context, encoder, body, data_mut) => {
-                    ready!(io.as_mut().poll_write_all_buf(&mut *write_buffer, cx)?);
+                    poll_write!(deadline, config, cx, poll_write_all_buf(io.as_mut(), write_buffer, session, cx));
 
                     loop {
                         while let Some(chunk) = data_mut {
                             let mut chunks = write_buffer.chain(chunk);
                             let mut io_slice = [std::io::IoSlice::new(&[]); 16];
                             let cnt = chunks.chunks_vectored(&mut io_slice);
-                            let write = ready!(io.as_mut().poll_write_vectored(&io_slice[..cnt], cx)?);
+                            let write = poll_write!(
+                                deadline,
+                                config,
+                                cx,
+                                poll_write_vectored(io.as_mut(), &io_slice[..cnt], session, cx)
+                            );
                             chunks.advance(write);
                             if !chunks.has_remaining() {
                                 *data_mut = None;
@@ -200,7 +406,10 @@ where
 
                     // TODO: check for recv shared handle should be dropped
 
-                    *phase = if context.needs_drain()? {
+                    if let Some(status) = context.status {
+                        session.observer.on_request_end(&session.info, status);
+                    }
+                    *phase = if session.keep_alive && context.needs_drain()? {
                         let Phase::ResponseChunked(context, _, _, _) = mem::replace(phase, Phase::Request) else {
                             unreachable!()
                         };
@@ -209,9 +418,31 @@ where
                         Phase::Complete
                     };
                 }
+                Phase::ErrorResponse(context) => {
+                    poll_write!(deadline, config, cx, poll_write_all_buf(io.as_mut(), write_buffer, session, cx));
+
+                    if let Some(status) = context.status {
+                        session.observer.on_request_end(&session.info, status);
+                    }
+                    *phase = if session.keep_alive && context.needs_drain()? {
+                        let Phase::ErrorResponse(context) = mem::replace(phase, Phase::Request) else {
+                            unreachable!()
+                        };
+                        Phase::Drain(context)
+                    } else {
+                        Phase::Complete
+                    };
+                }
                 Phase::Drain(context) => {
                     loop {
-                        let read = ready!(io.as_mut().poll_read(&mut *read_buffer, cx)?);
+                        let read = match poll_read(io.as_mut(), read_buffer, session, cx)? {
+                            Ready(read) => read,
+                            Pending => {
+                                ready!(deadline.poll(config.body_read_timeout, &*config.timer, cx));
+                                return Ready(Ok(()));
+                            }
+                        };
+                        deadline.clear();
                         if read == 0 {
                             return Ready(Ok(()))
                         }
@@ -222,13 +453,18 @@ where
                     *phase = Phase::Complete;
                 }
                 Phase::Complete => {
-                    if !session.keep_alive {
+                    if !session.keep_alive || *is_shutdown {
                         return Ready(Ok(()));
                     }
                     session.shared.detach();
                     read_buffer.reclaim();
-                    *phase = Phase::Request;
+                    *phase = Phase::Idle;
                 }
+                Phase::Flush => {
+                    poll_write!(deadline, config, cx, poll_write_all_buf(io.as_mut(), write_buffer, session, cx));
+                    return Ready(Ok(()));
+                }
+                Phase::Upgrade(core) => return core.poll(&*service, io.as_mut(), cx),
             }
         }
     }
@@ -243,18 +479,75 @@ where
 
     #[inline]
     fn poll(mut self: Pin<&mut Self>, cx: &mut std::task::Context) -> Poll<Self::Output> {
-        match ready!(self.as_mut().try_poll(cx)) {
-            Ok(()) => {
-                println!("connection closed");
-            }
-            Err(err) => {
-                eprintln!("connection aborted: {err}");
-            }
+        let result = ready!(self.as_mut().try_poll(cx));
+        let session = &self.session;
+        match result {
+            Ok(()) => session.observer.on_close(&session.info),
+            Err(err) => session.observer.on_abort(&session.info, &*err),
         }
         Poll::Ready(())
     }
 }
 
+// ===== Service =====
+
+/// Poll the service readiness, which is checked once per request.
+fn poll_service_ready<S: HttpService>(
+    service: &S,
+    is_ready: &mut bool,
+    cx: &mut std::task::Context,
+) -> Poll<()> {
+    if !*is_ready {
+        ready!(service.poll_ready(cx));
+        *is_ready = true;
+    }
+    Ready(())
+}
+
+// ===== IO =====
+
+/// Read from `io` and report the read bytes to the observer.
+fn poll_read<IO: AsyncRead>(
+    io: Pin<&mut IO>,
+    buf: &mut BytesMut,
+    session: &Session,
+    cx: &mut std::task::Context,
+) -> Poll<io::Result<usize>> {
+    let read = ready!(io.poll_read(buf, cx))?;
+    if read != 0 {
+        session.observer.on_read(&session.info, read);
+    }
+    Ready(Ok(read))
+}
+
+/// Write all of `buf` to `io` and report the written bytes to the observer.
+fn poll_write_all_buf<IO: AsyncWrite, B: Buf>(
+    io: Pin<&mut IO>,
+    buf: &mut B,
+    session: &Session,
+    cx: &mut std::task::Context,
+) -> Poll<io::Result<()>> {
+    let remaining = buf.remaining();
+    let result = io.poll_write_all_buf(buf, cx);
+    let written = remaining - buf.remaining();
+    if written != 0 {
+        session.observer.on_write(&session.info, written);
+    }
+    result
+}
+
+/// Write `bufs` to `io` and report the written bytes to the observer.
+fn poll_write_vectored<IO: AsyncWrite>(
+    io: Pin<&mut IO>,
+    bufs: &[io::IoSlice<'_>],
+    session: &Session,
+    cx: &mut std::task::Context,
+) -> Poll<io::Result<usize>> {
+    let written = ready!(io.poll_write_vectored(bufs, cx))?;
+    session.observer.on_write(&session.info, written);
+    Ready(Ok(written))
+}
+
 impl<S, IO> std::fmt::Debug for Connection<S, IO>
 where
     S: HttpService
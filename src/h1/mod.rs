@@ -3,9 +3,12 @@ mod states;
 mod chunked;
 mod body;
 mod proto;
+pub(crate) mod timeout;
+mod config;
 mod conn;
 
 #[cfg(test)]
 mod test;
 
+pub use config::Config;
 pub use conn::Connection;
@@ -1,14 +1,17 @@
 use std::mem;
 use std::task::Poll::{self, *};
-use tcio::bytes::{Buf, BytesMut};
+use tcio::bytes::{Buf, Bytes, BytesMut};
 use tcio::num::{itoa, wrapping_atou};
 
 use crate::body::{Body, Incoming};
 use crate::h1::body::{BodyDecoder, BodyEncoder, ContentKind};
 use crate::h1::states::Session;
-use crate::headers::{HeaderField, HeaderName, HeaderValue, lookup};
+use crate::headers::{HeaderField, HeaderName, HeaderValue, lookup, standard};
 use crate::http::error::{ParseError, ProtoError, UserError};
-use crate::http::{Authority, Method, Request, Response, Target, httpdate_now, request, response};
+use crate::http::{
+    Authority, Extensions, Method, Request, Response, StatusCode, Target, httpdate_now, request,
+    response,
+};
 use crate::headers::matches;
 
 use ParseError as P;
@@ -161,11 +164,16 @@ pub fn poll_request(
                 content = Some(ContentKind::Chunked);
             }
             CONNECTION => {
-                session.keep_alive = match value.as_slice() {
-                    b"keep-alive" => true,
-                    b"close" => false,
-                    _ => return Ready(Err(E::InvalidConnectionOption)),
-                };
+                for option in value.as_slice().split(|&b| b == b',') {
+                    match option.trim_ascii() {
+                        b"keep-alive" => session.keep_alive = true,
+                        b"close" => session.keep_alive = false,
+                        // handled in `h2c_upgrade`
+                        o if o.eq_ignore_ascii_case(b"upgrade") => {}
+                        o if o.eq_ignore_ascii_case(b"http2-settings") => {}
+                        _ => return Ready(Err(E::InvalidConnectionOption)),
+                    }
+                }
             }
             _ => {}
         };
@@ -202,11 +210,14 @@ pub fn poll_request(
         target,
         version: crate::http::Version::HTTP_11,
         headers: mem::take(&mut session.headers),
+        info: session.info.clone(),
+        extensions: Extensions::new(),
     };
 
     let context = RequestContext {
         method,
         decoder,
+        status: None,
     };
 
     Ready(Ok((parts, context)))
@@ -295,6 +306,8 @@ impl Headers {
 pub struct RequestContext {
     pub method: Method,
     pub decoder: BodyDecoder,
+    /// response status, available after the response writer is built
+    pub status: Option<StatusCode>,
 }
 
 impl RequestContext {
@@ -328,7 +341,7 @@ impl RequestContext {
     }
 
     pub fn build_response_writer<B>(
-        &self,
+        &mut self,
         response: Response<B>,
         session: &mut Session,
         write_buffer: &mut BytesMut,
@@ -337,10 +350,11 @@ impl RequestContext {
         B: Body,
     {
         let (parts, body) = response.into_parts();
+        self.status = Some(parts.status);
         let size_hint = body.size_hint();
         let clen = size_hint.1.filter(|&l|l == size_hint.0);
 
-        write_response_head(&parts, &mut *write_buffer, clen);
+        write_response_head(&parts, &mut *write_buffer, clen, session.keep_alive);
 
         // reuse header map allocation
         let mut headers = parts.headers;
@@ -358,6 +372,29 @@ impl RequestContext {
         }
     }
 
+    /// Write a response with buffered body, such as the rendered service error.
+    pub fn write_buffered_response(
+        &mut self,
+        response: Response<Bytes>,
+        session: &mut Session,
+        write_buffer: &mut BytesMut,
+    ) {
+        let (parts, body) = response.into_parts();
+        self.status = Some(parts.status);
+
+        write_response_head(&parts, &mut *write_buffer, Some(body.len() as u64), session.keep_alive);
+
+        // reuse header map allocation
+        let mut headers = parts.headers;
+        headers.clear();
+        session.headers = headers;
+
+        // https://www.rfc-editor.org/rfc/rfc9110.html#section-6.4.2-4
+        if !matches!(self.method, Method::HEAD) {
+            write_buffer.extend_from_slice(&body);
+        }
+    }
+
     /// Returns `Ok(bool)` indicating whether message body draining is required.
     ///
     /// # Errors
@@ -372,9 +409,63 @@ impl RequestContext {
     }
 }
 
+// ===== Upgrade =====
+
+const SWITCHING_PROTOCOLS_H2C: &[u8] =
+    b"HTTP/1.1 101 Switching Protocols\r\nConnection: Upgrade\r\nUpgrade: h2c\r\n\r\n";
+
+/// Returns the decoded `HTTP2-Settings` if the request is a valid `h2c` upgrade request.
+///
+/// Request with message body is not upgraded, and served as HTTP/1.1 instead.
+pub fn h2c_upgrade(parts: &request::Parts, context: &RequestContext) -> Option<Vec<u8>> {
+    let upgrade = parts.headers.get(standard::UPGRADE)?;
+    if !upgrade.as_bytes().eq_ignore_ascii_case(b"h2c") || !context.decoder.is_empty() {
+        return None;
+    }
+
+    let connection = parts.headers.get(standard::CONNECTION)?;
+    let (mut has_upgrade, mut has_settings) = (false, false);
+    for option in connection.as_bytes().split(|&b| b == b',') {
+        let option = option.trim_ascii();
+        has_upgrade |= option.eq_ignore_ascii_case(b"upgrade");
+        has_settings |= option.eq_ignore_ascii_case(b"http2-settings");
+    }
+    if !has_upgrade || !has_settings {
+        return None;
+    }
+
+    // A server MUST NOT upgrade the connection to HTTP/2 if this header field is not present or if
+    // more than one is present.
+    let mut settings = parts.headers.get_all(&"http2-settings");
+    let value = settings.next()?;
+    if settings.next().is_some() {
+        return None;
+    }
+
+    crate::h2::settings::decode_http2_settings(value.as_bytes())
+}
+
+/// Write the `101 (Switching Protocols)` response of `h2c` upgrade.
+pub fn write_switching_protocols(buf: &mut BytesMut) {
+    buf.extend_from_slice(SWITCHING_PROTOCOLS_H2C);
+}
+
 // ===== Response Writer =====
 
-fn write_response_head(res: &response::Parts, buf: &mut BytesMut, content_length: Option<u64>) {
+const REQUEST_TIMEOUT: &[u8] =
+    b"HTTP/1.1 408 Request Timeout\r\nConnection: close\r\nContent-Length: 0\r\n\r\n";
+
+/// Write the `408 (Request Timeout)` response, the connection should be closed afterwards.
+pub fn write_request_timeout(buf: &mut BytesMut) {
+    buf.extend_from_slice(REQUEST_TIMEOUT);
+}
+
+fn write_response_head(
+    res: &response::Parts,
+    buf: &mut BytesMut,
+    content_length: Option<u64>,
+    keep_alive: bool,
+) {
     buf.extend_from_slice(res.version.as_str().as_bytes());
     buf.extend_from_slice(b" ");
     buf.extend_from_slice(res.status.as_str().as_bytes());
@@ -393,6 +484,11 @@ fn write_response_head(res: &response::Parts, buf: &mut BytesMut, content_length
         },
     }
 
+    // https://www.rfc-editor.org/rfc/rfc9112.html#section-9.6
+    if !keep_alive && !res.headers.contains_key(standard::CONNECTION) {
+        buf.extend_from_slice(b"Connection: close\r\n");
+    }
+
     for f in &res.headers {
         buf.extend_from_slice(f.name().as_str().as_bytes());
         buf.extend_from_slice(b": ");
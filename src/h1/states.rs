@@ -1,6 +1,7 @@
 use crate::body::shared::SendHandle;
+use crate::event::{LogObserver, SharedObserver};
 use crate::headers::HeaderMap;
-use crate::http::Scheme;
+use crate::http::{ConnectionInfo, Scheme};
 
 #[derive(Debug)]
 pub struct Session {
@@ -8,15 +9,19 @@ pub struct Session {
     pub headers: HeaderMap,
     pub shared: SendHandle,
     pub keep_alive: bool,
+    pub info: ConnectionInfo,
+    pub observer: SharedObserver,
 }
 
 impl Session {
-    pub fn new() -> Self {
+    pub fn new(info: ConnectionInfo) -> Self {
         Self {
             scheme: Scheme::HTTP,
             headers: HeaderMap::with_capacity(32),
             shared: SendHandle::new(),
             keep_alive: true,
+            info,
+            observer: LogObserver::shared(),
         }
     }
 }
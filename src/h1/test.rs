@@ -1,5 +1,207 @@
-// #[test]
-// fn test_request() {
-//     todo!()
-// }
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tcio::bytes::Bytes;
+use tokio::io::{AsyncReadExt, AsyncWriteExt, DuplexStream};
 
+use crate::body::{Full, Incoming};
+use crate::event::{Observer, SharedObserver};
+use crate::h1::{Config, Connection};
+use crate::http::{ConnectionInfo, Request, Response, StatusCode};
+use crate::server::TestClient;
+use crate::service::{HttpService, from_fn};
+use crate::testing::{Gate, MockTimer, Recorder, block_on, settle};
+
+async fn hello(_: Request<Incoming>) -> Response<Full<Bytes>> {
+    Response::from_parts(Default::default(), Full::new(Bytes::from_static(b"hello")))
+}
+
+/// Spawn a connection serving `service`, returns the client side of the connection.
+fn serve<S>(service: S, config: Config, observer: SharedObserver) -> DuplexStream
+where
+    S: HttpService,
+    Connection<S, DuplexStream>: Send + 'static,
+{
+    let (client, server) = tokio::io::duplex(1024);
+    let conn = Connection::with_config(service, server, ConnectionInfo::default(), Arc::new(config))
+        .with_observer(observer);
+    tokio::spawn(conn);
+    client
+}
+
+#[test]
+fn test_header_read_timeout() {
+    block_on(async {
+        let timer = MockTimer::new();
+        let recorder = Recorder::default();
+        let config = Config::new()
+            .header_read_timeout(Duration::from_secs(5))
+            .timer(timer.clone());
+        let mut io = serve(from_fn(hello), config, recorder.shared());
+
+        io.write_all(b"GET / HTTP/1.1\r\n").await.unwrap();
+        settle().await;
+        timer.advance(Duration::from_secs(4));
+        settle().await;
+        assert!(recorder.events().is_empty());
+
+        // part of the request is received, thus `408 (Request Timeout)` is written
+        timer.advance(Duration::from_secs(1));
+        let mut response = Vec::new();
+        io.read_to_end(&mut response).await.unwrap();
+        assert!(response.starts_with(b"HTTP/1.1 408 "));
+        assert_eq!(recorder.events(), ["close"]);
+    });
+}
+
+#[test]
+fn test_header_read_timeout_empty() {
+    block_on(async {
+        let timer = MockTimer::new();
+        let recorder = Recorder::default();
+        let config = Config::new()
+            .header_read_timeout(Duration::from_secs(5))
+            .timer(timer.clone());
+        let mut io = serve(from_fn(hello), config, recorder.shared());
+
+        // nothing is received, the connection is closed without response
+        settle().await;
+        timer.advance(Duration::from_secs(5));
+        let mut response = Vec::new();
+        io.read_to_end(&mut response).await.unwrap();
+        assert!(response.is_empty());
+        assert_eq!(recorder.events(), ["close"]);
+    });
+}
+
+#[test]
+fn test_keep_alive_timeout() {
+    block_on(async {
+        let timer = MockTimer::new();
+        let recorder = Recorder::default();
+        let config = Config::new()
+            .keep_alive_timeout(Duration::from_secs(5))
+            .timer(timer.clone());
+        let mut client = TestClient::new(serve(from_fn(hello), config, recorder.shared()));
+
+        let response = client.send(Request::<Full<Bytes>>::default()).await.unwrap();
+        assert_eq!(response.status(), &StatusCode::OK);
+
+        settle().await;
+        timer.advance(Duration::from_secs(5));
+        let mut io = client.into_inner();
+        assert_eq!(io.read(&mut [0; 16]).await.unwrap(), 0);
+        assert_eq!(recorder.events(), ["request_end", "close"]);
+    });
+}
+
+#[test]
+fn test_body_read_timeout() {
+    block_on(async {
+        let timer = MockTimer::new();
+        let recorder = Recorder::default();
+        let config = Config::new()
+            .body_read_timeout(Duration::from_secs(5))
+            .timer(timer.clone());
+        let service = from_fn(|request: Request<Incoming>| async move {
+            let mut response = Response::<Full<Bytes>>::default();
+            if request.into_body().collect().await.is_err() {
+                *response.status_mut() = StatusCode::BAD_REQUEST;
+            }
+            response
+        });
+        let mut io = serve(service, config, recorder.shared());
+
+        io.write_all(b"POST / HTTP/1.1\r\nhost: localhost\r\ncontent-length: 10\r\n\r\nhello")
+            .await
+            .unwrap();
+        settle().await;
+
+        // the service receives the body error, and the connection is not reused
+        timer.advance(Duration::from_secs(5));
+        let mut response = Vec::new();
+        io.read_to_end(&mut response).await.unwrap();
+        assert!(response.starts_with(b"HTTP/1.1 400 "));
+        assert_eq!(recorder.events(), ["request_end", "close"]);
+    });
+}
+
+#[test]
+fn test_write_timeout() {
+    block_on(async {
+        let timer = MockTimer::new();
+        let recorder = Recorder::default();
+        let config = Config::new()
+            .write_timeout(Duration::from_secs(5))
+            .timer(timer.clone());
+        let service = from_fn(|_: Request<Incoming>| async {
+            Response::from_parts(Default::default(), Full::new(Bytes::from(vec![0; 64 * 1024])))
+        });
+        let mut io = serve(service, config, recorder.shared());
+
+        // the response is never read, thus the write is blocked
+        io.write_all(b"GET / HTTP/1.1\r\nhost: localhost\r\n\r\n").await.unwrap();
+        settle().await;
+        assert!(recorder.events().is_empty());
+
+        timer.advance(Duration::from_secs(5));
+        settle().await;
+        assert_eq!(recorder.events(), ["abort"]);
+    });
+}
+
+/// Observer that counts the read bytes.
+#[derive(Clone, Default)]
+struct ReadCount(Arc<AtomicUsize>);
+
+impl Observer for ReadCount {
+    fn on_read(&self, _: &ConnectionInfo, len: usize) {
+        self.0.fetch_add(len, Ordering::Relaxed);
+    }
+}
+
+#[test]
+fn test_backpressure() {
+    block_on(async {
+        let gate = Gate::default();
+        let reads = ReadCount::default();
+        let io = serve(gate.clone(), Config::new(), Arc::new(reads.clone()));
+
+        let mut client = TestClient::new(io);
+        let pending = tokio::spawn(async move {
+            client.send(Request::<Full<Bytes>>::default()).await
+        });
+        settle().await;
+
+        // the request is not read until the service is ready
+        assert_eq!(reads.0.load(Ordering::Relaxed), 0);
+        assert!(!pending.is_finished());
+
+        gate.open();
+        let response = pending.await.unwrap().unwrap();
+        assert_eq!(response.status(), &StatusCode::OK);
+        assert_ne!(reads.0.load(Ordering::Relaxed), 0);
+    });
+}
+
+#[test]
+fn test_backpressure_timeout() {
+    block_on(async {
+        let timer = MockTimer::new();
+        let recorder = Recorder::default();
+        let config = Config::new()
+            .header_read_timeout(Duration::from_secs(5))
+            .timer(timer.clone());
+        let mut io = serve(Gate::default(), config, recorder.shared());
+
+        io.write_all(b"GET / HTTP/1.1\r\nhost: localhost\r\n\r\n").await.unwrap();
+        settle().await;
+
+        // the header read timeout still applies while the service is not ready
+        timer.advance(Duration::from_secs(5));
+        let mut response = Vec::new();
+        io.read_to_end(&mut response).await.unwrap();
+        assert!(response.is_empty());
+        assert_eq!(recorder.events(), ["close"]);
+    });
+}
@@ -0,0 +1,49 @@
+use std::pin::Pin;
+use std::task::Poll;
+use std::time::Duration;
+
+use crate::rt::{Sleep, Timer};
+
+/// Deadline of a connection phase.
+///
+/// The deadline is armed on the first poll, and stays expired until it is cleared.
+pub struct Deadline {
+    sleep: Option<Pin<Box<dyn Sleep>>>,
+}
+
+impl Deadline {
+    pub fn new() -> Self {
+        Self { sleep: None }
+    }
+
+    /// Disarm the deadline.
+    pub fn clear(&mut self) {
+        self.sleep = None;
+    }
+
+    /// Poll for deadline expiry, arm it with `timeout` if not yet armed.
+    ///
+    /// Returns `Pending` forever if `timeout` is `None`.
+    pub fn poll(
+        &mut self,
+        timeout: Option<Duration>,
+        timer: &dyn Timer,
+        cx: &mut std::task::Context,
+    ) -> Poll<()> {
+        let Some(timeout) = timeout else {
+            return Poll::Pending;
+        };
+        self.sleep
+            .get_or_insert_with(|| timer.sleep(timeout))
+            .as_mut()
+            .poll(cx)
+    }
+}
+
+impl std::fmt::Debug for Deadline {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Deadline")
+            .field("is_armed", &self.sleep.is_some())
+            .finish()
+    }
+}
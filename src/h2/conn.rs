@@ -1,25 +1,64 @@
+use std::mem;
 use std::task::Poll;
 use std::{pin::Pin, task::ready};
-use tcio::bytes::BytesMut;
+use tcio::bytes::{Bytes, BytesMut};
 use tcio::io::{AsyncRead, AsyncWrite};
 
+use crate::body::error::BodyError;
+use crate::body::shared::{BodyDecode, SendHandle};
+use crate::body::{Body, Incoming};
+use crate::event::{LogObserver, SharedObserver};
+use crate::h2::error::{ConnectionError, ErrorCode};
+use crate::h2::hpack::Encoder;
 use crate::h2::state::{FrameResult, H2State};
-use crate::service::HttpService;
+use crate::headers::standard;
+use crate::http::{ConnectionInfo, Method, Request, StatusCode, response};
+use crate::service::{HttpService, InternalError, SharedErrorHandler};
 
 type BoxError = Box<dyn std::error::Error + Send + Sync>;
 
 const DEFAULT_BUFFER_CAP: usize = 512;
 
+const DEFAULT_HEADER_TABLE_SIZE: usize = 4096;
+
+/// Response bodies are not polled while the write buffer exceeds this size.
+const WRITE_BUFFER_LIMIT: usize = 64 * 1024;
+
+/// The default SETTINGS_INITIAL_WINDOW_SIZE is advertised, thus this is the most request body data
+/// buffered per stream.
+const RECV_WINDOW_SIZE: usize = 65_535;
+
 /// HTTP/2 Connection.
-#[derive(Debug)]
-pub struct Connection<S, IO> {
+pub struct Connection<S, IO>
+where
+    S: HttpService,
+{
+    core: Core<S>,
+    service: S,
+    io: IO,
+}
+
+/// HTTP/2 connection state that is independent of the IO.
+///
+/// This allows HTTP/1.1 connection to continue as HTTP/2 connection after `h2c` upgrade.
+pub(crate) struct Core<S>
+where
+    S: HttpService,
+{
     read_buffer: BytesMut,
     write_buffer: BytesMut,
     state: H2State,
+    encoder: Encoder,
     phase: Phase,
-    #[allow(unused, reason = "TODO")]
-    service: S,
-    io: IO,
+    /// graceful shutdown is started, new streams are refused
+    is_shutdown: bool,
+    info: ConnectionInfo,
+    observer: SharedObserver,
+    error_handler: SharedErrorHandler,
+    /// the request that initiate `h2c` upgrade, delivered as stream 1
+    upgrade: Option<Request<Incoming>>,
+    /// streams which response is in progress
+    streams: Vec<StreamTask<S>>,
 }
 
 #[derive(Debug)]
@@ -29,103 +68,503 @@ enum Phase {
     Shutdown,
 }
 
-type ConnectionProject<'a, IO> = (
-    Pin<&'a mut IO>,
-    &'a mut BytesMut,
-    &'a mut BytesMut,
-    &'a mut H2State,
-    &'a mut Phase,
-);
+/// Response of a stream.
+struct StreamTask<S>
+where
+    S: HttpService,
+{
+    id: u32,
+    is_head: bool,
+    status: StatusCode,
+    task: Task<S>,
+    body: Option<RecvBody>,
+}
+
+/// Request body of a stream, which is fed by DATA frames.
+struct RecvBody {
+    shared: SendHandle,
+    /// data that is not yet read by the service
+    buffer: BytesMut,
+    is_end_stream: bool,
+}
+
+enum Task<S>
+where
+    S: HttpService,
+{
+    Service(Pin<Box<S::Future>>),
+    /// response body, with the polled data that is not yet written as the flow control window is
+    /// exhausted
+    Body(Pin<Box<S::ResBody>>, Option<<S::ResBody as Body>::Data>),
+    /// error response body, which is written as the flow control window allows
+    Error(Bytes),
+}
 
-impl<S, IO> Connection<S, IO> {
+impl<S, IO> Connection<S, IO>
+where
+    S: HttpService,
+{
     pub fn new(service: S, io: IO) -> Self {
+        Self::with_info(service, io, ConnectionInfo::default())
+    }
+
+    /// Create new connection with given [`ConnectionInfo`].
+    pub fn with_info(service: S, io: IO, info: ConnectionInfo) -> Self {
+        Self::with_read_buffer(service, io, info, BytesMut::with_capacity(DEFAULT_BUFFER_CAP))
+    }
+
+    /// Create new connection with bytes that already read from `io`.
+    pub(crate) fn with_read_buffer(
+        service: S,
+        io: IO,
+        info: ConnectionInfo,
+        read_buffer: BytesMut,
+    ) -> Self {
         Self {
-            read_buffer: BytesMut::with_capacity(DEFAULT_BUFFER_CAP),
-            write_buffer: BytesMut::with_capacity(DEFAULT_BUFFER_CAP),
-            state: H2State::new(),
-            phase: Phase::Handshake,
+            core: Core::new(info, read_buffer),
             service,
             io,
         }
     }
+
+    /// Set the [`Observer`][crate::event::Observer] of connection events.
+    pub fn with_observer(mut self, observer: SharedObserver) -> Self {
+        self.core.observer = observer;
+        self
+    }
+
+    /// Start a graceful shutdown.
+    ///
+    /// A GOAWAY frame is sent, new streams are refused, and the connection is closed after all
+    /// in-flight streams are complete.
+    pub fn graceful_shutdown(self: Pin<&mut Self>) {
+        // SAFETY: `core` is not pinned
+        unsafe { self.get_unchecked_mut() }.core.graceful_shutdown();
+    }
 }
 
-impl<S, IO> Connection<S, IO>
+impl<S, IO> Future for Connection<S, IO>
 where
     S: HttpService,
     IO: AsyncRead + AsyncWrite,
 {
-    fn try_poll(
-        mut self: Pin<&mut Self>,
-        cx: &mut std::task::Context,
-    ) -> Poll<Result<bool, BoxError>> {
-        let (mut io, read_buffer, write_buffer, state, phase) = self.as_mut().project();
+    type Output = ();
 
-        match phase {
-            Phase::Handshake => {
-                if H2State::handshake(&mut *read_buffer, &mut *write_buffer)?.is_ready() {
-                    *phase = Phase::Active;
+    fn poll(self: Pin<&mut Self>, cx: &mut std::task::Context) -> Poll<Self::Output> {
+        // SAFETY: self is pinned, no custom Drop and Unpin
+        let me = unsafe { self.get_unchecked_mut() };
+        let io = unsafe { Pin::new_unchecked(&mut me.io) };
+
+        let result = ready!(me.core.poll(&me.service, io, cx));
+        let Core { info, observer, .. } = &me.core;
+        match result {
+            Ok(()) => observer.on_close(info),
+            Err(err) => observer.on_abort(info, &*err),
+        }
+        Poll::Ready(())
+    }
+}
+
+// ===== Core =====
+
+impl<S> Core<S>
+where
+    S: HttpService,
+{
+    pub(crate) fn new(info: ConnectionInfo, read_buffer: BytesMut) -> Self {
+        Self {
+            read_buffer,
+            write_buffer: BytesMut::with_capacity(DEFAULT_BUFFER_CAP),
+            state: H2State::new(),
+            encoder: Encoder::new(DEFAULT_HEADER_TABLE_SIZE),
+            phase: Phase::Handshake,
+            is_shutdown: false,
+            info,
+            observer: LogObserver::shared(),
+            error_handler: InternalError::shared(),
+            upgrade: None,
+            streams: Vec::new(),
+        }
+    }
+
+    /// Create connection state from HTTP/1.1 `h2c` upgrade.
+    ///
+    /// `settings` is the decoded `HTTP2-Settings` header value, and `write_buffer` should contains
+    /// the `101 (Switching Protocols)` response.
+    pub(crate) fn upgrade(
+        info: ConnectionInfo,
+        observer: SharedObserver,
+        error_handler: SharedErrorHandler,
+        read_buffer: BytesMut,
+        write_buffer: BytesMut,
+        settings: &[u8],
+        request: Request<Incoming>,
+    ) -> Result<Self, ConnectionError> {
+        let mut state = H2State::new();
+        if let Err(err) = state.upgrade(settings) {
+            observer.on_protocol_error(&info, &err);
+            return Err(err);
+        }
+        Ok(Self {
+            read_buffer,
+            write_buffer,
+            state,
+            encoder: Encoder::new(DEFAULT_HEADER_TABLE_SIZE),
+            // client still sends the connection preface after the `101` response
+            phase: Phase::Handshake,
+            is_shutdown: false,
+            info,
+            observer,
+            error_handler,
+            upgrade: Some(request),
+            streams: Vec::new(),
+        })
+    }
+
+    pub(crate) fn graceful_shutdown(&mut self) {
+        if self.is_shutdown {
+            return;
+        }
+        self.is_shutdown = true;
+        match self.phase {
+            // the upgrade request is still served, GOAWAY is sent after the handshake
+            Phase::Handshake if self.upgrade.is_some() => {}
+            // preface is not yet exchanged, close immediately
+            Phase::Handshake => self.phase = Phase::Shutdown,
+            Phase::Active => self.state.write_goaway(ErrorCode::NoError, &mut self.write_buffer),
+            Phase::Shutdown => {}
+        }
+    }
+
+    pub(crate) fn poll<IO>(
+        &mut self,
+        service: &S,
+        mut io: Pin<&mut IO>,
+        cx: &mut std::task::Context,
+    ) -> Poll<Result<(), BoxError>>
+    where
+        IO: AsyncRead + AsyncWrite,
+    {
+        loop {
+            match self.phase {
+                Phase::Handshake => {
+                    let handshake = H2State::handshake(&mut self.read_buffer, &mut self.write_buffer)
+                        .map_err(|err| {
+                            self.observer.on_protocol_error(&self.info, &err);
+                            err
+                        })?;
+                    if handshake.is_ready() {
+                        self.phase = Phase::Active;
+                        if let Some(request) = self.upgrade.take() {
+                            self.dispatch(service, 1, request, None);
+                        }
+                        if self.is_shutdown {
+                            self.state.write_goaway(ErrorCode::NoError, &mut self.write_buffer);
+                        }
+                        continue;
+                    }
                 }
-            }
-            Phase::Active => {
-                while let Some(result) = state.poll_frame(read_buffer, write_buffer)? {
-                    match result {
-                        FrameResult::None => {}
-                        FrameResult::Request(_stream_id, _map) => todo!(),
-                        FrameResult::Data(_stream_id, _data) => todo!(),
-                        FrameResult::Shutdown => *phase = Phase::Shutdown,
+                Phase::Active => {
+                    while let Some(result) = self
+                        .state
+                        .poll_frame(&mut self.read_buffer, &mut self.write_buffer)
+                        .inspect_err(|err| self.observer.on_protocol_error(&self.info, err))?
+                    {
+                        match result {
+                            FrameResult::None => {}
+                            FrameResult::Request(stream_id, mut parts, is_end_stream) => {
+                                if self.is_shutdown {
+                                    // stream is not processed, client can safely retry it
+                                    // in new connection
+                                    self.state.write_rst_stream(
+                                        stream_id,
+                                        ErrorCode::RefusedStream,
+                                        &mut self.write_buffer,
+                                    );
+                                } else if !self.refuse_if_busy(service, stream_id, cx) {
+                                    parts.info = self.info.clone();
+                                    let (body, recv) = if is_end_stream {
+                                        (Incoming::empty(), None)
+                                    } else {
+                                        let mut shared = SendHandle::new();
+                                        let body = Incoming::from_handle(shared.handle(cx), None);
+                                        let recv = RecvBody {
+                                            shared,
+                                            buffer: BytesMut::new(),
+                                            is_end_stream: false,
+                                        };
+                                        (body, Some(recv))
+                                    };
+                                    let request = Request::from_parts(parts, body);
+                                    self.dispatch(service, stream_id, request, recv);
+                                }
+                            }
+                            FrameResult::Data(stream_id, data, is_end_stream) => {
+                                self.recv_data(stream_id, data, is_end_stream);
+                            }
+                            // the response is no longer needed
+                            FrameResult::Reset(stream_id) => {
+                                self.streams.retain(|stream| stream.id != stream_id);
+                            }
+                            // client will not open new stream, close after in-flight streams
+                            FrameResult::Shutdown => self.is_shutdown = true,
+                        }
+                    }
+
+                    let is_blocked = self.poll_streams(cx);
+                    ready!(self.poll_write(io.as_mut(), cx)?);
+                    if is_blocked {
+                        // the write buffer is flushed, continue the streams
+                        continue;
+                    }
+                    if self.is_shutdown && self.streams.is_empty() {
+                        self.phase = Phase::Shutdown;
+                        continue;
                     }
                 }
+                Phase::Shutdown => {
+                    ready!(self.poll_write(io.as_mut(), cx)?);
+                    return Poll::Ready(Ok(()));
+                }
+            }
+
+            ready!(self.poll_write(io.as_mut(), cx)?);
+            let read = ready!(io.as_mut().poll_read(&mut self.read_buffer, cx)?);
+            if read == 0 {
+                return Poll::Ready(Ok(()));
             }
-            Phase::Shutdown => { }
+            self.observer.on_read(&self.info, read);
         }
+    }
 
-        let _ = io.as_mut().poll_write_all_buf(&mut *write_buffer, cx)?;
-        let result = io.as_mut().poll_read(&mut *read_buffer, cx)?;
-        Poll::Ready(Ok(!matches!(result, Poll::Ready(0))))
+    /// Write the whole write buffer and report the written bytes to the observer.
+    fn poll_write<IO: AsyncWrite>(
+        &mut self,
+        io: Pin<&mut IO>,
+        cx: &mut std::task::Context,
+    ) -> Poll<std::io::Result<()>> {
+        let len = self.write_buffer.len();
+        let result = io.poll_write_all_buf(&mut self.write_buffer, cx);
+        let written = len - self.write_buffer.len();
+        if written != 0 {
+            self.observer.on_write(&self.info, written);
+        }
+        result
     }
-}
 
-impl<S, IO> Future for Connection<S, IO>
-where
-    S: HttpService,
-    IO: AsyncRead + AsyncWrite,
-{
-    type Output = ();
+    /// Refuse new stream if the service is not ready, returns `true` if the stream is refused.
+    ///
+    /// Client can safely retry refused stream, as it is not processed at all.
+    fn refuse_if_busy(&mut self, service: &S, stream_id: u32, cx: &mut std::task::Context) -> bool {
+        if service.poll_ready(cx).is_ready() {
+            return false;
+        }
+        self.state.write_rst_stream(stream_id, ErrorCode::RefusedStream, &mut self.write_buffer);
+        true
+    }
+
+    /// Call the service with the request of a stream.
+    ///
+    /// The caller should check the service readiness.
+    fn dispatch(
+        &mut self,
+        service: &S,
+        stream_id: u32,
+        request: Request<Incoming>,
+        body: Option<RecvBody>,
+    ) {
+        self.observer.on_request_start(request.parts());
+        self.streams.push(StreamTask {
+            id: stream_id,
+            is_head: request.method() == &Method::HEAD,
+            status: StatusCode::OK,
+            task: Task::Service(Box::pin(service.call(request))),
+            body,
+        });
+    }
+
+    /// Buffer DATA frame payload to the request body of a stream.
+    fn recv_data(&mut self, stream_id: u32, data: BytesMut, is_end_stream: bool) {
+        let Some(index) = self.streams.iter().position(|stream| stream.id == stream_id) else {
+            // the response is already complete, thus the request body is discarded
+            return;
+        };
+        let Some(body) = &mut self.streams[index].body else {
+            return;
+        };
+        body.buffer.extend_from_slice(&data);
+        body.is_end_stream = is_end_stream;
+        if body.buffer.len() > RECV_WINDOW_SIZE {
+            // stream window is only restored when the data is read
+            let error = ErrorCode::FlowControlError;
+            self.state.write_rst_stream(stream_id, error, &mut self.write_buffer);
+            self.streams.swap_remove(index);
+        }
+    }
 
-    fn poll(mut self: Pin<&mut Self>, cx: &mut std::task::Context) -> Poll<Self::Output> {
+    /// Poll the response of all streams.
+    ///
+    /// Returns `true` if the write buffer is full, thus it should be flushed before the streams
+    /// is polled again.
+    fn poll_streams(&mut self, cx: &mut std::task::Context) -> bool {
+        let mut streams = mem::take(&mut self.streams);
+        streams.retain_mut(|stream| {
+            self.poll_body(stream, cx);
+            self.poll_stream(stream, cx).is_pending()
+        });
+        self.streams = streams;
+        self.write_buffer.len() >= WRITE_BUFFER_LIMIT
+    }
+
+    /// Deliver buffered data to the request body if it is requested.
+    fn poll_body(&mut self, stream: &mut StreamTask<S>, cx: &mut std::task::Context) {
+        let Some(body) = &mut stream.body else {
+            return;
+        };
+        let len = body.buffer.len();
+        let _ = body.shared.poll_read(&mut body.buffer, DataDecoder(body.is_end_stream), cx);
+        let read = len - body.buffer.len();
+        if read != 0 && !body.is_end_stream {
+            self.state.write_window_update(stream.id, read as u32, &mut self.write_buffer);
+        }
+    }
+
+    /// Poll the response of a stream, returns `Ready` when the response is completely buffered.
+    fn poll_stream(&mut self, stream: &mut StreamTask<S>, cx: &mut std::task::Context) -> Poll<()> {
         loop {
-            match ready!(self.as_mut().try_poll(cx)) {
-                Ok(true) => { }
-                Ok(false) => {
-                    println!("[CONNECTION] Closed");
-                    break;
-                },
-                Err(err) => {
-                    eprintln!("[ERROR] {err}");
+            match &mut stream.task {
+                Task::Service(future) => {
+                    let response = match ready!(future.as_mut().poll(cx)) {
+                        Ok(ok) => ok,
+                        Err(err) => {
+                            let err: BoxError = err.into();
+                            self.observer.on_service_error(&self.info, &*err);
+                            let (parts, body) = self.error_handler.render(&*err).into_parts();
+                            let is_end_stream = stream.is_head || body.is_empty();
+                            self.write_headers(stream.id, &parts, is_end_stream);
+                            if is_end_stream {
+                                self.observer.on_request_end(&self.info, parts.status);
+                                return Poll::Ready(());
+                            }
+                            stream.status = parts.status;
+                            stream.task = Task::Error(body);
+                            continue;
+                        }
+                    };
+
+                    let (parts, body) = response.into_parts();
+                    let is_end_stream = stream.is_head || body.is_end_stream();
+                    self.write_headers(stream.id, &parts, is_end_stream);
+                    if is_end_stream {
+                        self.observer.on_request_end(&self.info, parts.status);
+                        return Poll::Ready(());
+                    }
+                    stream.status = parts.status;
+                    stream.task = Task::Body(Box::pin(body), None);
+                }
+                Task::Body(body, pending) => {
+                    if let Some(data) = pending {
+                        let is_end_stream = body.is_end_stream();
+                        if !self.state.write_data(stream.id, data, is_end_stream, &mut self.write_buffer) {
+                            // continued when the window is updated
+                            return Poll::Pending;
+                        }
+                        if is_end_stream {
+                            break;
+                        }
+                        *pending = None;
+                    }
+                    if self.write_buffer.len() >= WRITE_BUFFER_LIMIT {
+                        return Poll::Pending;
+                    }
+                    match ready!(body.as_mut().poll_data(cx)) {
+                        Some(Ok(data)) => *pending = Some(data),
+                        None => {
+                            self.state.write_data(stream.id, &mut Bytes::new(), true, &mut self.write_buffer);
+                            break;
+                        }
+                        Some(Err(err)) => {
+                            // the response head is already written, thus the stream is reset
+                            let err: BoxError = err.into();
+                            self.observer.on_service_error(&self.info, &*err);
+                            self.state.write_rst_stream(stream.id, ErrorCode::InternalError, &mut self.write_buffer);
+                            return Poll::Ready(());
+                        }
+                    }
+                }
+                Task::Error(body) => {
+                    if !self.state.write_data(stream.id, body, true, &mut self.write_buffer) {
+                        // continued when the window is updated
+                        return Poll::Pending;
+                    }
                     break;
                 }
             }
         }
+        self.observer.on_request_end(&self.info, stream.status);
         Poll::Ready(())
     }
+
+    /// Write the response head as HEADERS frame.
+    fn write_headers(&mut self, stream_id: u32, parts: &response::Parts, is_end_stream: bool) {
+        let mut block = BytesMut::with_capacity(DEFAULT_BUFFER_CAP);
+        self.encoder.encode_status(parts.status, &mut block);
+        for field in &parts.headers {
+            if is_connection_specific(field.name()) {
+                continue;
+            }
+            self.encoder.encode_header(field.name().clone(), field.value().clone(), &mut block);
+        }
+        self.state.write_headers(stream_id, &block, is_end_stream, &mut self.write_buffer);
+    }
 }
 
-// ===== Projection =====
+/// Yields the buffered DATA frames payload, with the END_STREAM flag.
+struct DataDecoder(bool);
 
-impl<S, IO> Connection<S, IO> {
-    fn project(self: Pin<&mut Self>) -> ConnectionProject<'_, IO> {
-        // SAFETY: self is pinned, no custom Drop and Unpin
-        unsafe {
-            let me = self.get_unchecked_mut();
-            (
-                Pin::new_unchecked(&mut me.io),
-                &mut me.read_buffer,
-                &mut me.write_buffer,
-                &mut me.state,
-                &mut me.phase,
-            )
+impl BodyDecode for DataDecoder {
+    fn decode_chunk(
+        &mut self,
+        read_buffer: &mut BytesMut,
+    ) -> Poll<Result<Option<BytesMut>, BodyError>> {
+        if !read_buffer.is_empty() {
+            Poll::Ready(Ok(Some(read_buffer.split())))
+        } else if self.0 {
+            Poll::Ready(Ok(None))
+        } else {
+            Poll::Pending
         }
     }
 }
 
+/// Returns `true` if the header is HTTP/1.1 connection specific, which is forbidden in HTTP/2.
+fn is_connection_specific(name: &crate::headers::HeaderName) -> bool {
+    [
+        standard::CONNECTION,
+        standard::KEEP_ALIVE,
+        standard::TRANSFER_ENCODING,
+        standard::UPGRADE,
+    ]
+    .contains(name)
+}
+
+impl<S, IO> std::fmt::Debug for Connection<S, IO>
+where
+    S: HttpService,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Connection").finish_non_exhaustive()
+    }
+}
+
+impl<S> std::fmt::Debug for Core<S>
+where
+    S: HttpService,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Core")
+            .field("phase", &self.phase)
+            .field("streams", &self.streams.len())
+            .finish_non_exhaustive()
+    }
+}
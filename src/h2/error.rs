@@ -80,6 +80,8 @@ pub enum ConnectionError {
     ExcessiveFrame,
     /// Excessive headers list length.
     ExcessiveHeaders,
+    /// Flow control window exceeds the maximum size.
+    FlowControl,
     /// General errors defined in RFC9113 Section 8.1.1.
     Malformed,
     /// Header error.
@@ -97,6 +99,7 @@ impl std::fmt::Display for ConnectionError {
             Self::InvalidStreamId => f.write_str("invalid stream id"),
             Self::ExcessiveFrame => f.write_str("excessive frame length"),
             Self::ExcessiveHeaders => f.write_str("excessive headers list"),
+            Self::FlowControl => f.write_str("flow control window overflow"),
             Self::Malformed => f.write_str("malformed request"),
             Self::Header(err) => err.fmt(f),
             Self::Hpack(err) => err.fmt(f),
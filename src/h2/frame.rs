@@ -140,10 +140,13 @@ impl Header {
 
 // DATA/HEADERS
 const PADDED: u8 = 0x08;
-const END_STREAM: u8 = 0x01;
+pub(crate) const END_STREAM: u8 = 0x01;
 
 // HEADERS/CONTINUATION
-const END_HEADERS: u8 = 0x04;
+pub(crate) const END_HEADERS: u8 = 0x04;
+
+// HEADERS
+const PRIORITY: u8 = 0x20;
 
 // SETTINGS/PING
 const ACK: u8 = 0x01;
@@ -159,6 +162,11 @@ impl Header {
         self.flags & PADDED == PADDED
     }
 
+    /// Get the PRIORITY flag in HEADERS frame.
+    pub fn is_priority(&self) -> bool {
+        self.flags & PRIORITY == PRIORITY
+    }
+
     /// Get the END_STREAM flag in DATA or HEADERS frame.
     pub fn is_end_stream(&self) -> bool {
         self.flags & END_STREAM == END_STREAM
@@ -7,9 +7,13 @@ pub mod state;
 mod conn;
 
 pub use conn::Connection;
+pub(crate) use conn::Core;
 
 pub mod error;
 
+#[cfg(test)]
+mod test;
+
 #[derive(Clone, Copy, Debug)]
 enum ReqPseudoHdrKind {
     Method,
@@ -102,6 +102,40 @@ impl Default for Settings {
     }
 }
 
+// ===== HTTP2-Settings =====
+
+/// Decode `HTTP2-Settings` header value of HTTP/1.1 `h2c` upgrade request.
+///
+/// The value is the base64url encoded SETTINGS frame payload, with trailing padding omitted.
+///
+/// Returns `None` if the value is not a valid base64url.
+pub(crate) fn decode_http2_settings(value: &[u8]) -> Option<Vec<u8>> {
+    let value = value.strip_suffix(b"==").or_else(|| value.strip_suffix(b"=")).unwrap_or(value);
+    let mut payload = Vec::with_capacity(value.len() * 3 / 4);
+    let mut acc = 0u32;
+    let mut bits = 0;
+
+    for &byte in value {
+        let sextet = match byte {
+            b'A'..=b'Z' => byte - b'A',
+            b'a'..=b'z' => byte - b'a' + 26,
+            b'0'..=b'9' => byte - b'0' + 52,
+            b'-' => 62,
+            b'_' => 63,
+            _ => return None,
+        };
+        acc = (acc << 6) | sextet as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            payload.push((acc >> bits) as u8);
+            acc &= (1 << bits) - 1;
+        }
+    }
+
+    Some(payload)
+}
+
 // ===== Error =====
 
 /// An error that can occur in HTTP/2 settings operation.
@@ -170,3 +204,22 @@ impl std::fmt::Display for SettingsError {
         f.write_str(self.message())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::decode_http2_settings;
+
+    #[test]
+    fn test_decode_http2_settings() {
+        // SETTINGS_MAX_CONCURRENT_STREAMS = 100, SETTINGS_INITIAL_WINDOW_SIZE = 33554432,
+        // SETTINGS_ENABLE_PUSH = 0
+        assert_eq!(
+            decode_http2_settings(b"AAMAAABkAAQCAAAAAAIAAAAA").unwrap(),
+            [0, 3, 0, 0, 0, 100, 0, 4, 2, 0, 0, 0, 0, 2, 0, 0, 0, 0],
+        );
+        assert_eq!(decode_http2_settings(b"").unwrap(), []);
+        assert_eq!(decode_http2_settings(b"_-8").unwrap(), [0xff, 0xef]);
+        assert_eq!(decode_http2_settings(b"_-8=").unwrap(), [0xff, 0xef]);
+        assert!(decode_http2_settings(b"+/8=").is_none());
+    }
+}
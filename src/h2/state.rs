@@ -1,13 +1,14 @@
 use std::task::Poll;
 use tcio::bytes::{Buf, BytesMut};
 
-use crate::h2::error::{ConnectionError, HandshakeError};
+use crate::h2::error::{ConnectionError, ErrorCode, HandshakeError};
 use crate::h2::frame;
 use crate::h2::hpack::Decoder;
 use crate::h2::settings::{self, Settings};
 use crate::h2::stream::{self, StreamList};
-use crate::headers::{HeaderField, HeaderMap};
-use crate::http::{Authority, Target, Method, Scheme};
+use crate::headers::{HeaderField, HeaderMap, HeaderValue, standard};
+use crate::http::{Authority, Target, Method, Scheme, Version, request};
+use crate::log::{debug, trace};
 
 const PREFACE: &[u8; 24] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
 
@@ -17,12 +18,19 @@ pub struct H2State {
     settings: Settings,
     decoder: Decoder,
     streams: StreamList,
+    last_stream_id: u32,
+    /// connection flow control window for sending DATA frames
+    send_window: i64,
 }
 
 pub(crate) enum FrameResult {
     None,
-    Request(u32, HeaderMap),
-    Data(u32, BytesMut),
+    /// New stream is opened, with the END_STREAM flag.
+    Request(u32, request::Parts, bool),
+    /// Request body data of a stream, with the END_STREAM flag.
+    Data(u32, BytesMut, bool),
+    /// Stream is reset, thus its response is no longer needed.
+    Reset(u32),
     Shutdown,
 }
 
@@ -39,6 +47,22 @@ impl H2State {
             decoder: Decoder::with_capacity(settings.header_table_size as usize, 16),
             streams: StreamList::new(settings.max_concurrent_streams as usize),
             settings,
+            last_stream_id: 0,
+            send_window: DEFAULT_WINDOW_SIZE,
+        }
+    }
+
+    /// Check whether given bytes is the HTTP/2 connection preface.
+    ///
+    /// Returns `Pending` if more bytes is required to decide.
+    pub(crate) fn detect_preface(bytes: &[u8]) -> Poll<bool> {
+        let len = bytes.len().min(PREFACE.len());
+        if bytes[..len] != PREFACE[..len] {
+            Poll::Ready(false)
+        } else if len == PREFACE.len() {
+            Poll::Ready(true)
+        } else {
+            Poll::Pending
         }
     }
 
@@ -61,17 +85,155 @@ impl H2State {
         // write_buffer.extend_from_slice(PREFACE);
         write_buffer.extend_from_slice(&frame::Header::EMPTY_SETTINGS);
         read_buffer.advance(PREFACE.len());
-        println!("[HANDSHAKE] ok");
+        trace!("h2 handshake complete");
         Poll::Ready(Ok(()))
     }
 
     pub fn streams_mut(&mut self) -> &mut StreamList {
         &mut self.streams
     }
+
+    /// Write GOAWAY frame with the last processed stream id.
+    pub(crate) fn write_goaway(&self, error: ErrorCode, write_buffer: &mut BytesMut) {
+        let header = frame::Header {
+            len: 8,
+            ty: frame::Type::GoAway as u8,
+            flags: 0,
+            stream_id: 0,
+        };
+        write_buffer.extend_from_slice(&header.encode());
+        write_buffer.extend_from_slice(&self.last_stream_id.to_be_bytes());
+        write_buffer.extend_from_slice(&(error as u32).to_be_bytes());
+    }
+
+    /// Write RST_STREAM frame, and close the stream.
+    pub(crate) fn write_rst_stream(&mut self, stream_id: u32, error: ErrorCode, write_buffer: &mut BytesMut) {
+        self.close_stream(stream_id);
+        let header = frame::Header {
+            len: 4,
+            ty: frame::Type::RstStream as u8,
+            flags: 0,
+            stream_id,
+        };
+        write_buffer.extend_from_slice(&header.encode());
+        write_buffer.extend_from_slice(&(error as u32).to_be_bytes());
+    }
+
+    /// Write HEADERS frame with the encoded field `block`, followed by CONTINUATION frames if the
+    /// block is larger than the maximum frame size.
+    pub(crate) fn write_headers(
+        &mut self,
+        stream_id: u32,
+        mut block: &[u8],
+        is_end_stream: bool,
+        write_buffer: &mut BytesMut,
+    ) {
+        let mut ty = frame::Type::Headers;
+        let mut flags = if is_end_stream { frame::END_STREAM } else { 0 };
+        loop {
+            let (chunk, rest) = block.split_at(block.len().min(MAX_FRAME_SIZE));
+            if rest.is_empty() {
+                flags |= frame::END_HEADERS;
+            }
+            let header = frame::Header {
+                len: chunk.len() as u32,
+                ty: ty as u8,
+                flags,
+                stream_id,
+            };
+            write_buffer.extend_from_slice(&header.encode());
+            write_buffer.extend_from_slice(chunk);
+            if rest.is_empty() {
+                break;
+            }
+            block = rest;
+            ty = frame::Type::Continuation;
+            flags = 0;
+        }
+        if is_end_stream {
+            self.close_stream(stream_id);
+        }
+    }
+
+    /// Write DATA frames of `data` as far as the flow control window allows, split by the maximum
+    /// frame size.
+    ///
+    /// Returns `false` if the window is exhausted, the rest of `data` should be written after the
+    /// window is updated.
+    pub(crate) fn write_data<B: Buf>(
+        &mut self,
+        stream_id: u32,
+        data: &mut B,
+        is_end_stream: bool,
+        write_buffer: &mut BytesMut,
+    ) -> bool {
+        let Some(stream) = self.streams.stream_mut(stream_id) else {
+            // stream is already closed, e.g. it is reset
+            data.advance(data.remaining());
+            return true;
+        };
+        loop {
+            let window = self.send_window.min(stream.send_window()).max(0) as usize;
+            let len = data.remaining().min(window).min(MAX_FRAME_SIZE);
+            let is_last = len == data.remaining();
+            if len == 0 && !(is_last && is_end_stream) {
+                // either there is no data or the window is exhausted
+                return is_last;
+            }
+            let header = frame::Header {
+                len: len as u32,
+                ty: frame::Type::Data as u8,
+                flags: if is_last && is_end_stream { frame::END_STREAM } else { 0 },
+                stream_id,
+            };
+            write_buffer.extend_from_slice(&header.encode());
+
+            let mut remaining = len;
+            while remaining != 0 {
+                let chunk = data.chunk();
+                let cnt = chunk.len().min(remaining);
+                write_buffer.extend_from_slice(&chunk[..cnt]);
+                data.advance(cnt);
+                remaining -= cnt;
+            }
+            self.send_window -= len as i64;
+            *stream.send_window_mut() -= len as i64;
+            if is_last {
+                break;
+            }
+        }
+        if is_end_stream {
+            self.close_stream(stream_id);
+        }
+        true
+    }
+
+    /// Write WINDOW_UPDATE frame.
+    pub(crate) fn write_window_update(&self, stream_id: u32, increment: u32, write_buffer: &mut BytesMut) {
+        let header = frame::Header {
+            len: 4,
+            ty: frame::Type::WindowUpdate as u8,
+            flags: 0,
+            stream_id,
+        };
+        write_buffer.extend_from_slice(&header.encode());
+        write_buffer.extend_from_slice(&increment.to_be_bytes());
+    }
+
+    /// Closed stream is removed, later frames of the stream is recognized by `last_stream_id`.
+    fn close_stream(&mut self, stream_id: u32) {
+        self.streams.remove(stream_id);
+    }
 }
 
 const MAX_FRAME_SIZE: usize = 16_384;
 
+/// Initial flow control window size.
+const DEFAULT_WINDOW_SIZE: i64 = 65_535;
+
+/// Maximum flow control window size, 2^31-1.
+const MAX_WINDOW_SIZE: i64 = (1 << 31) - 1;
+
 impl H2State {
     pub(crate) fn poll_frame(
         &mut self,
@@ -97,169 +259,176 @@ impl H2State {
         use frame::Type as Ty;
         match ty {
             Ty::Headers => {
-                // also get the CONINUATION frames if any
-                let payload = {
-                    let mut bytes = &read_buffer[frame.frame_size()..];
-                    loop {
-                        let Some(frame) = bytes.first_chunk() else {
-                            // CONTINUATION frame have not been read yet
-                            return Ok(None);
-                        };
-                        let frame = frame::Header::decode(frame);
-                        let Some(frame::Type::Continuation) = frame.frame_type() else {
-                            return Err(E::UnexpectedFrame);
-                        };
-                        let Some(rest) = bytes.get(frame.frame_size()..) else {
-                            return Ok(None);
-                        };
-                        if frame.is_end_headers() {
-                            break;
-                        }
-                        bytes = rest;
-                    }
-                    let len = bytes.as_ptr().addr() - read_buffer.as_ptr().addr();
-                    read_buffer.split_to(len).freeze()
-                };
-
-                // get the stream
                 if frame.stream_id == 0 {
                     return Err(E::InvalidStreamId);
                 }
-                let stream = match self.streams.stream_mut(frame.stream_id) {
-                    Some(stream) => {
-                        // stream that idle will be `None`, and other state which can receive
-                        // headers is reserved
-                        if !stream.is_reserved() {
-                            return Err(E::UnexpectedFrame);
-                        }
-                        stream
-                    },
-                    None => {
-                        if frame.stream_id & 1 == 0 {
-                            return Err(E::InvalidStreamId);
-                        }
-                        self.streams.create(frame.stream_id)
-                    },
-                };
-                if frame.is_end_stream() {
-                    stream.set_state(stream::State::HalfClosedRemote);
-                }
 
-                let mut payload = payload;
-                let mut headers = HeaderMap::new();
+                // also get the CONTINUATION frames if any
+                let mut len = frame.frame_size();
+                let mut is_end_headers = frame.is_end_headers();
+                while !is_end_headers {
+                    let Some(next) = read_buffer[len..].first_chunk() else {
+                        // CONTINUATION frame have not been read yet
+                        return Ok(None);
+                    };
+                    let next = frame::Header::decode(next);
+                    let Some(frame::Type::Continuation) = next.frame_type() else {
+                        return Err(E::UnexpectedFrame);
+                    };
+                    if next.stream_id != frame.stream_id {
+                        return Err(E::UnexpectedFrame);
+                    }
+                    if next.len() > MAX_FRAME_SIZE {
+                        return Err(E::ExcessiveFrame);
+                    }
+                    len += next.frame_size();
+                    if read_buffer.len() < len {
+                        return Ok(None);
+                    }
+                    is_end_headers = next.is_end_headers();
+                }
+                let mut payload = read_buffer.split_to(len);
 
-                // ===== Pseudo Headers =====
-                let _reqline = {
-                    let frame = frame::Header::decode(&payload.try_get_chunk().expect("checked"));
+                // field block may be split across CONTINUATION frames
+                let mut block = {
+                    payload.advance(frame::Header::SIZE);
                     let mut block = payload.split_to(frame.len());
-
-                    let mut reqline_builder = ReqlineBuilder::default();
-
-                    loop {
-                        let field = self.decoder.decode(&mut block, write_buffer)?;
-                        if field.name().is_pseudo_header() {
-                            reqline_builder.on_field(field.as_ref())?;
-                        } else {
-                            headers.try_append_field(field.into_owned()).map_err(|_|E::ExcessiveHeaders)?;
-                            break;
+                    strip_padding(&frame, &mut block)?;
+                    if frame.is_priority() {
+                        if block.len() < PRIORITY_LEN {
+                            return Err(E::UnexpectedFrame);
                         }
+                        block.advance(PRIORITY_LEN);
                     }
+                    while let Some(next) = payload.first_chunk() {
+                        let next = frame::Header::decode(next);
+                        payload.advance(frame::Header::SIZE);
+                        block.extend_from_slice(&payload.split_to(next.len()));
+                    }
+                    block.freeze()
+                };
 
-                    // rest of the headers
-                    while !block.is_empty() {
-                        let field = self.decoder.decode(&mut block, write_buffer)?;
-                        if headers.try_append_field(field.into_owned()).is_err() {
-                            return Err(E::ExcessiveHeaders);
+                // get the stream
+                let kind = match self.streams.stream_mut(frame.stream_id) {
+                    // trailers, which ends the request body
+                    Some(stream)
+                        if matches!(stream.state(), stream::State::Open) && frame.is_end_stream() =>
+                    {
+                        stream.set_state(stream::State::HalfClosedRemote);
+                        Block::Trailers
+                    }
+                    Some(_) => return Err(E::UnexpectedFrame),
+                    None if frame.stream_id & 1 == 0 => return Err(E::InvalidStreamId),
+                    // trailers of closed stream, only decoded to keep the decoder state
+                    None if frame.stream_id <= self.last_stream_id => Block::Discard,
+                    None => {
+                        self.last_stream_id = frame.stream_id;
+                        let send_window = self.settings.initial_window_size as i64;
+                        let stream = self.streams.create(frame.stream_id, send_window);
+                        if frame.is_end_stream() {
+                            stream.set_state(stream::State::HalfClosedRemote);
                         }
+                        Block::Request
                     }
-
-                    reqline_builder.build()?
                 };
 
-                // ===== Regular Headers =====
-
-                while let Some(frame) = payload.try_get_chunk() {
-                    let frame = frame::Header::decode(&frame);
-                    let mut block = payload.split_to(frame.len());
-
-                    // println!(
-                    //     "[HEADER] padded={}, end_headers={}, end_stream={}",
-                    //     frame.is_padded(),
-                    //     frame.is_end_headers(),
-                    //     frame.is_end_stream(),
-                    // );
+                let mut buffer = BytesMut::new();
+                let mut reqline = ReqlineBuilder::default();
+                let mut headers = HeaderMap::new();
+                let mut is_regular = false;
 
-                    self.decoder.decode_size_update(&mut block)?;
+                self.decoder.decode_size_update(&mut block)?;
 
-                    while !block.is_empty() {
-                        let field = self.decoder.decode(&mut block, write_buffer)?;
+                while !block.is_empty() {
+                    let field = self.decoder.decode(&mut block, &mut buffer)?;
+                    if !matches!(kind, Block::Request) {
+                        continue;
+                    }
+                    if field.name().is_pseudo_header() {
+                        // pseudo headers must precede regular headers
+                        if is_regular {
+                            return Err(E::Malformed);
+                        }
+                        reqline.on_field(field.as_ref())?;
+                    } else {
+                        is_regular = true;
                         if headers.try_append_field(field.into_owned()).is_err() {
                             return Err(E::ExcessiveHeaders);
                         }
                     }
                 }
 
-                debug_assert!(payload.is_empty());
-
-                Ok(Some(FrameResult::Request(frame.stream_id, headers)))
+                Ok(Some(match kind {
+                    Block::Request => {
+                        let parts = reqline.build(headers)?;
+                        FrameResult::Request(frame.stream_id, parts, frame.is_end_stream())
+                    }
+                    Block::Trailers => FrameResult::Data(frame.stream_id, BytesMut::new(), true),
+                    Block::Discard => FrameResult::None,
+                }))
             }
             Ty::Data => {
-                // get the stream
                 if frame.stream_id == 0 {
                     return Err(E::InvalidStreamId);
                 }
+                read_buffer.advance(frame::Header::SIZE);
+                let mut data = read_buffer.split_to(frame.len());
+
+                // the connection window is restored immediately, while the stream window is
+                // restored as the data is read
+                if !data.is_empty() {
+                    self.write_window_update(0, data.len() as u32, write_buffer);
+                }
+
+                // get the stream
                 match self.streams.stream_mut(frame.stream_id) {
                     Some(stream) => {
-                        if let stream::State::Open | stream::State::HalfClosedRemote = stream.state() {
+                        if !matches!(stream.state(), stream::State::Open) {
                             return Err(E::UnexpectedFrame);
                         }
                         if frame.is_end_stream() {
                             stream.set_state(stream::State::HalfClosedRemote);
                         }
                     },
+                    // stream is already closed, e.g. it is refused
+                    None if frame.stream_id <= self.last_stream_id => return Ok(Some(FrameResult::None)),
                     None => return Err(E::UnexpectedFrame),
                 }
 
-                read_buffer.advance(frame::Header::SIZE);
-                let data = read_buffer.split_to(frame.len());
+                strip_padding(&frame, &mut data)?;
+                let padding = frame.len() - data.len();
+                if padding != 0 && !frame.is_end_stream() {
+                    self.write_window_update(frame.stream_id, padding as u32, write_buffer);
+                }
 
-                Ok(Some(FrameResult::Data(frame.stream_id, data)))
+                Ok(Some(FrameResult::Data(frame.stream_id, data, frame.is_end_stream())))
             }
             Ty::RstStream => {
                 // get the stream
                 if frame.stream_id == 0 {
                     return Err(E::InvalidStreamId);
                 }
-                match self.streams.stream_mut(frame.stream_id) {
-                    Some(stream) => stream.set_state(stream::State::Closed),
-                    None => return Err(E::UnexpectedFrame),
+                read_buffer.advance(frame.frame_size());
+                if self.streams.stream_mut(frame.stream_id).is_none() {
+                    return if frame.stream_id <= self.last_stream_id {
+                        // stream is already closed
+                        Ok(Some(FrameResult::None))
+                    } else {
+                        Err(E::UnexpectedFrame)
+                    };
                 }
+                self.close_stream(frame.stream_id);
 
-                Ok(Some(FrameResult::None))
+                Ok(Some(FrameResult::Reset(frame.stream_id)))
             }
             Ty::Settings => {
-
+                // acknowledgement of our settings is not acknowledged back
                 if !frame.is_ack() {
-                    let mut payload = &read_buffer[
-                        frame::Header::SIZE..frame::Header::SIZE + frame.len()
-                    ];
-
-                    while let Some((chunk, rest)) = payload.split_first_chunk() {
-                        let (id, val) = split_exact::<{ size_of::<u16>() + size_of::<u32>() }, _, _>(chunk);
-                        let id = u16::from_be_bytes(*id);
-                        let val = u32::from_be_bytes(*val);
-
-                        let Some(id) = settings::Id::from_u16(id) else {
-                            return Err(E::UnknownSetting);
-                        };
-
-                        println!("[SETTINGS] {id:?} = {val}");
-                        self.settings.set_by_id(id, val);
-                        payload = rest;
-                    }
+                    self.apply_settings(
+                        &read_buffer[frame::Header::SIZE..frame::Header::SIZE + frame.len()],
+                    )?;
+                    write_buffer.extend_from_slice(&frame::Header::ACK_SETTINGS);
                 }
-
-                write_buffer.extend_from_slice(&frame::Header::ACK_SETTINGS);
                 read_buffer.advance(frame.frame_size());
                 Ok(Some(FrameResult::None))
             },
@@ -276,15 +445,46 @@ impl H2State {
                 Ok(Some(FrameResult::None))
             }
             Ty::WindowUpdate => {
+                if frame.len() != 4 {
+                    return Err(E::UnexpectedFrame);
+                }
+                let increment = read_buffer[frame::Header::SIZE..].first_chunk().expect("checked");
+                let increment = (u32::from_be_bytes(*increment) & u32::MAX >> 1) as i64;
                 read_buffer.advance(frame.frame_size());
-                Ok(Some(FrameResult::None))
+
+                if frame.stream_id == 0 {
+                    if increment == 0 {
+                        return Err(E::UnexpectedFrame);
+                    }
+                    self.send_window += increment;
+                    if self.send_window > MAX_WINDOW_SIZE {
+                        return Err(E::FlowControl);
+                    }
+                    return Ok(Some(FrameResult::None));
+                }
+
+                let Some(stream) = self.streams.stream_mut(frame.stream_id) else {
+                    // stream is already closed
+                    return Ok(Some(FrameResult::None));
+                };
+                let window = stream.send_window_mut();
+                *window += increment;
+                let error = if increment == 0 {
+                    ErrorCode::ProtocolError
+                } else if *window > MAX_WINDOW_SIZE {
+                    ErrorCode::FlowControlError
+                } else {
+                    return Ok(Some(FrameResult::None));
+                };
+                self.write_rst_stream(frame.stream_id, error, write_buffer);
+                Ok(Some(FrameResult::Reset(frame.stream_id)))
             }
             Ty::GoAway => {
                 read_buffer.advance(frame.frame_size());
                 Ok(Some(FrameResult::Shutdown))
             }
             Ty::Priority => {
-                println!("[PRIORITY] priority frame are not supported");
+                debug!("h2 priority frame are not supported");
                 read_buffer.advance(frame.frame_size());
                 Ok(Some(FrameResult::None))
             }
@@ -296,6 +496,78 @@ impl H2State {
     }
 }
 
+impl H2State {
+    /// Apply SETTINGS frame payload.
+    fn apply_settings(&mut self, mut payload: &[u8]) -> Result<(), ConnectionError> {
+        while let Some((chunk, rest)) = payload.split_first_chunk() {
+            let (id, val) = split_exact::<{ size_of::<u16>() + size_of::<u32>() }, _, _>(chunk);
+            let id = u16::from_be_bytes(*id);
+            let val = u32::from_be_bytes(*val);
+
+            let Some(id) = settings::Id::from_u16(id) else {
+                return Err(ConnectionError::UnknownSetting);
+            };
+
+            trace!("h2 setting {id:?} = {val}");
+            if let settings::Id::InitialWindowSize = id {
+                if val as i64 > MAX_WINDOW_SIZE {
+                    return Err(ConnectionError::FlowControl);
+                }
+                // the change applies to the window of all streams
+                let delta = val as i64 - self.settings.initial_window_size as i64;
+                for stream in self.streams.iter_mut() {
+                    *stream.send_window_mut() += delta;
+                    if stream.send_window() > MAX_WINDOW_SIZE {
+                        return Err(ConnectionError::FlowControl);
+                    }
+                }
+            }
+            self.settings.set_by_id(id, val);
+            payload = rest;
+        }
+        Ok(())
+    }
+
+    /// Apply the state of HTTP/1.1 `h2c` upgrade.
+    ///
+    /// `settings` is the decoded `HTTP2-Settings` header value, which is applied as the client
+    /// settings. The upgrading request is assigned as stream 1 in half-closed (remote) state.
+    pub(crate) fn upgrade(&mut self, settings: &[u8]) -> Result<(), ConnectionError> {
+        self.apply_settings(settings)?;
+        self.last_stream_id = 1;
+        let send_window = self.settings.initial_window_size as i64;
+        self.streams.create(1, send_window).set_state(stream::State::HalfClosedRemote);
+        Ok(())
+    }
+}
+
+/// Length of the stream dependency and weight in HEADERS frame with PRIORITY flag.
+const PRIORITY_LEN: usize = 5;
+
+/// The kind of HEADERS frame field block.
+enum Block {
+    Request,
+    Trailers,
+    Discard,
+}
+
+/// Remove the padding of DATA or HEADERS frame payload.
+fn strip_padding(frame: &frame::Header, payload: &mut BytesMut) -> Result<(), ConnectionError> {
+    if !frame.is_padded() {
+        return Ok(());
+    }
+    let Some(&pad_len) = payload.first() else {
+        return Err(ConnectionError::UnexpectedFrame);
+    };
+    let pad_len = pad_len as usize;
+    if pad_len >= payload.len() {
+        return Err(ConnectionError::UnexpectedFrame);
+    }
+    payload.advance(1);
+    payload.truncate(payload.len() - pad_len);
+    Ok(())
+}
+
 fn split_exact<const S: usize, const M: usize, const N: usize>(bytes: &[u8; S]) -> (&[u8; M], &[u8; N]) {
     assert_eq!(M + N, S);
     let chunk1 = bytes[..M].try_into().expect("known size");
@@ -358,25 +630,30 @@ impl ReqlineBuilder {
         Ok(())
     }
 
-    fn build(self) -> Result<(Method, BytesMut), ConnectionError> {
-        // use ConnectionError as E;
-        //
-        // let Self {
-        //     method: Some(_method),
-        //     path: Some(path),
-        //     scheme: Some(scheme),
-        //     authority: Some(authority),
-        // } = self
-        // else {
-        //     return Err(E::Malformed);
-        // };
-        // let _target = HttpUri::from_parts(
-        //     scheme,
-        //     Host::from_slice(authority.host()).map_err(|_| E::Malformed)?,
-        //     path,
-        // );
-        // Ok(Reqline { method, target, version: crate::http::Version::HTTP_2 })
-        todo!("continue h2")
+    /// Build the request parts, `:authority` is used as the `Host` header if it is absent.
+    fn build(self, mut headers: HeaderMap) -> Result<request::Parts, ConnectionError> {
+        let Self {
+            method: Some(method),
+            path: Some(target),
+            scheme: Some(scheme),
+            authority,
+        } = self
+        else {
+            return Err(ConnectionError::Malformed);
+        };
+        if let Some(authority) = authority
+            && !headers.contains_key(standard::HOST)
+        {
+            headers.insert(standard::HOST, HeaderValue::from_slice(authority.as_str())?);
+        }
+        Ok(request::Parts {
+            method,
+            scheme,
+            target,
+            version: Version::HTTP_2,
+            headers,
+            ..Default::default()
+        })
     }
 }
 
@@ -17,13 +17,16 @@ pub enum State {
 pub struct Stream {
     id: u32,
     state: State,
+    /// flow control window for sending DATA frames, can be negative after SETTINGS change
+    send_window: i64,
 }
 
 impl Stream {
-    pub fn new(id: u32) -> Self {
+    pub fn new(id: u32, send_window: i64) -> Self {
         Self {
             id,
             state: State::Open,
+            send_window,
         }
     }
 
@@ -38,26 +41,46 @@ impl Stream {
     pub fn is_reserved(&self) -> bool {
         matches!(self.state, State::ReservedLocal | State::ReservedRemote)
     }
+
+    pub fn send_window(&self) -> i64 {
+        self.send_window
+    }
+
+    pub fn send_window_mut(&mut self) -> &mut i64 {
+        &mut self.send_window
+    }
 }
 
 // ===== Stream List =====
 
 #[derive(Debug)]
 pub struct StreamList {
+    streams: Vec<Stream>,
     max_stream: usize,
 }
 
 impl StreamList {
     pub fn new(max_stream: usize) -> Self {
-        Self { max_stream }
+        Self { streams: Vec::new(), max_stream }
     }
 
     pub fn stream_mut(&mut self, id: u32) -> Option<&mut Stream> {
-        todo!()
+        self.streams.iter_mut().find(|stream| stream.id == id)
     }
 
-    pub fn create(&mut self, id: u32) -> &mut Stream {
-        todo!()
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Stream> {
+        self.streams.iter_mut()
+    }
+
+    pub fn create(&mut self, id: u32, send_window: i64) -> &mut Stream {
+        self.streams.push(Stream::new(id, send_window));
+        self.streams.last_mut().expect("just pushed")
+    }
+
+    pub fn remove(&mut self, id: u32) {
+        if let Some(index) = self.streams.iter().position(|stream| stream.id == id) {
+            self.streams.swap_remove(index);
+        }
     }
 }
 
@@ -0,0 +1,97 @@
+use tcio::bytes::Bytes;
+use tokio::io::{AsyncWriteExt, DuplexStream};
+
+use crate::body::{Full, Incoming};
+use crate::h2::Connection;
+use crate::http::{Request, Response};
+use crate::service::{HttpService, from_fn};
+use crate::testing::{Frame, Gate, block_on, read_frame, read_response};
+
+const DATA: u8 = 0x0;
+const HEADERS: u8 = 0x1;
+const RST_STREAM: u8 = 0x3;
+const END_STREAM: u8 = 0x1;
+const END_HEADERS: u8 = 0x4;
+
+/// Connection preface, followed by empty SETTINGS frame.
+const PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n\0\0\0\x04\0\0\0\0\0";
+
+async fn hello(_: Request<Incoming>) -> Response<Full<Bytes>> {
+    Response::from_parts(Default::default(), Full::new(Bytes::from_static(b"hello")))
+}
+
+/// Spawn a connection serving `service`, returns the client side after the connection preface.
+async fn serve<S>(service: S) -> DuplexStream
+where
+    S: HttpService,
+    Connection<S, DuplexStream>: Send + 'static,
+{
+    let (mut client, server) = tokio::io::duplex(1024);
+    tokio::spawn(Connection::new(service, server));
+    client.write_all(PREFACE).await.unwrap();
+    client
+}
+
+/// Write HEADERS frame of `GET /` request without body.
+async fn write_request(io: &mut DuplexStream, stream_id: u32) {
+    // `:method: GET`, `:scheme: http` and `:path: /` are indexed in hpack static table
+    let mut frame = vec![0, 0, 3, HEADERS, END_HEADERS | END_STREAM];
+    frame.extend_from_slice(&stream_id.to_be_bytes());
+    frame.extend_from_slice(&[0x82, 0x86, 0x84]);
+    io.write_all(&frame).await.unwrap();
+}
+
+/// Read frames until a frame of type `ty` is received.
+async fn read_frame_of(io: &mut DuplexStream, ty: u8) -> Frame {
+    loop {
+        let frame = read_frame(io).await;
+        if frame.ty == ty {
+            return frame;
+        }
+    }
+}
+
+#[test]
+fn test_refuse_stream_while_busy() {
+    block_on(async {
+        let gate = Gate::default();
+        let mut io = serve(gate.clone()).await;
+
+        // the service is not ready, thus the stream is refused without processing
+        write_request(&mut io, 1).await;
+        let reset = read_frame_of(&mut io, RST_STREAM).await;
+        assert_eq!(reset.stream_id, 1);
+        // REFUSED_STREAM
+        assert_eq!(reset.payload, 0x7u32.to_be_bytes());
+
+        // new stream is served once the service is ready
+        gate.open();
+        write_request(&mut io, 3).await;
+        let (block, data) = read_response(&mut io, 3).await;
+        // `:status: 200` is indexed in hpack static table
+        assert_eq!(block.first(), Some(&0x88));
+        assert_eq!(data, b"hello");
+    });
+}
+
+#[test]
+fn test_flow_control() {
+    block_on(async {
+        let mut io = serve(from_fn(hello)).await;
+
+        // SETTINGS_INITIAL_WINDOW_SIZE = 2
+        io.write_all(b"\0\0\x06\x04\0\0\0\0\0\0\x04\0\0\0\x02").await.unwrap();
+        write_request(&mut io, 1).await;
+
+        // the response body is written as far as the stream window allows
+        let data = read_frame_of(&mut io, DATA).await;
+        assert_eq!(data.payload, b"he");
+        assert_eq!(data.flags & END_STREAM, 0);
+
+        // the rest is written after WINDOW_UPDATE of the stream
+        io.write_all(b"\0\0\x04\x08\0\0\0\0\x01\0\0\0\x03").await.unwrap();
+        let data = read_frame_of(&mut io, DATA).await;
+        assert_eq!(data.payload, b"llo");
+        assert_eq!(data.flags & END_STREAM, END_STREAM);
+    });
+}
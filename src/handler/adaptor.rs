@@ -0,0 +1,172 @@
+use std::convert::Infallible;
+use std::marker::PhantomData;
+use tcio::bytes::Bytes;
+
+use super::{FromRequest, FromRequestParts, IntoResponse};
+use crate::body::{Full, Incoming};
+use crate::http::{Request, Response};
+use crate::service::{BoxFuture, Service};
+
+// ===== Handler =====
+
+/// Async function that accepts extractors as arguments and returns [`IntoResponse`].
+///
+/// Handler can accept up to 8 arguments. All arguments except the last one must implement
+/// [`FromRequestParts`], and the last argument must implement [`FromRequest`]. If an extractor
+/// failed, its rejection is returned as the response and the function is not called.
+///
+/// # Examples
+///
+/// ```
+/// use tsue::handler::{Handler, Query};
+/// use tsue::http::{Method, StatusCode};
+/// use tsue::routing::{Params, Router};
+///
+/// async fn update(params: Params, query: Query, body: String) -> (StatusCode, String) {
+///     let id = params.get("id").unwrap_or_default();
+///     let force = query.get("force").is_some();
+///     (StatusCode::OK, format!("update {id} with {body}, force: {force}"))
+/// }
+///
+/// let router = Router::new().route(Method::PUT, "/users/:id", update.into_service());
+/// ```
+pub trait Handler<T>: Clone + Send + Sized + 'static {
+    /// Call the handler with the request.
+    fn call(self, request: Request<Incoming>) -> BoxFuture<Response<Full<Bytes>>, Infallible>;
+
+    /// Convert the handler into a [`Service`].
+    #[inline]
+    fn into_service(self) -> HandlerService<Self, T> {
+        HandlerService {
+            handler: self,
+            _args: PhantomData,
+        }
+    }
+}
+
+impl<F, Fut> Handler<()> for F
+where
+    F: FnOnce() -> Fut + Clone + Send + 'static,
+    Fut: Future<Output: IntoResponse> + Send + 'static,
+{
+    fn call(self, _: Request<Incoming>) -> BoxFuture<Response<Full<Bytes>>, Infallible> {
+        Box::pin(async move { Ok(self().await.into_response()) })
+    }
+}
+
+macro_rules! handler {
+    ([$($ty:ident),*], $last:ident) => {
+        impl<F, Fut, $($ty,)* $last> Handler<($($ty,)* $last,)> for F
+        where
+            F: FnOnce($($ty,)* $last) -> Fut + Clone + Send + 'static,
+            Fut: Future<Output: IntoResponse> + Send + 'static,
+            $($ty: FromRequestParts + Send + 'static,)*
+            $last: FromRequest + Send + 'static,
+        {
+            #[allow(non_snake_case, unused_mut)]
+            fn call(self, request: Request<Incoming>) -> BoxFuture<Response<Full<Bytes>>, Infallible> {
+                Box::pin(async move {
+                    let (mut parts, body) = request.into_parts();
+                    $(
+                        let $ty = match $ty::from_request_parts(&mut parts) {
+                            Ok(ok) => ok,
+                            Err(rejection) => return Ok(rejection.into_response()),
+                        };
+                    )*
+                    let $last = match $last::from_request(Request::from_parts(parts, body)).await {
+                        Ok(ok) => ok,
+                        Err(rejection) => return Ok(rejection.into_response()),
+                    };
+                    Ok(self($($ty,)* $last).await.into_response())
+                })
+            }
+        }
+    };
+}
+
+handler!([], T1);
+handler!([T1], T2);
+handler!([T1, T2], T3);
+handler!([T1, T2, T3], T4);
+handler!([T1, T2, T3, T4], T5);
+handler!([T1, T2, T3, T4, T5], T6);
+handler!([T1, T2, T3, T4, T5, T6], T7);
+handler!([T1, T2, T3, T4, T5, T6, T7], T8);
+
+// ===== HandlerService =====
+
+/// Service returned from [`Handler::into_service`].
+pub struct HandlerService<H, T> {
+    handler: H,
+    _args: PhantomData<fn() -> T>,
+}
+
+impl<H, T> Service<Request<Incoming>> for HandlerService<H, T>
+where
+    H: Handler<T>,
+{
+    type Response = Response<Full<Bytes>>;
+
+    type Error = Infallible;
+
+    type Future = BoxFuture<Response<Full<Bytes>>, Infallible>;
+
+    #[inline]
+    fn call(&self, request: Request<Incoming>) -> Self::Future {
+        self.handler.clone().call(request)
+    }
+}
+
+impl<H: Clone, T> Clone for HandlerService<H, T> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self {
+            handler: self.handler.clone(),
+            _args: PhantomData,
+        }
+    }
+}
+
+impl<H, T> std::fmt::Debug for HandlerService<H, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HandlerService").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::handler::{BodyLimit, Query};
+    use crate::http::{Method, StatusCode, Target, request};
+    use crate::testing::block_on;
+
+    async fn handler(method: Method, query: Query, body: String) -> StatusCode {
+        if method == Method::POST && query.get("a") == Some("1") && body == "hello" {
+            StatusCode::OK
+        } else {
+            StatusCode::BAD_REQUEST
+        }
+    }
+
+    fn call(target: &'static str, limit: Option<usize>) -> StatusCode {
+        let mut parts = request::Parts {
+            method: Method::POST,
+            target: Target::from_static(target.as_bytes()),
+            ..Default::default()
+        };
+        if let Some(limit) = limit {
+            parts.extensions.insert(BodyLimit(limit));
+        }
+        let request = Request::from_parts(parts, Incoming::new("hello"));
+        let response = block_on(handler.into_service().call(request)).unwrap();
+        *response.status()
+    }
+
+    #[test]
+    fn test_handler() {
+        assert_eq!(call("/?a=1", None), StatusCode::OK);
+        assert_eq!(call("/?a=2", None), StatusCode::BAD_REQUEST);
+        assert_eq!(call("/?a=%zz", None), StatusCode::BAD_REQUEST);
+        assert_eq!(call("/?a=1", Some(4)), StatusCode::CONTENT_TOO_LARGE);
+    }
+}
@@ -0,0 +1,208 @@
+use std::convert::Infallible;
+use tcio::bytes::{Bytes, BytesMut};
+
+use super::IntoResponse;
+use crate::body::{Full, Incoming};
+use crate::headers::HeaderMap;
+use crate::http::{Method, Request, Response, StatusCode, Target, request};
+use crate::routing::Params;
+
+/// Default limit of the collected request body, which is 2MiB.
+pub const DEFAULT_BODY_LIMIT: usize = 2 * 1024 * 1024;
+
+// ===== FromRequestParts =====
+
+/// Type that can be extracted from [`request::Parts`].
+///
+/// Any number of parts extractors can be used as [`Handler`][super::Handler] arguments.
+pub trait FromRequestParts: Sized {
+    /// Response returned when extraction failed.
+    type Rejection: IntoResponse;
+
+    /// Extract `Self` from request parts.
+    fn from_request_parts(parts: &mut request::Parts) -> Result<Self, Self::Rejection>;
+}
+
+// ===== FromRequest =====
+
+/// Type that can be extracted from the whole [`Request`], including the body.
+///
+/// Because the body can only be consumed once, only the last [`Handler`][super::Handler]
+/// argument can be a request extractor. All [`FromRequestParts`] types are also request
+/// extractors.
+pub trait FromRequest: Sized {
+    /// Response returned when extraction failed.
+    type Rejection: IntoResponse;
+
+    /// Extract `Self` from request.
+    fn from_request(
+        request: Request<Incoming>,
+    ) -> impl Future<Output = Result<Self, Self::Rejection>> + Send;
+}
+
+impl<T> FromRequest for T
+where
+    T: FromRequestParts + Send,
+    T::Rejection: Send,
+{
+    type Rejection = T::Rejection;
+
+    async fn from_request(request: Request<Incoming>) -> Result<Self, Self::Rejection> {
+        let (mut parts, _) = request.into_parts();
+        T::from_request_parts(&mut parts)
+    }
+}
+
+// ===== Rejection =====
+
+/// Extraction failure, responded with the status and message as text body.
+#[derive(Debug, Clone, Copy)]
+pub struct Rejection {
+    status: StatusCode,
+    message: &'static str,
+}
+
+impl Rejection {
+    pub(crate) const fn new(status: StatusCode, message: &'static str) -> Self {
+        Self { status, message }
+    }
+
+    /// Returns the response status.
+    #[inline]
+    pub const fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// Returns the failure message.
+    #[inline]
+    pub const fn message(&self) -> &'static str {
+        self.message
+    }
+}
+
+impl IntoResponse for Rejection {
+    #[inline]
+    fn into_response(self) -> Response<Full<Bytes>> {
+        (self.status, self.message).into_response()
+    }
+}
+
+impl std::error::Error for Rejection { }
+
+impl std::fmt::Display for Rejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.message)
+    }
+}
+
+// ===== Parts Extractors =====
+
+impl FromRequestParts for Method {
+    type Rejection = Infallible;
+
+    #[inline]
+    fn from_request_parts(parts: &mut request::Parts) -> Result<Self, Self::Rejection> {
+        Ok(parts.method)
+    }
+}
+
+impl FromRequestParts for Target {
+    type Rejection = Infallible;
+
+    #[inline]
+    fn from_request_parts(parts: &mut request::Parts) -> Result<Self, Self::Rejection> {
+        Ok(parts.target.clone())
+    }
+}
+
+impl FromRequestParts for HeaderMap {
+    type Rejection = Infallible;
+
+    #[inline]
+    fn from_request_parts(parts: &mut request::Parts) -> Result<Self, Self::Rejection> {
+        Ok(parts.headers.clone())
+    }
+}
+
+/// Path parameters captured by [`Router`][crate::routing::Router].
+///
+/// Rejected with `500 Internal Server Error` if the request is not routed by a router.
+impl FromRequestParts for Params {
+    type Rejection = Rejection;
+
+    fn from_request_parts(parts: &mut request::Parts) -> Result<Self, Self::Rejection> {
+        match parts.extensions.get::<Params>() {
+            Some(params) => Ok(params.clone()),
+            None => Err(Rejection::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "missing path parameters",
+            )),
+        }
+    }
+}
+
+// ===== Request Extractors =====
+
+impl FromRequest for Request<Incoming> {
+    type Rejection = Infallible;
+
+    async fn from_request(request: Request<Incoming>) -> Result<Self, Self::Rejection> {
+        Ok(request)
+    }
+}
+
+/// Maximum size of the collected request body.
+///
+/// Insert this into the request extensions to override [`DEFAULT_BODY_LIMIT`] of the [`Bytes`]
+/// and [`String`] extractors.
+#[derive(Debug, Clone, Copy)]
+pub struct BodyLimit(pub usize);
+
+/// Collect the request body.
+///
+/// Rejected with `413 Content Too Large` if the body exceeds the [`BodyLimit`], or
+/// `400 Bad Request` if reading the body failed.
+impl FromRequest for Bytes {
+    type Rejection = Rejection;
+
+    async fn from_request(request: Request<Incoming>) -> Result<Self, Self::Rejection> {
+        const TOO_LARGE: Rejection =
+            Rejection::new(StatusCode::CONTENT_TOO_LARGE, "request body too large");
+
+        let limit = request
+            .extensions()
+            .get::<BodyLimit>()
+            .map_or(DEFAULT_BODY_LIMIT, |limit| limit.0);
+        let mut body = request.into_body();
+
+        if body.size_hint().0 > limit as u64 {
+            return Err(TOO_LARGE);
+        }
+
+        let mut buffer = BytesMut::new();
+        while let Some(result) = body.read().await {
+            let Ok(data) = result else {
+                return Err(Rejection::new(StatusCode::BAD_REQUEST, "failed to read request body"));
+            };
+            if buffer.len() + data.len() > limit {
+                return Err(TOO_LARGE);
+            }
+            buffer.extend_from_slice(&data);
+        }
+        Ok(buffer.freeze())
+    }
+}
+
+/// Collect the request body as UTF-8 text.
+///
+/// Rejected like [`Bytes`], or with `400 Bad Request` if the body is not valid UTF-8.
+impl FromRequest for String {
+    type Rejection = Rejection;
+
+    async fn from_request(request: Request<Incoming>) -> Result<Self, Self::Rejection> {
+        let bytes = Bytes::from_request(request).await?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| {
+            Rejection::new(StatusCode::BAD_REQUEST, "request body is not valid UTF-8")
+        })
+    }
+}
@@ -0,0 +1,180 @@
+use std::convert::Infallible;
+use tcio::bytes::Bytes;
+
+use crate::body::Full;
+use crate::headers::{HeaderMap, HeaderValue, standard};
+use crate::http::{Response, StatusCode};
+
+const TEXT_PLAIN: &[u8] = b"text/plain; charset=utf-8";
+const OCTET_STREAM: &[u8] = b"application/octet-stream";
+
+/// Type that can be converted into a [`Response`].
+///
+/// Text body is sent with `text/plain` content type, while bytes body is sent with
+/// `application/octet-stream` content type.
+///
+/// # Examples
+///
+/// ```
+/// use tsue::handler::IntoResponse;
+/// use tsue::headers::{HeaderMap, HeaderValue, standard};
+/// use tsue::http::StatusCode;
+///
+/// let response = (StatusCode::CREATED, "created").into_response();
+/// assert_eq!(response.status(), &StatusCode::CREATED);
+///
+/// let mut headers = HeaderMap::new();
+/// headers.insert(standard::CONTENT_TYPE, HeaderValue::from_static(b"text/html"));
+/// let response = (StatusCode::OK, headers, "<h1>Hello</h1>").into_response();
+/// assert_eq!(response.headers().get(standard::CONTENT_TYPE).unwrap(), "text/html");
+/// ```
+pub trait IntoResponse {
+    /// Convert `self` into [`Response`].
+    fn into_response(self) -> Response<Full<Bytes>>;
+}
+
+impl IntoResponse for Response<Full<Bytes>> {
+    #[inline]
+    fn into_response(self) -> Response<Full<Bytes>> {
+        self
+    }
+}
+
+impl IntoResponse for Infallible {
+    #[inline]
+    fn into_response(self) -> Response<Full<Bytes>> {
+        match self { }
+    }
+}
+
+impl IntoResponse for () {
+    #[inline]
+    fn into_response(self) -> Response<Full<Bytes>> {
+        Response::from_parts(Default::default(), Full::new(Bytes::new()))
+    }
+}
+
+impl IntoResponse for StatusCode {
+    #[inline]
+    fn into_response(self) -> Response<Full<Bytes>> {
+        let mut response = ().into_response();
+        *response.status_mut() = self;
+        response
+    }
+}
+
+// ===== Body =====
+
+fn with_content_type(body: Bytes, content_type: &'static [u8]) -> Response<Full<Bytes>> {
+    let mut response = Response::from_parts(Default::default(), Full::new(body));
+    let content_type = HeaderValue::from_static(content_type);
+    response.headers_mut().insert(standard::CONTENT_TYPE, content_type);
+    response
+}
+
+impl IntoResponse for &'static str {
+    #[inline]
+    fn into_response(self) -> Response<Full<Bytes>> {
+        with_content_type(Bytes::from_static(self.as_bytes()), TEXT_PLAIN)
+    }
+}
+
+impl IntoResponse for String {
+    #[inline]
+    fn into_response(self) -> Response<Full<Bytes>> {
+        with_content_type(Bytes::from(self), TEXT_PLAIN)
+    }
+}
+
+impl IntoResponse for Vec<u8> {
+    #[inline]
+    fn into_response(self) -> Response<Full<Bytes>> {
+        with_content_type(Bytes::from(self), OCTET_STREAM)
+    }
+}
+
+impl IntoResponse for Bytes {
+    #[inline]
+    fn into_response(self) -> Response<Full<Bytes>> {
+        with_content_type(self, OCTET_STREAM)
+    }
+}
+
+// ===== Combinations =====
+
+impl<T: IntoResponse> IntoResponse for (StatusCode, T) {
+    #[inline]
+    fn into_response(self) -> Response<Full<Bytes>> {
+        let mut response = self.1.into_response();
+        *response.status_mut() = self.0;
+        response
+    }
+}
+
+/// Headers in the [`HeaderMap`] replace the headers of the same name set by the body.
+impl<T: IntoResponse> IntoResponse for (StatusCode, HeaderMap, T) {
+    fn into_response(self) -> Response<Full<Bytes>> {
+        let (status, headers, body) = self;
+        let mut response = (status, body).into_response();
+        let target = response.headers_mut();
+        for (name, _) in headers.pairs() {
+            target.remove(name);
+        }
+        for (name, value) in headers.pairs() {
+            target.append(name.clone(), value.clone());
+        }
+        response
+    }
+}
+
+impl<T, E> IntoResponse for Result<T, E>
+where
+    T: IntoResponse,
+    E: IntoResponse,
+{
+    #[inline]
+    fn into_response(self) -> Response<Full<Bytes>> {
+        match self {
+            Ok(ok) => ok.into_response(),
+            Err(err) => err.into_response(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_into_response() {
+        let response = StatusCode::NOT_FOUND.into_response();
+        assert_eq!(response.status(), &StatusCode::NOT_FOUND);
+        assert!(response.headers().get(standard::CONTENT_TYPE).is_none());
+
+        let response = String::from("text").into_response();
+        assert_eq!(response.status(), &StatusCode::OK);
+        assert_eq!(
+            response.headers().get(standard::CONTENT_TYPE).unwrap(),
+            "text/plain; charset=utf-8"
+        );
+
+        let response = (StatusCode::BAD_REQUEST, vec![1, 2, 3]).into_response();
+        assert_eq!(response.status(), &StatusCode::BAD_REQUEST);
+        assert_eq!(
+            response.headers().get(standard::CONTENT_TYPE).unwrap(),
+            "application/octet-stream"
+        );
+
+        let mut headers = HeaderMap::new();
+        headers.append(standard::CONTENT_TYPE, HeaderValue::from_static(b"application/json"));
+        let response = (StatusCode::CREATED, headers, "{}").into_response();
+        assert_eq!(response.status(), &StatusCode::CREATED);
+        assert_eq!(
+            response.headers().get(standard::CONTENT_TYPE).unwrap(),
+            "application/json"
+        );
+
+        let result: Result<&str, StatusCode> = Err(StatusCode::FORBIDDEN);
+        assert_eq!(result.into_response().status(), &StatusCode::FORBIDDEN);
+    }
+}
@@ -0,0 +1,72 @@
+//! Ergonomic request handlers.
+//!
+//! - [`IntoResponse`] convert handler output into a response
+//! - [`FromRequestParts`] and [`FromRequest`] extract handler arguments from a request
+//! - [`Handler`] async function with extractors as arguments, usable as a service
+//! - [`handler_fn`] create a service from an async function that returns [`IntoResponse`]
+use std::convert::Infallible;
+use tcio::bytes::Bytes;
+use tcio::futures::{Map, map};
+
+use crate::body::Full;
+use crate::http::Response;
+use crate::service::Service;
+
+mod into_response;
+mod extract;
+mod query;
+mod adaptor;
+
+pub use into_response::IntoResponse;
+pub use extract::{BodyLimit, DEFAULT_BODY_LIMIT, FromRequest, FromRequestParts, Rejection};
+pub use query::Query;
+pub use adaptor::{Handler, HandlerService};
+
+// ===== HandlerFn =====
+
+/// Create a [`Service`] from an async function that returns [`IntoResponse`].
+///
+/// This is like [`from_fn`][crate::service::from_fn], except that the function does not need to
+/// build the [`Response`] manually.
+///
+/// # Examples
+///
+/// ```
+/// use tsue::body::Incoming;
+/// use tsue::handler::handler_fn;
+/// use tsue::http::{Request, StatusCode};
+///
+/// let service = handler_fn(|request: Request<Incoming>| async move {
+///     match request.target().path() {
+///         "/" => Ok("Hello World"),
+///         _ => Err(StatusCode::NOT_FOUND),
+///     }
+/// });
+/// ```
+#[inline]
+pub fn handler_fn<F>(f: F) -> HandlerFn<F> {
+    HandlerFn { f }
+}
+
+/// Service returned from [`handler_fn`].
+#[derive(Debug, Clone, Default)]
+pub struct HandlerFn<F> {
+    f: F,
+}
+
+impl<F, Fut, Req> Service<Req> for HandlerFn<F>
+where
+    F: Fn(Req) -> Fut,
+    Fut: Future<Output: IntoResponse>,
+{
+    type Response = Response<Full<Bytes>>;
+
+    type Error = Infallible;
+
+    type Future = Map<Fut, fn(Fut::Output) -> Result<Response<Full<Bytes>>, Infallible>>;
+
+    #[inline]
+    fn call(&self, request: Req) -> Self::Future {
+        map((self.f)(request), |output| Ok(output.into_response()))
+    }
+}
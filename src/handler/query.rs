@@ -0,0 +1,102 @@
+use super::{FromRequestParts, Rejection};
+use crate::http::{StatusCode, percent_decode, request};
+
+/// Decoded query component of the request target.
+///
+/// The query is decoded as `application/x-www-form-urlencoded`, where `+` is decoded as space.
+///
+/// Rejected with `400 Bad Request` if the query contains invalid percent encoding or the decoded
+/// value is not valid UTF-8.
+///
+/// # Examples
+///
+/// ```
+/// use tsue::handler::Query;
+///
+/// let query = Query::parse("name=ferret&color=purple+blue&tag=a&tag=b").unwrap();
+/// assert_eq!(query.get("name"), Some("ferret"));
+/// assert_eq!(query.get("color"), Some("purple blue"));
+/// assert_eq!(query.get_all("tag").collect::<Vec<_>>(), ["a", "b"]);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Query {
+    pairs: Vec<(String, String)>,
+}
+
+impl Query {
+    /// Decode a query string, returns `None` if decoding failed.
+    pub fn parse(query: &str) -> Option<Self> {
+        let mut pairs = Vec::new();
+        for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            pairs.push((percent_decode(name, true)?, percent_decode(value, true)?));
+        }
+        Some(Self { pairs })
+    }
+
+    /// Returns the first value of `name`.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.pairs
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Returns all values of `name`.
+    pub fn get_all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> {
+        self.pairs
+            .iter()
+            .filter(move |(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Returns an iterator over name and value, in the order they appear in the query.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.pairs.iter().map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+
+    /// Returns the number of pairs.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.pairs.len()
+    }
+
+    /// Returns `true` if there is no pair.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.pairs.is_empty()
+    }
+}
+
+impl FromRequestParts for Query {
+    type Rejection = Rejection;
+
+    fn from_request_parts(parts: &mut request::Parts) -> Result<Self, Self::Rejection> {
+        match parts.target.query() {
+            Some(query) => Self::parse(query)
+                .ok_or(Rejection::new(StatusCode::BAD_REQUEST, "invalid query string")),
+            None => Ok(Self::default()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_query() {
+        let query = Query::parse("a=1&b=hello%20world&&c&a=2&d=%E2%9C%93").unwrap();
+        assert_eq!(
+            query.iter().collect::<Vec<_>>(),
+            [("a", "1"), ("b", "hello world"), ("c", ""), ("a", "2"), ("d", "✓")]
+        );
+        assert_eq!(query.get("a"), Some("1"));
+        assert_eq!(query.get("e"), None);
+
+        assert!(Query::parse("").unwrap().is_empty());
+        assert!(Query::parse("a=%2").is_none());
+        assert!(Query::parse("a=%zz").is_none());
+        assert!(Query::parse("a=%FF").is_none());
+    }
+}
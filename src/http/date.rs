@@ -151,10 +151,68 @@ pub fn httpdate(v: SystemTime) -> [u8; 29] {
     buf
 }
 
+/// Parse [httpdate][rfc] in IMF-fixdate format.
+///
+/// Returns `None` if the date is invalid, or in obsolete format.
+///
+/// [rfc]: <https://datatracker.ietf.org/doc/html/rfc9110#name-date-time-formats>
+pub fn parse_httpdate(v: &[u8]) -> Option<SystemTime> {
+    // Sun, 06 Nov 1994 08:49:37 GMT
+    let v: &[u8; 29] = v.try_into().ok()?;
+    if &v[3..5] != b", " || v[7] != b' ' || v[11] != b' ' || v[16] != b' ' || &v[25..] != b" GMT" {
+        return None;
+    }
+    if v[19] != b':' || v[22] != b':' {
+        return None;
+    }
+
+    let num = |range: std::ops::Range<usize>| {
+        v[range].iter().try_fold(0i64, |acc, &b| {
+            b.is_ascii_digit().then(|| acc * 10 + (b - b'0') as i64)
+        })
+    };
+
+    let mday = num(5..7)?;
+    let mon = match &v[8..11] {
+        b"Jan" => 1,
+        b"Feb" => 2,
+        b"Mar" => 3,
+        b"Apr" => 4,
+        b"May" => 5,
+        b"Jun" => 6,
+        b"Jul" => 7,
+        b"Aug" => 8,
+        b"Sep" => 9,
+        b"Oct" => 10,
+        b"Nov" => 11,
+        b"Dec" => 12,
+        _ => return None,
+    };
+    let year = num(12..16)?;
+    let hour = num(17..19)?;
+    let min = num(20..22)?;
+    let sec = num(23..25)?;
+
+    if !(1..=31).contains(&mday) || year < 1970 || hour > 23 || min > 59 || sec > 60 {
+        return None;
+    }
+
+    // days since epoch, from <http://howardhinnant.github.io/date_algorithms.html>
+    let y = if mon <= 2 { year - 1 } else { year };
+    let era = y / 400;
+    let yoe = y - era * 400;
+    let doy = (153 * ((mon + 9) % 12) + 2) / 5 + mday - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146097 + doe - 719468;
+
+    let secs = days * 86400 + hour * 3600 + min * 60 + sec;
+    Some(UNIX_EPOCH + std::time::Duration::from_secs(secs.try_into().ok()?))
+}
+
 #[cfg(test)]
 mod tests {
     use std::time::{Duration, UNIX_EPOCH};
-    use super::httpdate;
+    use super::{httpdate, parse_httpdate};
 
     #[test]
     fn test_httpdate() {
@@ -163,5 +221,18 @@ mod tests {
         let d = UNIX_EPOCH + Duration::from_secs(1475419451);
         assert_eq!(str::from_utf8(&httpdate(d)), Ok("Sun, 02 Oct 2016 14:44:11 GMT"));
     }
+
+    #[test]
+    fn test_parse_httpdate() {
+        assert_eq!(parse_httpdate(b"Thu, 01 Jan 1970 00:00:00 GMT"), Some(UNIX_EPOCH));
+        let d = UNIX_EPOCH + Duration::from_secs(1475419451);
+        assert_eq!(parse_httpdate(&httpdate(d)), Some(d));
+        let d = UNIX_EPOCH + Duration::from_secs(951825600);
+        assert_eq!(parse_httpdate(b"Tue, 29 Feb 2000 12:00:00 GMT"), Some(d));
+
+        assert_eq!(parse_httpdate(b"Sunday, 06-Nov-94 08:49:37 GMT"), None);
+        assert_eq!(parse_httpdate(b"Sun, 06 Foo 1994 08:49:37 GMT"), None);
+        assert_eq!(parse_httpdate(b"Sun, 06 Nov 1994 08:49:37 UTC"), None);
+    }
 }
 
@@ -0,0 +1,232 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::hash::{BuildHasherDefault, Hasher};
+
+type AnyMap = HashMap<TypeId, Box<dyn AnyClone + Send + Sync>, BuildHasherDefault<IdHasher>>;
+
+/// Type keyed map of request and response extensions.
+///
+/// Extensions allow middleware to attach arbitrary data to a request or response, such as an
+/// authenticated user or a request ID. Each type can only have one value.
+///
+/// The map does not allocate until the first value is inserted.
+///
+/// # Examples
+///
+/// ```
+/// use tsue::http::Extensions;
+///
+/// #[derive(Clone, Debug, PartialEq)]
+/// struct RequestId(u64);
+///
+/// let mut ext = Extensions::new();
+/// assert!(ext.insert(RequestId(1)).is_none());
+/// assert_eq!(ext.get::<RequestId>(), Some(&RequestId(1)));
+/// assert_eq!(ext.insert(RequestId(2)), Some(RequestId(1)));
+/// assert_eq!(ext.remove::<RequestId>(), Some(RequestId(2)));
+/// assert!(ext.is_empty());
+/// ```
+#[derive(Clone, Default)]
+pub struct Extensions {
+    map: Option<Box<AnyMap>>,
+}
+
+impl Extensions {
+    /// Create new empty [`Extensions`].
+    #[inline]
+    pub const fn new() -> Self {
+        Self { map: None }
+    }
+
+    /// Insert a value, returns the previous value of the same type if any.
+    pub fn insert<T: Clone + Send + Sync + 'static>(&mut self, value: T) -> Option<T> {
+        self.map
+            .get_or_insert_with(Default::default)
+            .insert(TypeId::of::<T>(), Box::new(value))
+            .and_then(downcast)
+    }
+
+    /// Returns shared reference to the value of type `T`.
+    pub fn get<T: Send + Sync + 'static>(&self) -> Option<&T> {
+        self.map
+            .as_ref()?
+            .get(&TypeId::of::<T>())
+            .and_then(|value| (**value).as_any().downcast_ref())
+    }
+
+    /// Returns mutable reference to the value of type `T`.
+    pub fn get_mut<T: Send + Sync + 'static>(&mut self) -> Option<&mut T> {
+        self.map
+            .as_mut()?
+            .get_mut(&TypeId::of::<T>())
+            .and_then(|value| (**value).as_any_mut().downcast_mut())
+    }
+
+    /// Returns mutable reference to the value of type `T`, inserting the result of `f` if it is
+    /// not present.
+    pub fn get_or_insert_with<T, F>(&mut self, f: F) -> &mut T
+    where
+        T: Clone + Send + Sync + 'static,
+        F: FnOnce() -> T,
+    {
+        let map = self.map.get_or_insert_with(Default::default);
+        let value = map.entry(TypeId::of::<T>()).or_insert_with(|| Box::new(f()));
+        (**value).as_any_mut().downcast_mut().expect("value is keyed by its type id")
+    }
+
+    /// Remove the value of type `T`.
+    pub fn remove<T: Send + Sync + 'static>(&mut self) -> Option<T> {
+        self.map
+            .as_mut()?
+            .remove(&TypeId::of::<T>())
+            .and_then(downcast)
+    }
+
+    /// Returns `true` if the map contains a value of type `T`.
+    #[inline]
+    pub fn contains<T: Send + Sync + 'static>(&self) -> bool {
+        self.map
+            .as_ref()
+            .is_some_and(|map| map.contains_key(&TypeId::of::<T>()))
+    }
+
+    /// Move all values from `other`, replacing values of the same type.
+    pub fn extend(&mut self, other: Self) {
+        if let Some(other) = other.map {
+            match &mut self.map {
+                Some(map) => map.extend(*other),
+                None => self.map = Some(other),
+            }
+        }
+    }
+
+    /// Remove all values.
+    #[inline]
+    pub fn clear(&mut self) {
+        if let Some(map) = &mut self.map {
+            map.clear();
+        }
+    }
+
+    /// Returns `true` if there is no value.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.map.as_ref().is_none_or(|map| map.is_empty())
+    }
+
+    /// Returns the number of values.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.map.as_ref().map_or(0, |map| map.len())
+    }
+}
+
+impl std::fmt::Debug for Extensions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Extensions").field("len", &self.len()).finish_non_exhaustive()
+    }
+}
+
+fn downcast<T: 'static>(value: Box<dyn AnyClone + Send + Sync>) -> Option<T> {
+    value.into_any().downcast().ok().map(|value| *value)
+}
+
+// ===== AnyClone =====
+
+trait AnyClone: Any {
+    fn clone_box(&self) -> Box<dyn AnyClone + Send + Sync>;
+
+    fn as_any(&self) -> &dyn Any;
+
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+
+    fn into_any(self: Box<Self>) -> Box<dyn Any>;
+}
+
+impl<T: Clone + Send + Sync + 'static> AnyClone for T {
+    fn clone_box(&self) -> Box<dyn AnyClone + Send + Sync> {
+        Box::new(self.clone())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        self
+    }
+}
+
+impl Clone for Box<dyn AnyClone + Send + Sync> {
+    #[inline]
+    fn clone(&self) -> Self {
+        (**self).clone_box()
+    }
+}
+
+// ===== IdHasher =====
+
+/// [`TypeId`] is already a hash, thus no need to hash it again.
+#[derive(Default)]
+struct IdHasher(u64);
+
+impl Hasher for IdHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        // `TypeId` only calls `write_u64`, but fallback just in case
+        for &byte in bytes {
+            self.0 = self.0.rotate_left(8) ^ u64::from(byte);
+        }
+    }
+
+    #[inline]
+    fn write_u64(&mut self, n: u64) {
+        self.0 = n;
+    }
+
+    #[inline]
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct A(u8);
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct B(&'static str);
+
+    #[test]
+    fn test_extensions() {
+        let mut ext = Extensions::new();
+        assert!(ext.is_empty());
+        assert_eq!(ext.insert(A(1)), None);
+        assert_eq!(ext.insert(B("b")), None);
+        assert_eq!(ext.len(), 2);
+        assert_eq!(ext.get::<A>(), Some(&A(1)));
+
+        ext.get_mut::<A>().unwrap().0 = 2;
+        let cloned = ext.clone();
+        assert_eq!(ext.insert(A(3)), Some(A(2)));
+        assert_eq!(cloned.get::<A>(), Some(&A(2)));
+
+        *ext.get_or_insert_with(|| 0u32) += 1;
+        assert_eq!(ext.get::<u32>(), Some(&1));
+
+        let mut other = Extensions::new();
+        other.insert(B("c"));
+        ext.extend(other);
+        assert_eq!(ext.remove::<B>(), Some(B("c")));
+        assert!(!ext.contains::<B>());
+
+        ext.clear();
+        assert!(ext.is_empty());
+    }
+}
@@ -0,0 +1,87 @@
+/// Socket address of a connection.
+#[derive(Debug, Clone)]
+pub enum SocketAddr {
+    /// Internet socket address, either IPv4 or IPv6.
+    Inet(std::net::SocketAddr),
+    /// Unix domain socket address.
+    #[cfg(unix)]
+    Unix(tokio::net::unix::SocketAddr),
+}
+
+impl SocketAddr {
+    /// Returns the internet socket address if any.
+    #[inline]
+    pub const fn as_inet(&self) -> Option<&std::net::SocketAddr> {
+        match self {
+            Self::Inet(addr) => Some(addr),
+            #[cfg(unix)]
+            Self::Unix(_) => None,
+        }
+    }
+
+    /// Returns the IP address if this is an internet socket address.
+    #[inline]
+    pub fn ip(&self) -> Option<std::net::IpAddr> {
+        self.as_inet().map(std::net::SocketAddr::ip)
+    }
+}
+
+impl From<std::net::SocketAddr> for SocketAddr {
+    #[inline]
+    fn from(value: std::net::SocketAddr) -> Self {
+        Self::Inet(value)
+    }
+}
+
+#[cfg(unix)]
+impl From<tokio::net::unix::SocketAddr> for SocketAddr {
+    #[inline]
+    fn from(value: tokio::net::unix::SocketAddr) -> Self {
+        Self::Unix(value)
+    }
+}
+
+impl std::fmt::Display for SocketAddr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Inet(addr) => addr.fmt(f),
+            #[cfg(unix)]
+            Self::Unix(addr) => match addr.as_pathname() {
+                Some(path) => path.display().fmt(f),
+                None => f.write_str("(unnamed)"),
+            },
+        }
+    }
+}
+
+// ===== ConnectionInfo =====
+
+/// Information of the connection where a request is received.
+#[derive(Debug, Clone, Default)]
+pub struct ConnectionInfo {
+    local_addr: Option<SocketAddr>,
+    peer_addr: Option<SocketAddr>,
+}
+
+impl ConnectionInfo {
+    /// Create new [`ConnectionInfo`].
+    #[inline]
+    pub const fn new(local_addr: Option<SocketAddr>, peer_addr: Option<SocketAddr>) -> Self {
+        Self {
+            local_addr,
+            peer_addr,
+        }
+    }
+
+    /// Returns the local address of the connection, if known.
+    #[inline]
+    pub const fn local_addr(&self) -> Option<&SocketAddr> {
+        self.local_addr.as_ref()
+    }
+
+    /// Returns the remote peer address of the connection, if known.
+    #[inline]
+    pub const fn peer_addr(&self) -> Option<&SocketAddr> {
+        self.peer_addr.as_ref()
+    }
+}
@@ -9,20 +9,26 @@ mod scheme;
 mod authority;
 mod target;
 mod uri;
+mod info;
 pub mod request;
 pub mod response;
 mod head;
+mod extensions;
+mod percent;
 
 pub mod error;
 
 pub use method::Method;
 pub use version::Version;
 pub use status::StatusCode;
-pub use date::{httpdate, httpdate_now};
+pub use date::{httpdate, httpdate_now, parse_httpdate};
 pub use scheme::Scheme;
 pub use authority::Authority;
 pub use target::Target;
 pub use uri::HttpUri;
+pub use info::{ConnectionInfo, SocketAddr};
 pub use request::Request;
 pub use response::Response;
 pub use head::{RequestHead, ResponseHead};
+pub use extensions::Extensions;
+pub(crate) use percent::percent_decode;
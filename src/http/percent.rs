@@ -0,0 +1,50 @@
+/// Percent-decode `input` into UTF-8 string.
+///
+/// If `plus_as_space` is `true`, `+` is decoded as space, as in
+/// `application/x-www-form-urlencoded`.
+///
+/// Returns `None` if the input contains invalid percent encoding or the decoded bytes is not
+/// valid UTF-8.
+pub(crate) fn percent_decode(input: &str, plus_as_space: bool) -> Option<String> {
+    if !input.contains('%') && !(plus_as_space && input.contains('+')) {
+        return Some(input.to_owned());
+    }
+    let mut bytes = Vec::with_capacity(input.len());
+    let mut iter = input.bytes();
+    while let Some(byte) = iter.next() {
+        match byte {
+            b'+' if plus_as_space => bytes.push(b' '),
+            b'%' => {
+                let hi = hex(iter.next()?)?;
+                let lo = hex(iter.next()?)?;
+                bytes.push((hi << 4) | lo);
+            }
+            _ => bytes.push(byte),
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+const fn hex(byte: u8) -> Option<u8> {
+    match byte {
+        b'0'..=b'9' => Some(byte - b'0'),
+        b'a'..=b'f' => Some(byte - b'a' + 10),
+        b'A'..=b'F' => Some(byte - b'A' + 10),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::percent_decode;
+
+    #[test]
+    fn test_percent_decode() {
+        assert_eq!(percent_decode("a%20b+c", false).as_deref(), Some("a b+c"));
+        assert_eq!(percent_decode("a%20b+c", true).as_deref(), Some("a b c"));
+        assert_eq!(percent_decode("%E2%9C%93", false).as_deref(), Some("✓"));
+        assert_eq!(percent_decode("%2", false), None);
+        assert_eq!(percent_decode("%zz", false), None);
+        assert_eq!(percent_decode("%FF", false), None);
+    }
+}
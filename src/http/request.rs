@@ -1,6 +1,6 @@
 //! HTTP Request
 use crate::headers::HeaderMap;
-use crate::http::{Method, Scheme, Target, Version};
+use crate::http::{ConnectionInfo, Extensions, Method, Scheme, Target, Version};
 
 /// HTTP Request Parts.
 #[derive(Debug, Default, Clone)]
@@ -10,6 +10,8 @@ pub struct Parts {
     pub target: Target,
     pub version: Version,
     pub headers: HeaderMap,
+    pub info: ConnectionInfo,
+    pub extensions: Extensions,
 }
 
 /// HTTP Request.
@@ -61,6 +63,16 @@ impl<T> Request<T> {
         headers(),
         /// Returns mutable reference to [`HeaderMap`].
         headers_mut() -> HeaderMap;
+
+        /// Returns shared reference to [`ConnectionInfo`].
+        info(),
+        /// Returns mutable reference to [`ConnectionInfo`].
+        info_mut() -> ConnectionInfo;
+
+        /// Returns shared reference to [`Extensions`].
+        extensions(),
+        /// Returns mutable reference to [`Extensions`].
+        extensions_mut() -> Extensions;
     }
 
     /// Returns shared reference to request body.
@@ -1,6 +1,6 @@
 //! HTTP Response
 use crate::headers::HeaderMap;
-use crate::http::{StatusCode, Version};
+use crate::http::{Extensions, StatusCode, Version};
 
 /// HTTP Response Parts.
 #[derive(Debug, Default)]
@@ -8,6 +8,7 @@ pub struct Parts {
     pub version: Version,
     pub status: StatusCode,
     pub headers: HeaderMap,
+    pub extensions: Extensions,
 }
 
 /// HTTP Response.
@@ -55,6 +56,11 @@ impl<T> Response<T> {
         headers(),
         /// Returns mutable reference to [`HeaderMap`].
         headers_mut() -> HeaderMap;
+
+        /// Returns shared reference to [`Extensions`].
+        extensions(),
+        /// Returns mutable reference to [`Extensions`].
+        extensions_mut() -> Extensions;
     }
 
     /// Returns shared reference to response body.
@@ -24,6 +24,30 @@ impl Default for StatusCode {
 }
 
 impl StatusCode {
+    /// Create [`StatusCode`] from `u16`.
+    ///
+    /// Returns `None` if the status code is not supported.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tsue::http::StatusCode;
+    ///
+    /// assert_eq!(StatusCode::from_u16(404), Some(StatusCode::NOT_FOUND));
+    /// assert_eq!(StatusCode::from_u16(299), None);
+    /// ```
+    pub const fn from_u16(code: u16) -> Option<Self> {
+        let mut i = 0;
+        while i < VALUES.len() {
+            if VALUES[i].0 == code {
+                // SAFETY: status code in `VALUES` is non zero
+                return Some(Self(unsafe { NonZeroU16::new_unchecked(code) }));
+            }
+            i += 1;
+        }
+        None
+    }
+
     /// Returns status code value as `u16`.
     ///
     /// # Examples
@@ -452,8 +476,10 @@ fn test_status_code() {
         StatusCode::SWITCHING_PROTOCOL.as_str(),
         "101 Switching Protocols"
     );
+    assert_eq!(StatusCode::from_u16(0), None);
 
     for (status, expected_reason) in TEST_STATUS {
+        assert_eq!(StatusCode::from_u16(status.as_u16()), Some(status));
         assert_eq!(status.reason(), expected_reason);
         assert_eq!(status.code_str(), status.0.to_string());
         assert_eq!(status.as_str(), format!("{} {expected_reason}", status.0));
@@ -22,7 +22,15 @@
 //!
 //! ## User Abstraction
 //!
-//! - [`service`] abstract user defined logic
+//! - [`service`] abstract user defined logic, and middleware composition
+//! - [`routing`] route requests to services by method and path
+//! - [`handler`] ergonomic request handlers
+//! - [`fs`] static file serving
+//!
+//! ## Runtime
+//!
+//! - [`rt`] runtime abstraction, such as timer and executor
+//! - [`event`] connection lifecycle observer
 //!
 //! ## Integrations
 //!
@@ -43,6 +51,8 @@
 
 mod log;
 mod matches;
+#[cfg(test)]
+mod testing;
 
 pub use tcio::bytes;
 
@@ -57,6 +67,13 @@ pub mod h2;
 
 // user abstraction
 pub mod service;
+pub mod routing;
+pub mod handler;
+pub mod fs;
+
+// runtime
+pub mod rt;
+pub mod event;
 
 // integration
 pub mod server;
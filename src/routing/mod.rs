@@ -0,0 +1,10 @@
+//! Request routing.
+//!
+//! - [`Router`] route requests by method and path pattern
+//! - [`Params`] path parameters captured by the router
+mod tree;
+mod params;
+mod router;
+
+pub use params::Params;
+pub use router::Router;
@@ -0,0 +1,56 @@
+/// Path parameters captured by [`Router`][super::Router].
+///
+/// Captured parameters are inserted into the request [`Extensions`][crate::http::Extensions]
+/// before the route service is called. Values are the raw path segments, they are not percent
+/// decoded.
+///
+/// # Examples
+///
+/// ```
+/// use tsue::body::Incoming;
+/// use tsue::http::Request;
+/// use tsue::routing::Params;
+///
+/// fn user_id(request: &Request<Incoming>) -> Option<&str> {
+///     request.extensions().get::<Params>()?.get("id")
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Params {
+    entries: Vec<(Box<str>, Box<str>)>,
+}
+
+impl Params {
+    /// Returns the value of parameter `name`.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|(key, _)| &**key == name)
+            .map(|(_, value)| &**value)
+    }
+
+    /// Returns an iterator over parameter name and value, in the order they appear in the path.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.entries.iter().map(|(name, value)| (&**name, &**value))
+    }
+
+    /// Returns the number of parameters.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if there is no parameter.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub(crate) fn push(&mut self, name: &str, value: &str) {
+        self.entries.push((name.into(), value.into()));
+    }
+
+    pub(crate) fn truncate(&mut self, len: usize) {
+        self.entries.truncate(len);
+    }
+}
@@ -0,0 +1,329 @@
+use std::marker::PhantomData;
+use std::sync::Arc;
+use tcio::bytes::Bytes;
+
+use super::Params;
+use super::tree::Node;
+use crate::body::{Full, Incoming};
+use crate::headers::{HeaderValue, standard};
+use crate::http::{Method, Request, Response, StatusCode};
+use crate::service::{BoxFuture, Boxed, Service, ServiceExt};
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Shared route service, thus cloning the router does not clone each service.
+type Route<B> = Arc<
+    dyn Service<
+            Request<Incoming>,
+            Response = Response<B>,
+            Error = BoxError,
+            Future = BoxFuture<Response<B>, BoxError>,
+        > + Send
+        + Sync,
+>;
+
+// ===== Router =====
+
+/// Route requests to services by method and path.
+///
+/// Path patterns consist of segments separated by `/`, a segment can be:
+///
+/// - static, e.g. `/users`, matches exactly
+/// - `:name`, matches a single non-empty segment
+/// - `*name`, matches the rest of the path, only allowed as the last segment
+///
+/// When multiple patterns match, static segment is preferred, then `:name`, then `*name`.
+/// Captured segments are available as [`Params`] in the request extensions.
+///
+/// If the path matches but the method does not, `405 Method Not Allowed` is returned with the
+/// `Allow` header. `HEAD` request is routed to `GET` service if there is no `HEAD` service. If the
+/// path does not match, the request is passed to the [`fallback`][Router::fallback] service,
+/// which returns `404 Not Found` by default.
+///
+/// # Examples
+///
+/// ```
+/// use tsue::body::{Full, Incoming};
+/// use tsue::bytes::Bytes;
+/// use tsue::http::{Method, Request, Response};
+/// use tsue::routing::{Params, Router};
+/// use tsue::service::from_fn;
+///
+/// async fn user(request: Request<Incoming>) -> Response<Full<Bytes>> {
+///     let params = request.extensions().get::<Params>().unwrap();
+///     let id = params.get("id").unwrap();
+///     Response::from_parts(Default::default(), Full::new(Bytes::copy_from_slice(id.as_bytes())))
+/// }
+///
+/// let router = Router::new()
+///     .route(Method::GET, "/users/:id", from_fn(user))
+///     .route(Method::DELETE, "/users/:id", from_fn(user));
+/// ```
+pub struct Router<B = Full<Bytes>> {
+    tree: Arc<Node<Endpoint<B>>>,
+    fallback: Route<B>,
+}
+
+impl<B> Default for Router<B>
+where
+    B: Default + Send + 'static,
+{
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<B> Router<B>
+where
+    B: Default + Send + 'static,
+{
+    /// Create new empty [`Router`].
+    pub fn new() -> Self {
+        Self {
+            tree: Arc::default(),
+            fallback: Arc::new(Boxed(Status {
+                status: StatusCode::NOT_FOUND,
+                _body: PhantomData,
+            })),
+        }
+    }
+}
+
+impl<B> Router<B> {
+    /// Add a route for `method` and path `pattern`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pattern is invalid, conflicts with existing pattern, or the route is already
+    /// registered.
+    pub fn route<S>(mut self, method: Method, pattern: &str, service: S) -> Self
+    where
+        S: Service<Request<Incoming>, Response = Response<B>> + Send + Sync + 'static,
+        S::Error: Into<BoxError>,
+        S::Future: Send + 'static,
+    {
+        let endpoint = Arc::make_mut(&mut self.tree).insert(pattern);
+        if endpoint.routes.iter().any(|(m, _)| *m == method) {
+            panic!("route `{method} {pattern}` is already registered");
+        }
+        endpoint.routes.push((method, Arc::new(Boxed(service.map_err(Into::<BoxError>::into)))));
+        endpoint.allow = endpoint.allow_header();
+        self
+    }
+
+    /// Set the service for requests that does not match any route.
+    pub fn fallback<S>(mut self, service: S) -> Self
+    where
+        S: Service<Request<Incoming>, Response = Response<B>> + Send + Sync + 'static,
+        S::Error: Into<BoxError>,
+        S::Future: Send + 'static,
+    {
+        self.fallback = Arc::new(Boxed(service.map_err(Into::<BoxError>::into)));
+        self
+    }
+}
+
+impl<B> Service<Request<Incoming>> for Router<B>
+where
+    B: Default + Send + 'static,
+{
+    type Response = Response<B>;
+
+    type Error = BoxError;
+
+    type Future = BoxFuture<Response<B>, BoxError>;
+
+    fn call(&self, mut request: Request<Incoming>) -> Self::Future {
+        let mut params = Params::default();
+        let Some(endpoint) = self.tree.find(request.target().path(), &mut params) else {
+            return self.fallback.call(request);
+        };
+
+        match endpoint.route(*request.method()) {
+            Some(route) => {
+                request.extensions_mut().insert(params);
+                route.call(request)
+            }
+            None => {
+                let mut response = status(StatusCode::METHOD_NOT_ALLOWED);
+                if let Some(allow) = &endpoint.allow {
+                    response.headers_mut().insert(standard::ALLOW, allow.clone());
+                }
+                Box::pin(std::future::ready(Ok(response)))
+            }
+        }
+    }
+}
+
+impl<B> Clone for Router<B> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self {
+            tree: self.tree.clone(),
+            fallback: self.fallback.clone(),
+        }
+    }
+}
+
+impl<B> std::fmt::Debug for Router<B> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Router")
+            .field("tree", &self.tree)
+            .finish_non_exhaustive()
+    }
+}
+
+// ===== Endpoint =====
+
+/// Services of a single path pattern.
+struct Endpoint<B> {
+    routes: Vec<(Method, Route<B>)>,
+    /// the `Allow` header value, updated when a route is added
+    allow: Option<HeaderValue>,
+}
+
+impl<B> Endpoint<B> {
+    fn route(&self, method: Method) -> Option<&Route<B>> {
+        let find = |method| {
+            self.routes
+                .iter()
+                .find(|(m, _)| *m == method)
+                .map(|(_, route)| route)
+        };
+        match method {
+            Method::HEAD => find(Method::HEAD).or_else(|| find(Method::GET)),
+            _ => find(method),
+        }
+    }
+
+    fn allow_header(&self) -> Option<HeaderValue> {
+        let mut allow = String::new();
+        let mut methods = self.routes.iter().map(|(method, _)| *method).collect::<Vec<_>>();
+        if methods.contains(&Method::GET) && !methods.contains(&Method::HEAD) {
+            methods.push(Method::HEAD);
+        }
+        for method in methods {
+            if !allow.is_empty() {
+                allow.push_str(", ");
+            }
+            allow.push_str(method.as_str());
+        }
+        HeaderValue::from_slice(allow).ok()
+    }
+}
+
+impl<B> Default for Endpoint<B> {
+    fn default() -> Self {
+        Self {
+            routes: Vec::new(),
+            allow: None,
+        }
+    }
+}
+
+impl<B> Clone for Endpoint<B> {
+    fn clone(&self) -> Self {
+        Self {
+            routes: self.routes.clone(),
+            allow: self.allow.clone(),
+        }
+    }
+}
+
+impl<B> std::fmt::Debug for Endpoint<B> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list()
+            .entries(self.routes.iter().map(|(method, _)| method))
+            .finish()
+    }
+}
+
+// ===== Status =====
+
+/// Service that always returns empty response with the given status.
+struct Status<B> {
+    status: StatusCode,
+    _body: PhantomData<fn() -> B>,
+}
+
+impl<B: Default> Service<Request<Incoming>> for Status<B> {
+    type Response = Response<B>;
+
+    type Error = BoxError;
+
+    type Future = std::future::Ready<Result<Response<B>, BoxError>>;
+
+    #[inline]
+    fn call(&self, _: Request<Incoming>) -> Self::Future {
+        std::future::ready(Ok(status(self.status)))
+    }
+}
+
+fn status<B: Default>(status: StatusCode) -> Response<B> {
+    let mut response = Response::<B>::default();
+    *response.status_mut() = status;
+    response
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::http::{Target, request};
+    use crate::service::from_fn;
+    use crate::testing::block_on;
+
+    /// Returns captured params in response extensions.
+    async fn echo(request: Request<Incoming>) -> Response<Full<Bytes>> {
+        let params = request.extensions().get::<Params>().cloned().unwrap();
+        let mut response = Response::<Full<Bytes>>::default();
+        response.extensions_mut().insert(params);
+        response
+    }
+
+    fn call(router: &Router, method: Method, path: &'static str) -> Response<Full<Bytes>> {
+        let parts = request::Parts {
+            method,
+            target: Target::from_static(path.as_bytes()),
+            ..Default::default()
+        };
+        block_on(router.call(Request::from_parts(parts, Incoming::default()))).unwrap()
+    }
+
+    #[test]
+    fn test_router() {
+        let router = Router::new()
+            .route(Method::GET, "/users/:id", from_fn(echo))
+            .route(Method::POST, "/users/:id", from_fn(echo))
+            .route(Method::GET, "/files/*path", from_fn(echo));
+
+        let response = call(&router, Method::GET, "/users/42");
+        assert_eq!(response.status(), &StatusCode::OK);
+        assert_eq!(response.extensions().get::<Params>().unwrap().get("id"), Some("42"));
+
+        let response = call(&router, Method::HEAD, "/files/a/b");
+        assert_eq!(response.status(), &StatusCode::OK);
+        assert_eq!(response.extensions().get::<Params>().unwrap().get("path"), Some("a/b"));
+
+        let response = call(&router, Method::DELETE, "/users/42");
+        assert_eq!(response.status(), &StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(response.headers().get(standard::ALLOW).unwrap(), "GET, POST, HEAD");
+
+        let response = call(&router, Method::GET, "/posts");
+        assert_eq!(response.status(), &StatusCode::NOT_FOUND);
+
+        let router = router.fallback(from_fn(|_: Request<Incoming>| async {
+            status::<Full<Bytes>>(StatusCode::IM_A_TEAPOT)
+        }));
+        let response = call(&router, Method::GET, "/posts");
+        assert_eq!(response.status(), &StatusCode::IM_A_TEAPOT);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_duplicate_route() {
+        let _ = Router::<Full<Bytes>>::new()
+            .route(Method::GET, "/", from_fn(echo))
+            .route(Method::GET, "/", from_fn(echo));
+    }
+}
@@ -0,0 +1,298 @@
+//! Radix tree of path patterns.
+use super::Params;
+
+/// A node in the radix tree.
+///
+/// Static children are compressed by their common prefix, thus the first byte of each static
+/// child is distinct. Matching prefers static children, then `:param`, then `*wildcard`.
+#[derive(Debug, Clone)]
+pub(crate) struct Node<T> {
+    prefix: Box<str>,
+    value: Option<T>,
+    statics: Vec<Node<T>>,
+    param: Option<Box<Param<T>>>,
+    wildcard: Option<Box<Wildcard<T>>>,
+}
+
+#[derive(Debug, Clone)]
+struct Param<T> {
+    name: Box<str>,
+    node: Node<T>,
+}
+
+#[derive(Debug, Clone)]
+struct Wildcard<T> {
+    name: Box<str>,
+    value: T,
+}
+
+impl<T> Default for Node<T> {
+    fn default() -> Self {
+        Self::new("")
+    }
+}
+
+impl<T> Node<T> {
+    fn new(prefix: &str) -> Self {
+        Self {
+            prefix: prefix.into(),
+            value: None,
+            statics: Vec::new(),
+            param: None,
+            wildcard: None,
+        }
+    }
+
+    /// Returns the value of `pattern`, inserting the default value if it is not present.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pattern is invalid, or conflicts with existing pattern.
+    pub(crate) fn insert(&mut self, pattern: &str) -> &mut T
+    where
+        T: Default,
+    {
+        let pieces = match parse(pattern) {
+            Ok(ok) => ok,
+            Err(err) => panic!("invalid route pattern {pattern:?}: {err}"),
+        };
+
+        let mut node = self;
+        for piece in pieces {
+            match piece {
+                Piece::Static(path) => node = node.insert_static(path),
+                Piece::Param(name) => {
+                    let param = node.param.get_or_insert_with(|| {
+                        Box::new(Param {
+                            name: name.into(),
+                            node: Node::new(""),
+                        })
+                    });
+                    if &*param.name != name {
+                        panic!(
+                            "route pattern {pattern:?} conflicts with existing parameter `:{}`",
+                            param.name
+                        );
+                    }
+                    node = &mut param.node;
+                }
+                Piece::Wildcard(name) => {
+                    let wildcard = node.wildcard.get_or_insert_with(|| {
+                        Box::new(Wildcard {
+                            name: name.into(),
+                            value: T::default(),
+                        })
+                    });
+                    if &*wildcard.name != name {
+                        panic!(
+                            "route pattern {pattern:?} conflicts with existing wildcard `*{}`",
+                            wildcard.name
+                        );
+                    }
+                    return &mut wildcard.value;
+                }
+            }
+        }
+        node.value.get_or_insert_with(T::default)
+    }
+
+    fn insert_static(&mut self, path: &str) -> &mut Node<T> {
+        if path.is_empty() {
+            return self;
+        }
+
+        let Some(i) = self
+            .statics
+            .iter()
+            .position(|child| child.prefix.as_bytes()[0] == path.as_bytes()[0])
+        else {
+            self.statics.push(Node::new(path));
+            return self.statics.last_mut().unwrap();
+        };
+
+        let child = &mut self.statics[i];
+        let common = child
+            .prefix
+            .bytes()
+            .zip(path.bytes())
+            .take_while(|(a, b)| a == b)
+            .count();
+
+        if common < child.prefix.len() {
+            // split the child at the common prefix
+            let mut split = Node::new(&child.prefix[common..]);
+            split.value = child.value.take();
+            split.statics = std::mem::take(&mut child.statics);
+            split.param = child.param.take();
+            split.wildcard = child.wildcard.take();
+            child.prefix = child.prefix[..common].into();
+            child.statics.push(split);
+        }
+
+        child.insert_static(&path[common..])
+    }
+
+    /// Find the value matching `path`, captured parameters are pushed into `params`.
+    pub(crate) fn find(&self, path: &str, params: &mut Params) -> Option<&T> {
+        let path = path.strip_prefix(&*self.prefix)?;
+
+        if path.is_empty()
+            && let Some(value) = &self.value
+        {
+            return Some(value);
+        }
+
+        if let Some(child) = self.statics.iter().find(|child| path.starts_with(&*child.prefix))
+            && let Some(value) = child.find(path, params)
+        {
+            return Some(value);
+        }
+
+        if let Some(param) = &self.param {
+            let end = path.find('/').unwrap_or(path.len());
+            if end != 0 {
+                let len = params.len();
+                params.push(&param.name, &path[..end]);
+                if let Some(value) = param.node.find(&path[end..], params) {
+                    return Some(value);
+                }
+                params.truncate(len);
+            }
+        }
+
+        if let Some(wildcard) = &self.wildcard {
+            params.push(&wildcard.name, path);
+            return Some(&wildcard.value);
+        }
+
+        None
+    }
+}
+
+// ===== Parser =====
+
+#[derive(Debug, PartialEq)]
+enum Piece<'a> {
+    Static(&'a str),
+    Param(&'a str),
+    Wildcard(&'a str),
+}
+
+fn parse(pattern: &str) -> Result<Vec<Piece<'_>>, &'static str> {
+    let Some(path) = pattern.strip_prefix('/') else {
+        return Err("pattern must starts with `/`");
+    };
+
+    let mut pieces = Vec::new();
+    let mut static_start = 0;
+    let mut offset = 1;
+    let mut segments = path.split('/').peekable();
+
+    while let Some(segment) = segments.next() {
+        // `:` and `*` are only special at the start of a segment
+        if let Some(kind @ (b':' | b'*')) = segment.bytes().next() {
+            if static_start < offset {
+                pieces.push(Piece::Static(&pattern[static_start..offset]));
+            }
+
+            let name = &segment[1..];
+            if name.is_empty() {
+                return Err("parameter name must not be empty");
+            }
+
+            if kind == b'*' {
+                if segments.peek().is_some() {
+                    return Err("wildcard must be the last segment");
+                }
+                pieces.push(Piece::Wildcard(name));
+            } else {
+                pieces.push(Piece::Param(name));
+            }
+            static_start = offset + segment.len();
+        }
+        offset += segment.len() + 1;
+    }
+
+    if static_start < pattern.len() {
+        pieces.push(Piece::Static(&pattern[static_start..]));
+    }
+
+    Ok(pieces)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn find(tree: &Node<u8>, path: &str) -> Option<(u8, Vec<(String, String)>)> {
+        let mut params = Params::default();
+        let value = tree.find(path, &mut params)?;
+        let params = params
+            .iter()
+            .map(|(name, value)| (name.to_owned(), value.to_owned()))
+            .collect();
+        Some((*value, params))
+    }
+
+    fn p(name: &str, value: &str) -> (String, String) {
+        (name.to_owned(), value.to_owned())
+    }
+
+    #[test]
+    fn test_parse() {
+        use Piece::*;
+
+        assert_eq!(parse("/"), Ok(vec![Static("/")]));
+        assert_eq!(parse("/users/:id"), Ok(vec![Static("/users/"), Param("id")]));
+        assert_eq!(
+            parse("/users/:id/posts/:post"),
+            Ok(vec![Static("/users/"), Param("id"), Static("/posts/"), Param("post")])
+        );
+        assert_eq!(parse("/files/*path"), Ok(vec![Static("/files/"), Wildcard("path")]));
+        assert_eq!(parse("/a:b/c*d"), Ok(vec![Static("/a:b/c*d")]));
+
+        assert!(parse("users").is_err());
+        assert!(parse("/users/:").is_err());
+        assert!(parse("/files/*path/more").is_err());
+    }
+
+    #[test]
+    fn test_tree() {
+        let mut tree = Node::default();
+        *tree.insert("/") = 1;
+        *tree.insert("/users") = 2;
+        *tree.insert("/users/:id") = 3;
+        *tree.insert("/users/:id/posts") = 4;
+        *tree.insert("/users/me") = 5;
+        *tree.insert("/uploads") = 6;
+        *tree.insert("/files/*path") = 7;
+        *tree.insert("/files/readme") = 8;
+
+        assert_eq!(find(&tree, "/"), Some((1, vec![])));
+        assert_eq!(find(&tree, "/users"), Some((2, vec![])));
+        assert_eq!(find(&tree, "/users/42"), Some((3, vec![p("id", "42")])));
+        assert_eq!(find(&tree, "/users/42/posts"), Some((4, vec![p("id", "42")])));
+        assert_eq!(find(&tree, "/users/me"), Some((5, vec![])));
+        assert_eq!(find(&tree, "/users/mel"), Some((3, vec![p("id", "mel")])));
+        assert_eq!(find(&tree, "/uploads"), Some((6, vec![])));
+        assert_eq!(find(&tree, "/files/a/b.txt"), Some((7, vec![p("path", "a/b.txt")])));
+        assert_eq!(find(&tree, "/files/"), Some((7, vec![p("path", "")])));
+        assert_eq!(find(&tree, "/files/readme"), Some((8, vec![])));
+
+        assert_eq!(find(&tree, "/user"), None);
+        assert_eq!(find(&tree, "/users/"), None);
+        assert_eq!(find(&tree, "/users/42/comments"), None);
+        assert_eq!(find(&tree, "/files"), None);
+
+        // same pattern returns the same value
+        assert_eq!(*tree.insert("/users/:id"), 3);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_param_conflict() {
+        let mut tree = Node::<u8>::default();
+        tree.insert("/users/:id");
+        tree.insert("/users/:name/posts");
+    }
+}
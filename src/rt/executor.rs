@@ -0,0 +1,80 @@
+// ===== Executor =====
+
+/// Spawn futures in the background.
+///
+/// Used by [`Server`][crate::server::Server] to spawn connection futures.
+pub trait Executor<F> {
+    /// Spawn the future.
+    fn execute(&self, future: F);
+}
+
+// ===== TokioExecutor =====
+
+/// [`Executor`] that spawn futures with [`tokio::spawn`].
+///
+/// Spawned futures must be `Send`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TokioExecutor;
+
+impl<F> Executor<F> for TokioExecutor
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    #[inline]
+    fn execute(&self, future: F) {
+        tokio::spawn(future);
+    }
+}
+
+// ===== LocalExecutor =====
+
+/// [`Executor`] that spawn futures with [`tokio::task::spawn_local`].
+///
+/// This allows `!Send` futures, such as service that hold `Rc`, to be spawned. It must be used
+/// within [`LocalSet`][tokio::task::LocalSet].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LocalExecutor;
+
+impl<F> Executor<F> for LocalExecutor
+where
+    F: Future<Output = ()> + 'static,
+{
+    #[inline]
+    fn execute(&self, future: F) {
+        tokio::task::spawn_local(future);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::rc::Rc;
+    use tcio::bytes::Bytes;
+    use tokio::task::LocalSet;
+
+    use super::*;
+    use crate::body::{Full, Incoming};
+    use crate::http::{Request, Response, StatusCode};
+    use crate::server::{Http1Server, MemoryListener, TestClient};
+    use crate::service::from_fn;
+    use crate::testing::block_on;
+
+    #[test]
+    fn test_local_executor() {
+        let local = LocalSet::new();
+        block_on(local.run_until(async {
+            // `Rc` makes the service and its future `!Send`
+            let body = Rc::new(Bytes::from_static(b"hello"));
+            let service = from_fn(move |_: Request<Incoming>| {
+                let body = Rc::clone(&body);
+                async move { Response::from_parts(Default::default(), Full::new((*body).clone())) }
+            });
+            let (listener, connector) = MemoryListener::new();
+            tokio::task::spawn_local(Http1Server::new(service, listener).executor(LocalExecutor));
+
+            let mut client = TestClient::new(connector.connect().unwrap());
+            let response = client.send(Request::<Full<Bytes>>::default()).await.unwrap();
+            assert_eq!(response.status(), &StatusCode::OK);
+            assert_eq!(response.body(), &Bytes::from_static(b"hello"));
+        }));
+    }
+}
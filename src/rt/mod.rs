@@ -0,0 +1,6 @@
+//! Runtime abstraction.
+mod timer;
+mod executor;
+
+pub use timer::{Sleep, Timer, TokioTimer};
+pub use executor::{Executor, LocalExecutor, TokioExecutor};
@@ -0,0 +1,65 @@
+use std::pin::Pin;
+use std::time::{Duration, Instant};
+
+// ===== Timer =====
+
+/// Provide timer used for timeouts.
+///
+/// Implementing this trait allows timeouts to be driven by other runtime or by a mocked clock in
+/// tests.
+pub trait Timer {
+    /// Returns a future that resolves after `duration` has elapsed.
+    fn sleep(&self, duration: Duration) -> Pin<Box<dyn Sleep>>;
+
+    /// Returns a future that resolves at `deadline`.
+    fn sleep_until(&self, deadline: Instant) -> Pin<Box<dyn Sleep>>;
+
+    /// Reset the `sleep` future to resolve at `deadline`.
+    ///
+    /// The default implementation replace the future with new one.
+    fn reset(&self, sleep: &mut Pin<Box<dyn Sleep>>, deadline: Instant) {
+        *sleep = self.sleep_until(deadline);
+    }
+}
+
+/// A future returned by [`Timer`].
+pub trait Sleep: Future<Output = ()> + Send + Sync { }
+
+impl<T: Timer + ?Sized> Timer for std::sync::Arc<T> {
+    #[inline]
+    fn sleep(&self, duration: Duration) -> Pin<Box<dyn Sleep>> {
+        T::sleep(self, duration)
+    }
+
+    #[inline]
+    fn sleep_until(&self, deadline: Instant) -> Pin<Box<dyn Sleep>> {
+        T::sleep_until(self, deadline)
+    }
+
+    #[inline]
+    fn reset(&self, sleep: &mut Pin<Box<dyn Sleep>>, deadline: Instant) {
+        T::reset(self, sleep, deadline);
+    }
+}
+
+// ===== TokioTimer =====
+
+/// [`Timer`] implementation using tokio runtime.
+///
+/// [`Timer::sleep`] respect tokio paused clock, which is useful in tests.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TokioTimer;
+
+impl Sleep for tokio::time::Sleep { }
+
+impl Timer for TokioTimer {
+    #[inline]
+    fn sleep(&self, duration: Duration) -> Pin<Box<dyn Sleep>> {
+        Box::pin(tokio::time::sleep(duration))
+    }
+
+    #[inline]
+    fn sleep_until(&self, deadline: Instant) -> Pin<Box<dyn Sleep>> {
+        Box::pin(tokio::time::sleep_until(deadline.into()))
+    }
+}
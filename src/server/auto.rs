@@ -0,0 +1,376 @@
+use std::mem;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Poll, ready};
+use tcio::bytes::BytesMut;
+use tcio::io::{AsyncRead, AsyncWrite};
+
+use crate::{h1, h2};
+use crate::event::SharedObserver;
+use crate::h1::timeout::Deadline;
+use crate::h2::state::H2State;
+use crate::http::ConnectionInfo;
+use crate::server::{Driver, GracefulShutdown};
+use crate::service::HttpService;
+
+const DEFAULT_BUFFER_CAP: usize = 1024;
+
+// ===== Auto Driver =====
+
+/// A [`Driver`] that serve both HTTP/1.1 and HTTP/2 in the same port.
+///
+/// The protocol is detected by peeking the first bytes of the stream. If it matches the HTTP/2
+/// connection preface, the stream is served as HTTP/2 with prior knowledge, otherwise it is served
+/// as HTTP/1.1.
+///
+/// The detection is bounded by the HTTP/1.1 config header read timeout.
+#[derive(Debug, Clone)]
+pub struct Auto {
+    h1: Arc<h1::Config>,
+}
+
+impl Default for Auto {
+    /// Create [`Auto`] driver with `h2c` upgrade enabled.
+    #[inline]
+    fn default() -> Self {
+        Self::new(h1::Config::new().h2c_upgrade(true))
+    }
+}
+
+impl Auto {
+    /// Create new [`Auto`] driver with given HTTP/1.1 connection [`Config`][h1::Config].
+    #[inline]
+    pub fn new(h1: h1::Config) -> Self {
+        Self { h1: Arc::new(h1) }
+    }
+}
+
+impl<S, IO> Driver<S, IO> for Auto
+where
+    S: HttpService,
+    IO: Unpin,
+{
+    type Future = AutoConnection<S, IO>;
+
+    #[inline]
+    fn call(&self, service: S, io: IO, info: ConnectionInfo, observer: SharedObserver) -> Self::Future {
+        AutoConnection {
+            phase: Phase::Detect(Detect {
+                service,
+                io,
+                info,
+                observer,
+                config: self.h1.clone(),
+                read_buffer: BytesMut::with_capacity(DEFAULT_BUFFER_CAP),
+                deadline: Deadline::new(),
+            }),
+        }
+    }
+}
+
+// ===== AutoConnection =====
+
+/// Connection that is either HTTP/1.1 or HTTP/2.
+pub struct AutoConnection<S, IO>
+where
+    S: HttpService,
+{
+    phase: Phase<S, IO>,
+}
+
+enum Phase<S, IO>
+where
+    S: HttpService,
+{
+    Detect(Detect<S, IO>),
+    H1(h1::Connection<S, IO>),
+    H2(h2::Connection<S, IO>),
+    Complete,
+}
+
+struct Detect<S, IO> {
+    service: S,
+    io: IO,
+    info: ConnectionInfo,
+    observer: SharedObserver,
+    config: Arc<h1::Config>,
+    read_buffer: BytesMut,
+    deadline: Deadline,
+}
+
+impl<S, IO> Future for AutoConnection<S, IO>
+where
+    S: HttpService,
+    IO: AsyncRead + AsyncWrite + Unpin,
+{
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut std::task::Context) -> Poll<Self::Output> {
+        // SAFETY: the connection is never moved once it is constructed in `phase`, and `io` in
+        // `Phase::Detect` is `Unpin`
+        let phase = unsafe { &mut self.get_unchecked_mut().phase };
+
+        loop {
+            match phase {
+                Phase::Detect(Detect { io, info, observer, config, read_buffer, deadline, .. }) => {
+                    let is_h2 = loop {
+                        if let Poll::Ready(is_h2) = H2State::detect_preface(read_buffer) {
+                            break is_h2;
+                        }
+                        match Pin::new(&mut *io).poll_read(&mut *read_buffer, cx) {
+                            Poll::Ready(Ok(0)) => observer.on_close(info),
+                            Poll::Ready(Ok(read)) => {
+                                observer.on_read(info, read);
+                                continue;
+                            }
+                            Poll::Ready(Err(err)) => observer.on_abort(info, &err),
+                            Poll::Pending => {
+                                // no complete request is received, same as HTTP/1.1 header
+                                // read timeout, the connection is closed
+                                ready!(deadline.poll(config.header_read_timeout, &*config.timer, cx));
+                                observer.on_close(info);
+                            }
+                        }
+                        *phase = Phase::Complete;
+                        return Poll::Ready(());
+                    };
+
+                    let Phase::Detect(detect) = mem::replace(phase, Phase::Complete) else {
+                        unreachable!()
+                    };
+                    let Detect { service, io, info, observer, config, read_buffer, .. } = detect;
+
+                    *phase = if is_h2 {
+                        Phase::H2(
+                            h2::Connection::with_read_buffer(service, io, info, read_buffer)
+                                .with_observer(observer),
+                        )
+                    } else {
+                        Phase::H1(
+                            h1::Connection::with_read_buffer(service, io, info, config, read_buffer)
+                                .with_observer(observer),
+                        )
+                    };
+                }
+                // SAFETY: `self` is pinned, thus `self.phase` is also pinned
+                Phase::H1(conn) => return unsafe { Pin::new_unchecked(conn) }.poll(cx),
+                Phase::H2(conn) => return unsafe { Pin::new_unchecked(conn) }.poll(cx),
+                Phase::Complete => return Poll::Ready(()),
+            }
+        }
+    }
+}
+
+impl<S, IO> GracefulShutdown for AutoConnection<S, IO>
+where
+    S: HttpService,
+    IO: AsyncRead + AsyncWrite + Unpin,
+{
+    fn graceful_shutdown(self: Pin<&mut Self>) {
+        // SAFETY: the connection is not moved
+        let phase = unsafe { &mut self.get_unchecked_mut().phase };
+        match phase {
+            // no request is received yet
+            Phase::Detect(_) => *phase = Phase::Complete,
+            Phase::H1(conn) => unsafe { Pin::new_unchecked(conn) }.graceful_shutdown(),
+            Phase::H2(conn) => unsafe { Pin::new_unchecked(conn) }.graceful_shutdown(),
+            Phase::Complete => {}
+        }
+    }
+}
+
+impl<S, IO> std::fmt::Debug for AutoConnection<S, IO>
+where
+    S: HttpService,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let protocol = match self.phase {
+            Phase::Detect(_) => "detect",
+            Phase::H1(_) => "HTTP/1.1",
+            Phase::H2(_) => "HTTP/2",
+            Phase::Complete => "complete",
+        };
+        f.debug_struct("AutoConnection").field("protocol", &protocol).finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+    use tcio::bytes::Bytes;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt, DuplexStream};
+    use tokio::sync::Notify;
+
+    use super::*;
+    use crate::body::{Full, Incoming};
+    use crate::http::{Request, Response, StatusCode};
+    use crate::server::{AutoServer, MemoryConnector, MemoryListener, Server, TestClient};
+    use crate::service::from_fn;
+    use crate::testing::{MockTimer, block_on, read_frame, read_response, settle};
+
+    const SETTINGS: u8 = 0x4;
+    const GOAWAY: u8 = 0x7;
+
+    async fn hello(_: Request<Incoming>) -> Response<Full<Bytes>> {
+        Response::from_parts(Default::default(), Full::new(Bytes::from_static(b"hello")))
+    }
+
+    /// Upgrade new connection to `h2c`, and send the connection preface.
+    async fn upgrade(connector: &MemoryConnector) -> DuplexStream {
+        // HTTP2-Settings: SETTINGS_MAX_CONCURRENT_STREAMS = 100
+        let mut client = TestClient::new(connector.connect().unwrap());
+        client
+            .send_raw(b"GET / HTTP/1.1\r\n\
+                host: localhost\r\n\
+                connection: Upgrade, HTTP2-Settings\r\n\
+                upgrade: h2c\r\n\
+                http2-settings: AAMAAABk\r\n\r\n")
+            .await
+            .unwrap();
+
+        // frames may directly follow the response head, so read it byte per byte
+        let mut io = client.into_inner();
+        let mut head = Vec::new();
+        while !head.ends_with(b"\r\n\r\n") {
+            head.push(io.read_u8().await.unwrap());
+        }
+        assert!(head.starts_with(b"HTTP/1.1 101 "));
+
+        // connection preface, followed by empty SETTINGS frame
+        io.write_all(b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n\0\0\0\x04\0\0\0\0\0").await.unwrap();
+        io
+    }
+
+    #[test]
+    fn test_h2c_upgrade() {
+        block_on(async {
+            let (listener, connector) = MemoryListener::new();
+            tokio::spawn(AutoServer::new(from_fn(hello), listener));
+
+            // the upgrading request is responded as stream 1
+            let mut io = upgrade(&connector).await;
+            let (block, data) = read_response(&mut io, 1).await;
+
+            // `:status: 200` is indexed in hpack static table
+            assert_eq!(block.first(), Some(&0x88));
+            assert_eq!(data, b"hello");
+        });
+    }
+
+    #[test]
+    fn test_h2c_graceful_shutdown() {
+        block_on(async {
+            let started = Arc::new(Notify::new());
+            let release = Arc::new(Notify::new());
+            let service = {
+                let (started, release) = (started.clone(), release.clone());
+                from_fn(move |request: Request<Incoming>| {
+                    let (started, release) = (started.clone(), release.clone());
+                    async move {
+                        started.notify_one();
+                        release.notified().await;
+                        hello(request).await
+                    }
+                })
+            };
+            let (listener, connector) = MemoryListener::new();
+            let server = AutoServer::new(service, listener);
+            let shutdown = server.shutdown_handle();
+            let server = tokio::spawn(server);
+
+            let mut io = upgrade(&connector).await;
+            started.notified().await;
+            shutdown.shutdown();
+
+            // GOAWAY with stream 1 as the last processed stream
+            let goaway = loop {
+                let frame = read_frame(&mut io).await;
+                if frame.ty == GOAWAY {
+                    break frame;
+                }
+            };
+            assert_eq!(goaway.payload[..4], 1u32.to_be_bytes());
+            assert!(!server.is_finished());
+
+            // in-flight stream is still responded
+            release.notify_one();
+            let (_, data) = read_response(&mut io, 1).await;
+            assert_eq!(data, b"hello");
+
+            // then the connection is closed
+            assert_eq!(io.read(&mut [0; 16]).await.unwrap(), 0);
+            server.await.unwrap();
+        });
+    }
+
+    #[test]
+    fn test_detect_h1_buffered() {
+        block_on(async {
+            let (listener, connector) = MemoryListener::new();
+            let service = from_fn(|request: Request<Incoming>| async move {
+                let method = Bytes::copy_from_slice(request.method().as_str().as_bytes());
+                Response::from_parts(Default::default(), Full::new(method))
+            });
+            tokio::spawn(AutoServer::new(service, listener));
+
+            // `P` is also the first byte of the HTTP/2 preface, thus it is buffered until the next
+            // byte is received
+            let mut io = connector.connect().unwrap();
+            io.write_all(b"P").await.unwrap();
+            settle().await;
+            io.write_all(b"OST / HTTP/1.1\r\nhost: localhost\r\nconnection: close\r\n\r\n").await.unwrap();
+
+            let mut response = Vec::new();
+            io.read_to_end(&mut response).await.unwrap();
+            assert!(response.starts_with(b"HTTP/1.1 200 "));
+            assert!(response.ends_with(b"POST"));
+        });
+    }
+
+    #[test]
+    fn test_detect_h2_buffered() {
+        block_on(async {
+            let (listener, connector) = MemoryListener::new();
+            tokio::spawn(AutoServer::new(from_fn(hello), listener));
+
+            // the preface is split, thus the first part is buffered while detecting
+            let mut io = connector.connect().unwrap();
+            io.write_all(b"PRI * HTTP/2.0\r\n").await.unwrap();
+            settle().await;
+            io.write_all(b"\r\nSM\r\n\r\n\0\0\0\x04\0\0\0\0\0").await.unwrap();
+
+            let frame = read_frame(&mut io).await;
+            assert_eq!(frame.ty, SETTINGS);
+            assert_eq!(frame.stream_id, 0);
+
+            // HEADERS with END_HEADERS and END_STREAM, `:method: GET`, `:scheme: http` and
+            // `:path: /` are indexed in hpack static table, followed by literal `:authority`
+            io.write_all(b"\0\0\x0e\x01\x05\0\0\0\x01\x82\x86\x84\x41\x09localhost").await.unwrap();
+            let (block, data) = read_response(&mut io, 1).await;
+            assert_eq!(block.first(), Some(&0x88));
+            assert_eq!(data, b"hello");
+        });
+    }
+
+    #[test]
+    fn test_detect_timeout() {
+        block_on(async {
+            let timer = MockTimer::new();
+            let config = h1::Config::new()
+                .header_read_timeout(Duration::from_secs(5))
+                .timer(timer.clone());
+            let (listener, connector) = MemoryListener::new();
+            tokio::spawn(Server::with_driver(from_fn(hello), listener, Auto::new(config)));
+
+            let mut io = connector.connect().unwrap();
+            io.write_all(b"PRI").await.unwrap();
+            let read = tokio::spawn(async move { io.read(&mut [0; 16]).await.unwrap() });
+            settle().await;
+            assert!(!read.is_finished());
+
+            // the connection is closed without response
+            timer.advance(Duration::from_secs(5));
+            assert_eq!(read.await.unwrap(), 0);
+        });
+    }
+}
@@ -0,0 +1,310 @@
+//! HTTP/1.1 client for testing.
+use std::io;
+use std::pin::{Pin, pin};
+use tcio::bytes::{Buf, Bytes, BytesMut};
+use tcio::io::{AsyncRead, AsyncWrite};
+
+use crate::body::Body;
+use crate::headers::{HeaderMap, HeaderName, HeaderValue, standard};
+use crate::http::{Method, Request, Response, StatusCode, Version, response};
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+const DEFAULT_BUFFER_CAP: usize = 1024;
+
+// ===== TestClient =====
+
+/// A minimal HTTP/1.1 client for testing a server.
+///
+/// Requests are sent sequentially over a single connection. The response body is read entirely
+/// before [`send`][TestClient::send] returns.
+///
+/// # Examples
+///
+/// ```no_run
+/// # async fn test() -> std::io::Result<()> {
+/// use tsue::body::Full;
+/// use tsue::bytes::Bytes;
+/// use tsue::http::{Request, StatusCode};
+/// use tsue::server::{MemoryListener, TestClient};
+///
+/// let (listener, connector) = MemoryListener::new();
+/// // serve `listener` with `Server`
+///
+/// let mut client = TestClient::new(connector.connect()?);
+/// let response = client.send(Request::<Full<Bytes>>::default()).await?;
+/// assert_eq!(response.status(), &StatusCode::OK);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct TestClient<IO> {
+    io: IO,
+    read_buffer: BytesMut,
+    write_buffer: BytesMut,
+}
+
+impl<IO> TestClient<IO> {
+    /// Create new [`TestClient`] that send requests through `io`.
+    pub fn new(io: IO) -> Self {
+        Self {
+            io,
+            read_buffer: BytesMut::with_capacity(DEFAULT_BUFFER_CAP),
+            write_buffer: BytesMut::with_capacity(DEFAULT_BUFFER_CAP),
+        }
+    }
+
+    /// Returns the underlying IO.
+    #[inline]
+    pub fn into_inner(self) -> IO {
+        self.io
+    }
+}
+
+impl<IO> TestClient<IO>
+where
+    IO: AsyncRead + AsyncWrite + Unpin,
+{
+    /// Send raw bytes to the server.
+    pub async fn send_raw(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.write_buffer.extend_from_slice(bytes);
+        self.flush().await
+    }
+
+    /// Send a request and wait for the response.
+    ///
+    /// If the request does not contains `Host` header, `Host: localhost` is sent. If the request
+    /// body is not empty and the request contains neither `Content-Length` nor
+    /// `Transfer-Encoding`, the `Content-Length` is sent.
+    pub async fn send<B>(&mut self, request: Request<B>) -> io::Result<Response<Bytes>>
+    where
+        B: Body,
+        B::Error: Into<BoxError>,
+    {
+        let (parts, body) = request.into_parts();
+
+        // ===== Request Body =====
+
+        let mut body = pin!(body);
+        let mut content = BytesMut::new();
+        while let Some(result) = std::future::poll_fn(|cx| body.as_mut().poll_data(cx)).await {
+            let mut data = result.map_err(|err| io::Error::other(err.into()))?;
+            while data.has_remaining() {
+                let chunk = data.chunk();
+                content.extend_from_slice(chunk);
+                let len = chunk.len();
+                data.advance(len);
+            }
+        }
+
+        // ===== Request Head =====
+
+        let buf = &mut self.write_buffer;
+        buf.extend_from_slice(parts.method.as_str().as_bytes());
+        buf.extend_from_slice(b" ");
+        buf.extend_from_slice(parts.target.as_str().as_bytes());
+        buf.extend_from_slice(b" HTTP/1.1\r\n");
+
+        if !parts.headers.contains_key(standard::HOST) {
+            buf.extend_from_slice(b"host: localhost\r\n");
+        }
+        if !content.is_empty()
+            && !parts.headers.contains_key(standard::CONTENT_LENGTH)
+            && !parts.headers.contains_key(standard::TRANSFER_ENCODING)
+        {
+            buf.extend_from_slice(format!("content-length: {}\r\n", content.len()).as_bytes());
+        }
+        for (name, value) in parts.headers.pairs() {
+            buf.extend_from_slice(name.as_str().as_bytes());
+            buf.extend_from_slice(b": ");
+            buf.extend_from_slice(value.as_bytes());
+            buf.extend_from_slice(b"\r\n");
+        }
+        buf.extend_from_slice(b"\r\n");
+        buf.extend_from_slice(&content);
+
+        self.flush().await?;
+        self.read_response(&parts.method).await
+    }
+
+    async fn read_response(&mut self, method: &Method) -> io::Result<Response<Bytes>> {
+        // ===== Response Head =====
+
+        let head_len = loop {
+            if let Some(i) = self.read_buffer.windows(4).position(|w| w == b"\r\n\r\n") {
+                break i + 4;
+            }
+            self.fill().await?;
+        };
+        let head = self.read_buffer.split_to(head_len).freeze();
+        let mut lines = head[..head_len - 4].split(|&b| b == b'\n').map(trim_cr);
+
+        let line = lines.next().unwrap_or_default();
+        let mut line = line.splitn(3, |&b| b == b' ');
+        let version = line
+            .next()
+            .and_then(Version::from_bytes)
+            .ok_or_else(|| invalid("invalid response version"))?;
+        let status = line
+            .next()
+            .and_then(|code| std::str::from_utf8(code).ok()?.parse().ok())
+            .and_then(StatusCode::from_u16)
+            .ok_or_else(|| invalid("invalid response status"))?;
+
+        let mut headers = HeaderMap::new();
+        for line in lines {
+            let Some(colon) = line.iter().position(|&b| b == b':') else {
+                return Err(invalid("invalid response header"));
+            };
+            let name = HeaderName::from_slice(&line[..colon])
+                .map_err(|_| invalid("invalid response header name"))?;
+            let value = HeaderValue::from_slice(line[colon + 1..].trim_ascii())
+                .map_err(|_| invalid("invalid response header value"))?;
+            headers.append(name, value);
+        }
+
+        // ===== Response Body =====
+
+        let is_chunked = headers
+            .get_all(&standard::TRANSFER_ENCODING)
+            .any(|value| value.as_bytes().trim_ascii().eq_ignore_ascii_case(b"chunked"));
+        let content_length = match headers.get(standard::CONTENT_LENGTH) {
+            Some(value) => Some(
+                value
+                    .as_str()
+                    .trim()
+                    .parse::<usize>()
+                    .map_err(|_| invalid("invalid response content-length"))?,
+            ),
+            None => None,
+        };
+
+        let body = if *method == Method::HEAD
+            || status.is_informational()
+            || status == StatusCode::NO_CONTENT
+            || status == StatusCode::NOT_MODIFIED
+        {
+            Bytes::new()
+        } else if is_chunked {
+            self.read_chunked().await?
+        } else if let Some(len) = content_length {
+            while self.read_buffer.len() < len {
+                self.fill().await?;
+            }
+            self.read_buffer.split_to(len).freeze()
+        } else {
+            // body is delimited by the connection close
+            while self.read().await? != 0 { }
+            self.read_buffer.split().freeze()
+        };
+
+        let parts = response::Parts {
+            version,
+            status,
+            headers,
+            extensions: Default::default(),
+        };
+        Ok(Response::from_parts(parts, body))
+    }
+
+    async fn read_chunked(&mut self) -> io::Result<Bytes> {
+        let mut body = BytesMut::new();
+        loop {
+            let line = self.read_line().await?;
+            let size = line.split(|&b| b == b';').next().unwrap_or_default();
+            let size = std::str::from_utf8(size.trim_ascii())
+                .ok()
+                .and_then(|size| usize::from_str_radix(size, 16).ok())
+                .ok_or_else(|| invalid("invalid chunk size"))?;
+
+            if size == 0 {
+                // trailer section
+                while !self.read_line().await?.is_empty() { }
+                return Ok(body.freeze());
+            }
+
+            while self.read_buffer.len() < size + 2 {
+                self.fill().await?;
+            }
+            body.extend_from_slice(&self.read_buffer[..size]);
+            if self.read_buffer[size..size + 2] != *b"\r\n" {
+                return Err(invalid("invalid chunk delimiter"));
+            }
+            self.read_buffer.advance(size + 2);
+        }
+    }
+
+    /// Read a line, excluding the line ending.
+    async fn read_line(&mut self) -> io::Result<Vec<u8>> {
+        loop {
+            if let Some(i) = self.read_buffer.iter().position(|&b| b == b'\n') {
+                let line = trim_cr(&self.read_buffer[..i]).to_vec();
+                self.read_buffer.advance(i + 1);
+                return Ok(line);
+            }
+            self.fill().await?;
+        }
+    }
+
+    /// Read more bytes, returns error if the connection is closed.
+    async fn fill(&mut self) -> io::Result<()> {
+        match self.read().await? {
+            0 => Err(io::ErrorKind::UnexpectedEof.into()),
+            _ => Ok(()),
+        }
+    }
+
+    async fn read(&mut self) -> io::Result<usize> {
+        self.read_buffer.reserve(DEFAULT_BUFFER_CAP);
+        std::future::poll_fn(|cx| Pin::new(&mut self.io).poll_read(&mut self.read_buffer, cx)).await
+    }
+
+    async fn flush(&mut self) -> io::Result<()> {
+        std::future::poll_fn(|cx| {
+            Pin::new(&mut self.io).poll_write_all_buf(&mut self.write_buffer, cx)
+        })
+        .await
+    }
+}
+
+fn trim_cr(line: &[u8]) -> &[u8] {
+    line.strip_suffix(b"\r").unwrap_or(line)
+}
+
+fn invalid(msg: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::body::{Full, Incoming};
+    use crate::http::request;
+    use crate::server::{Http1Server, MemoryListener};
+    use crate::service::from_fn;
+    use crate::testing::block_on;
+
+    #[test]
+    fn test_memory_roundtrip() {
+        block_on(async {
+            let (listener, connector) = MemoryListener::new();
+            let service = from_fn(|request: Request<Incoming>| async move {
+                let body = request.into_body().collect().await.unwrap();
+                Response::from_parts(response::Parts::default(), Full::new(body))
+            });
+            tokio::spawn(Http1Server::new(service, listener));
+
+            let mut client = TestClient::new(connector.connect().unwrap());
+            for _ in 0..2 {
+                let parts = request::Parts {
+                    method: Method::POST,
+                    ..Default::default()
+                };
+                let request = Request::from_parts(parts, Full::new(Bytes::from_static(b"hello")));
+                let response = client.send(request).await.unwrap();
+                assert_eq!(response.status(), &StatusCode::OK);
+                assert_eq!(response.body(), &Bytes::from_static(b"hello"));
+            }
+        });
+    }
+}
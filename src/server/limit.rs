@@ -0,0 +1,103 @@
+use std::io;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{Poll, Waker};
+use std::time::Duration;
+
+// ===== ConnectionLimit =====
+
+/// Limit of concurrent connections of a server.
+#[derive(Debug)]
+pub(crate) struct ConnectionLimit {
+    shared: Arc<Mutex<State>>,
+}
+
+#[derive(Debug)]
+struct State {
+    active: usize,
+    max: usize,
+    /// waker of the accept loop, registered when the limit is reached
+    waker: Option<Waker>,
+}
+
+/// A connection slot, released when dropped.
+#[derive(Debug)]
+pub(crate) struct Permit {
+    shared: Arc<Mutex<State>>,
+}
+
+impl ConnectionLimit {
+    pub(crate) fn new(max: usize) -> Self {
+        Self {
+            shared: Arc::new(Mutex::new(State {
+                active: 0,
+                max,
+                waker: None,
+            })),
+        }
+    }
+
+    /// Poll until there is available connection slot.
+    ///
+    /// Only the accept loop should poll this, as only one waker is registered.
+    pub(crate) fn poll_ready(&self, cx: &mut std::task::Context) -> Poll<()> {
+        let mut state = lock(&self.shared);
+        if state.active < state.max {
+            state.waker = None;
+            Poll::Ready(())
+        } else {
+            state.waker = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+
+    /// Acquire a connection slot.
+    ///
+    /// This should be called after [`poll_ready`][Self::poll_ready] returns `Ready`.
+    pub(crate) fn acquire(&self) -> Permit {
+        lock(&self.shared).active += 1;
+        Permit {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        let mut state = lock(&self.shared);
+        state.active -= 1;
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+fn lock(shared: &Mutex<State>) -> MutexGuard<'_, State> {
+    shared.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+// ===== Accept Backoff =====
+
+const MIN_BACKOFF: Duration = Duration::from_millis(5);
+
+const MAX_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Returns `true` if accept error only affect single connection, thus next accept can be retried
+/// immediately.
+pub(crate) fn is_connection_error(err: &io::Error) -> bool {
+    matches!(
+        err.kind(),
+        io::ErrorKind::ConnectionRefused
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::Interrupted
+            | io::ErrorKind::WouldBlock
+    )
+}
+
+/// Returns the next backoff duration, which is doubled every consecutive error.
+pub(crate) fn next_backoff(current: Option<Duration>) -> Duration {
+    match current {
+        Some(backoff) => (backoff * 2).min(MAX_BACKOFF),
+        None => MIN_BACKOFF,
+    }
+}
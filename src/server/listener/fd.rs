@@ -0,0 +1,285 @@
+//! Listener from inherited file descriptors.
+//!
+//! This allows the listening socket to be created by another process, such as [systemd socket
+//! activation], a process supervisor, or the previous instance of the application. It enables
+//! zero downtime restart, and binding privileged port without running the application as root.
+//!
+//! [systemd socket activation]: <https://www.freedesktop.org/software/systemd/man/latest/sd_listen_fds.html>
+use std::io;
+use std::mem::ManuallyDrop;
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd, FromRawFd, OwnedFd, RawFd};
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::net::{TcpListener, UnixListener};
+
+/// The first file descriptor passed by systemd, `SD_LISTEN_FDS_START`.
+const LISTEN_FDS_START: RawFd = 3;
+
+/// Whether the file descriptors in environment variables is already taken.
+static IS_TAKEN: AtomicBool = AtomicBool::new(false);
+
+// ===== ListenFd =====
+
+/// A listening socket inherited from another process.
+///
+/// Use [`into_tcp`][ListenFd::into_tcp] or [`into_unix`][ListenFd::into_unix] to convert it into
+/// a [`Listener`][super::Listener].
+#[derive(Debug)]
+pub struct ListenFd {
+    fd: OwnedFd,
+    name: Option<String>,
+}
+
+impl ListenFd {
+    /// Create [`ListenFd`] from an owned file descriptor.
+    #[inline]
+    pub fn new(fd: OwnedFd) -> Self {
+        Self { fd, name: None }
+    }
+
+    /// Returns the name of the socket, as configured with `FileDescriptorName=` in systemd.
+    #[inline]
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Convert into [`TcpListener`].
+    ///
+    /// Returns error if the socket is not an IPv4 or IPv6 socket. The socket is not closed, it
+    /// can be taken back with [`ListenFdError::into_inner`].
+    ///
+    /// # Panics
+    ///
+    /// This function panics if it is not called from within a tokio runtime with IO enabled.
+    pub fn into_tcp(self) -> Result<TcpListener, ListenFdError> {
+        // fails if the socket address is not an internet address
+        let fd = self.check(|listener: &std::net::TcpListener| {
+            listener.local_addr()?;
+            listener.set_nonblocking(true)
+        })?;
+        Ok(TcpListener::from_std(fd.into())?)
+    }
+
+    /// Convert into [`UnixListener`].
+    ///
+    /// Returns error if the socket is not a unix domain socket. The socket is not closed, it can
+    /// be taken back with [`ListenFdError::into_inner`].
+    ///
+    /// # Panics
+    ///
+    /// This function panics if it is not called from within a tokio runtime with IO enabled.
+    pub fn into_unix(self) -> Result<UnixListener, ListenFdError> {
+        // fails if the socket address is not a unix address
+        let fd = self.check(|listener: &std::os::unix::net::UnixListener| {
+            listener.local_addr()?;
+            listener.set_nonblocking(true)
+        })?;
+        Ok(UnixListener::from_std(fd.into())?)
+    }
+
+    /// Run `check` on the borrowed socket, the socket is returned in the error if it fails.
+    fn check<L, F>(self, check: F) -> Result<OwnedFd, ListenFdError>
+    where
+        L: FromRawFd,
+        F: FnOnce(&L) -> io::Result<()>,
+    {
+        // SAFETY: the descriptor is open, and `ManuallyDrop` prevents it from being closed twice
+        let borrowed = ManuallyDrop::new(unsafe { L::from_raw_fd(self.fd.as_raw_fd()) });
+        match check(&*borrowed) {
+            Ok(()) => Ok(self.fd),
+            Err(error) => Err(ListenFdError {
+                error,
+                fd: Some(self),
+            }),
+        }
+    }
+}
+
+impl From<OwnedFd> for ListenFd {
+    #[inline]
+    fn from(fd: OwnedFd) -> Self {
+        Self::new(fd)
+    }
+}
+
+impl FromRawFd for ListenFd {
+    #[inline]
+    unsafe fn from_raw_fd(fd: RawFd) -> Self {
+        // SAFETY: the caller guarantees that `fd` is open and owned
+        Self::new(unsafe { OwnedFd::from_raw_fd(fd) })
+    }
+}
+
+impl AsFd for ListenFd {
+    #[inline]
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.fd.as_fd()
+    }
+}
+
+impl AsRawFd for ListenFd {
+    #[inline]
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
+
+// ===== ListenFdError =====
+
+/// Error returned when converting [`ListenFd`] into a listener.
+#[derive(Debug)]
+pub struct ListenFdError {
+    error: io::Error,
+    fd: Option<ListenFd>,
+}
+
+impl ListenFdError {
+    /// Returns the [`ListenFd`] that failed to convert.
+    ///
+    /// Returns `None` if the socket is already consumed when the error occurs.
+    #[inline]
+    pub fn into_inner(self) -> Option<ListenFd> {
+        self.fd
+    }
+}
+
+impl From<io::Error> for ListenFdError {
+    #[inline]
+    fn from(error: io::Error) -> Self {
+        Self { error, fd: None }
+    }
+}
+
+impl From<ListenFdError> for io::Error {
+    #[inline]
+    fn from(err: ListenFdError) -> Self {
+        err.error
+    }
+}
+
+impl std::error::Error for ListenFdError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+impl std::fmt::Display for ListenFdError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(&self.error, f)
+    }
+}
+
+// ===== listen_fds =====
+
+/// Returns the sockets passed with systemd socket activation protocol.
+///
+/// The sockets are read from `LISTEN_FDS`, `LISTEN_PID`, and `LISTEN_FDNAMES` environment
+/// variables. If the variables are not set, or `LISTEN_PID` is not the current process, an empty
+/// list is returned.
+///
+/// The file descriptors are owned by the returned [`ListenFd`], thus subsequent call will always
+/// returns an empty list. The environment variables are not unset.
+pub fn listen_fds() -> io::Result<Vec<ListenFd>> {
+    let names = parse_env(
+        std::env::var("LISTEN_PID").ok().as_deref(),
+        std::env::var("LISTEN_FDS").ok().as_deref(),
+        std::env::var("LISTEN_FDNAMES").ok().as_deref(),
+        std::process::id(),
+    )?;
+
+    if names.is_empty() || IS_TAKEN.swap(true, Ordering::AcqRel) {
+        return Ok(Vec::new());
+    }
+
+    let mut fds = Vec::with_capacity(names.len());
+    for (fd, name) in (LISTEN_FDS_START..).zip(names) {
+        // SAFETY: the file descriptors is passed by the parent process for this process, and
+        // `IS_TAKEN` guarantees it is only owned once
+        let inherited = unsafe { OwnedFd::from_raw_fd(fd) };
+        // systemd does not set `FD_CLOEXEC`, duplicating the descriptor set it, so the socket is
+        // not leaked into child processes
+        let fd = inherited.try_clone()?;
+        drop(inherited);
+        fds.push(ListenFd { fd, name });
+    }
+    Ok(fds)
+}
+
+/// Returns the name of each passed file descriptor.
+fn parse_env(
+    pid: Option<&str>,
+    fds: Option<&str>,
+    names: Option<&str>,
+    current_pid: u32,
+) -> io::Result<Vec<Option<String>>> {
+    let (Some(pid), Some(fds)) = (pid, fds) else {
+        return Ok(Vec::new());
+    };
+
+    let Ok(pid) = pid.parse::<u32>() else {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "invalid LISTEN_PID"));
+    };
+    if pid != current_pid {
+        return Ok(Vec::new());
+    }
+
+    let Ok(count) = fds.parse::<usize>() else {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "invalid LISTEN_FDS"));
+    };
+    if count > (RawFd::MAX - LISTEN_FDS_START) as usize {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "invalid LISTEN_FDS"));
+    }
+
+    // names is ignored if it does not match the number of file descriptors
+    match names.map(|names| names.split(':').collect::<Vec<_>>()) {
+        Some(names) if names.len() == count => {
+            Ok(names.into_iter().map(|name| Some(name.to_owned())).collect())
+        }
+        _ => Ok(vec![None; count]),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testing::block_on;
+
+    #[test]
+    fn test_parse_env() {
+        assert!(parse_env(None, None, None, 7).unwrap().is_empty());
+        assert!(parse_env(Some("7"), None, None, 7).unwrap().is_empty());
+        assert!(parse_env(Some("8"), Some("2"), None, 7).unwrap().is_empty());
+        assert_eq!(parse_env(Some("7"), Some("2"), None, 7).unwrap(), [None, None]);
+        assert_eq!(
+            parse_env(Some("7"), Some("2"), Some("http:https"), 7).unwrap(),
+            [Some("http".into()), Some("https".into())]
+        );
+        assert_eq!(parse_env(Some("7"), Some("1"), Some("http:https"), 7).unwrap(), [None]);
+        assert!(parse_env(Some("a"), Some("1"), None, 7).is_err());
+        assert!(parse_env(Some("7"), Some("-1"), None, 7).is_err());
+    }
+
+    #[test]
+    fn test_into_listener() {
+        block_on(async {
+            let tcp = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            let addr = tcp.local_addr().unwrap();
+            let listener = ListenFd::from(OwnedFd::from(tcp)).into_tcp().unwrap();
+            assert_eq!(listener.local_addr().unwrap(), addr);
+
+            // the socket is kept when converting into the wrong type
+            let tcp = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            let addr = tcp.local_addr().unwrap();
+            let err = ListenFd::from(OwnedFd::from(tcp)).into_unix().unwrap_err();
+            let listener = err.into_inner().unwrap().into_tcp().unwrap();
+            assert_eq!(listener.local_addr().unwrap(), addr);
+
+            let path = std::env::temp_dir().join(format!("tsue-fd-{}.sock", std::process::id()));
+            let _ = std::fs::remove_file(&path);
+            let unix = std::os::unix::net::UnixListener::bind(&path).unwrap();
+            let err = ListenFd::from(OwnedFd::from(unix)).into_tcp().unwrap_err();
+            let listener = err.into_inner().unwrap().into_unix().unwrap();
+            assert_eq!(listener.local_addr().unwrap().as_pathname(), Some(path.as_path()));
+            let _ = std::fs::remove_file(&path);
+        });
+    }
+}
@@ -0,0 +1,181 @@
+use std::io;
+use std::pin::Pin;
+use std::task::Poll;
+use tcio::io::{AsyncRead, AsyncWrite};
+
+use super::Listener;
+use crate::http::ConnectionInfo;
+
+const DEFAULT_MAX_PENDING: usize = 128;
+
+// ===== MapStream =====
+
+/// A [`Listener`] that map each accepted stream with an async function.
+///
+/// Created by [`Listener::map_stream`]. Streams are mapped concurrently, thus a slow stream does
+/// not block other streams to be accepted.
+///
+/// An error returned by the function is reported as
+/// [`ConnectionAborted`][io::ErrorKind::ConnectionAborted], thus the server can continue to accept
+/// other connections.
+pub struct MapStream<L, F, Fut>
+where
+    L: Listener,
+{
+    listener: L,
+    f: F,
+    pending: Vec<(Pin<Box<Fut>>, MappedAddr<L::Addr>)>,
+    max_pending: usize,
+}
+
+impl<L, F, Fut> MapStream<L, F, Fut>
+where
+    L: Listener,
+{
+    pub(crate) fn new(listener: L, f: F) -> Self {
+        Self {
+            listener,
+            f,
+            pending: Vec::new(),
+            max_pending: DEFAULT_MAX_PENDING,
+        }
+    }
+
+    /// Set the maximum number of streams that are being mapped at the same time, default to 128.
+    ///
+    /// When the limit is reached, no more stream is accepted until one of the streams is mapped.
+    pub fn max_pending(mut self, max: usize) -> Self {
+        self.max_pending = max;
+        self
+    }
+}
+
+impl<L, F, Fut, T, E> Listener for MapStream<L, F, Fut>
+where
+    L: Listener,
+    F: FnMut(L::Stream) -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+    T: AsyncRead + AsyncWrite,
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    type Stream = T;
+
+    type Addr = MappedAddr<L::Addr>;
+
+    fn poll_accept(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context,
+    ) -> Poll<io::Result<(Self::Stream, Self::Addr)>> {
+        // SAFETY: `listener` is never moved, and `pending` futures are boxed
+        let me = unsafe { self.get_unchecked_mut() };
+        let mut listener = unsafe { Pin::new_unchecked(&mut me.listener) };
+
+        while me.pending.len() < me.max_pending {
+            let (stream, addr) = match listener.as_mut().poll_accept(cx) {
+                Poll::Ready(Ok(ok)) => ok,
+                Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+                Poll::Pending => break,
+            };
+            let info = L::connection_info(&stream, &addr);
+            me.pending.push((Box::pin((me.f)(stream)), MappedAddr { addr, info }));
+        }
+
+        for i in 0..me.pending.len() {
+            let Poll::Ready(result) = me.pending[i].0.as_mut().poll(cx) else {
+                continue;
+            };
+            let (_, addr) = me.pending.swap_remove(i);
+            return Poll::Ready(match result {
+                Ok(stream) => Ok((stream, addr)),
+                Err(err) => Err(io::Error::new(io::ErrorKind::ConnectionAborted, err)),
+            });
+        }
+
+        Poll::Pending
+    }
+
+    #[inline]
+    fn connection_info(_: &Self::Stream, addr: &Self::Addr) -> ConnectionInfo {
+        addr.info.clone()
+    }
+}
+
+impl<L, F, Fut> std::fmt::Debug for MapStream<L, F, Fut>
+where
+    L: Listener + std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MapStream")
+            .field("listener", &self.listener)
+            .field("pending", &self.pending.len())
+            .field("max_pending", &self.max_pending)
+            .finish_non_exhaustive()
+    }
+}
+
+// ===== MappedAddr =====
+
+/// Address of a stream accepted by [`MapStream`].
+///
+/// It also holds the [`ConnectionInfo`] of the original stream.
+#[derive(Debug, Clone)]
+pub struct MappedAddr<A> {
+    addr: A,
+    info: ConnectionInfo,
+}
+
+impl<A> MappedAddr<A> {
+    /// Returns the address of the original listener.
+    #[inline]
+    pub fn addr(&self) -> &A {
+        &self.addr
+    }
+
+    /// Returns the [`ConnectionInfo`] of the original stream.
+    #[inline]
+    pub fn info(&self) -> &ConnectionInfo {
+        &self.info
+    }
+
+    /// Returns the address of the original listener.
+    #[inline]
+    pub fn into_addr(self) -> A {
+        self.addr
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::server::MemoryListener;
+    use crate::testing::block_on;
+
+    #[test]
+    fn test_map_stream_error() {
+        block_on(async {
+            let (listener, connector) = MemoryListener::new();
+            let mut count = 0;
+            let mut listener = listener.map_stream(move |stream| {
+                count += 1;
+                let is_rejected = count == 1;
+                async move {
+                    if is_rejected {
+                        Err("handshake failed")
+                    } else {
+                        Ok(stream)
+                    }
+                }
+            });
+
+            let _first = connector.connect().unwrap();
+            let _second = connector.connect().unwrap();
+
+            let result = std::future::poll_fn(|cx| Pin::new(&mut listener).poll_accept(cx)).await;
+            assert_eq!(result.unwrap_err().kind(), io::ErrorKind::ConnectionAborted);
+
+            // the next stream is still accepted
+            let result = std::future::poll_fn(|cx| Pin::new(&mut listener).poll_accept(cx)).await;
+            assert!(result.is_ok());
+        });
+    }
+}
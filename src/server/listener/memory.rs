@@ -0,0 +1,81 @@
+//! In-memory listener.
+use std::io;
+use std::pin::Pin;
+use std::task::Poll;
+use tokio::io::DuplexStream;
+use tokio::sync::mpsc;
+
+use super::Listener;
+
+const DEFAULT_BUFFER_SIZE: usize = 64 * 1024;
+
+// ===== MemoryListener =====
+
+/// A [`Listener`] that accept in-memory streams, created with [`MemoryConnector`].
+///
+/// This allows a server to be tested end to end without binding a socket.
+///
+/// When all connectors are dropped, the listener will never accept new stream.
+#[derive(Debug)]
+pub struct MemoryListener {
+    rx: mpsc::UnboundedReceiver<DuplexStream>,
+}
+
+/// Create a stream connected to a [`MemoryListener`].
+#[derive(Debug, Clone)]
+pub struct MemoryConnector {
+    tx: mpsc::UnboundedSender<DuplexStream>,
+    buffer_size: usize,
+}
+
+impl MemoryListener {
+    /// Create new [`MemoryListener`] and its [`MemoryConnector`].
+    pub fn new() -> (Self, MemoryConnector) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let connector = MemoryConnector {
+            tx,
+            buffer_size: DEFAULT_BUFFER_SIZE,
+        };
+        (Self { rx }, connector)
+    }
+}
+
+impl MemoryConnector {
+    /// Set the buffer size of each direction of created streams, default to 64KB.
+    ///
+    /// Write to a stream is pending when the buffer is full.
+    #[inline]
+    pub fn buffer_size(mut self, size: usize) -> Self {
+        self.buffer_size = size;
+        self
+    }
+
+    /// Create new stream, the other half is accepted by the [`MemoryListener`].
+    ///
+    /// Returns [`ConnectionRefused`][io::ErrorKind::ConnectionRefused] error if the listener is
+    /// dropped.
+    pub fn connect(&self) -> io::Result<DuplexStream> {
+        let (client, server) = tokio::io::duplex(self.buffer_size);
+        match self.tx.send(server) {
+            Ok(()) => Ok(client),
+            Err(_) => Err(io::ErrorKind::ConnectionRefused.into()),
+        }
+    }
+}
+
+impl Listener for MemoryListener {
+    type Stream = DuplexStream;
+
+    type Addr = ();
+
+    fn poll_accept(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context,
+    ) -> Poll<io::Result<(Self::Stream, Self::Addr)>> {
+        match self.get_mut().rx.poll_recv(cx) {
+            Poll::Ready(Some(stream)) => Poll::Ready(Ok((stream, ()))),
+            // all connectors are dropped, no more stream will be accepted
+            Poll::Ready(None) | Poll::Pending => Poll::Pending,
+        }
+    }
+}
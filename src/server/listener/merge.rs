@@ -0,0 +1,223 @@
+use std::io;
+use std::pin::Pin;
+use std::task::Poll;
+use tcio::io::{AsyncRead, AsyncWrite};
+
+use super::Listener;
+use crate::http::ConnectionInfo;
+
+// ===== Merge =====
+
+/// A [`Listener`] that accept from two listeners.
+///
+/// Created by [`Listener::merge`]. Both listeners are polled in turn, so one busy listener does not
+/// starve the other.
+#[derive(Debug)]
+pub struct Merge<A, B> {
+    left: A,
+    right: B,
+    is_right_first: bool,
+}
+
+impl<A, B> Merge<A, B> {
+    pub(crate) fn new(left: A, right: B) -> Self {
+        Self {
+            left,
+            right,
+            is_right_first: false,
+        }
+    }
+
+    /// Returns the inner listeners.
+    pub fn into_inner(self) -> (A, B) {
+        (self.left, self.right)
+    }
+}
+
+impl<A, B> Listener for Merge<A, B>
+where
+    A: Listener,
+    B: Listener,
+    Either<A::Stream, B::Stream>: AsyncRead + AsyncWrite,
+{
+    type Stream = Either<A::Stream, B::Stream>;
+
+    type Addr = Either<A::Addr, B::Addr>;
+
+    fn poll_accept(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context,
+    ) -> Poll<io::Result<(Self::Stream, Self::Addr)>> {
+        // SAFETY: `left` and `right` are never moved
+        let me = unsafe { self.get_unchecked_mut() };
+        let left = unsafe { Pin::new_unchecked(&mut me.left) };
+        let right = unsafe { Pin::new_unchecked(&mut me.right) };
+
+        me.is_right_first = !me.is_right_first;
+
+        if me.is_right_first {
+            if let Poll::Ready(result) = poll_right::<A, B>(right, cx) {
+                return Poll::Ready(result);
+            }
+            poll_left::<A, B>(left, cx)
+        } else {
+            if let Poll::Ready(result) = poll_left::<A, B>(left, cx) {
+                return Poll::Ready(result);
+            }
+            poll_right::<A, B>(right, cx)
+        }
+    }
+
+    fn connection_info(stream: &Self::Stream, addr: &Self::Addr) -> ConnectionInfo {
+        match (stream, addr) {
+            (Either::Left(stream), Either::Left(addr)) => A::connection_info(stream, addr),
+            (Either::Right(stream), Either::Right(addr)) => B::connection_info(stream, addr),
+            _ => unreachable!("stream and address are accepted from the same listener"),
+        }
+    }
+}
+
+type Accepted<A, B> = io::Result<(
+    Either<<A as Listener>::Stream, <B as Listener>::Stream>,
+    Either<<A as Listener>::Addr, <B as Listener>::Addr>,
+)>;
+
+fn poll_left<A: Listener, B: Listener>(
+    left: Pin<&mut A>,
+    cx: &mut std::task::Context,
+) -> Poll<Accepted<A, B>> {
+    left.poll_accept(cx)
+        .map_ok(|(stream, addr)| (Either::Left(stream), Either::Left(addr)))
+}
+
+fn poll_right<A: Listener, B: Listener>(
+    right: Pin<&mut B>,
+    cx: &mut std::task::Context,
+) -> Poll<Accepted<A, B>> {
+    right.poll_accept(cx)
+        .map_ok(|(stream, addr)| (Either::Right(stream), Either::Right(addr)))
+}
+
+// ===== Either =====
+
+/// Either one of two types.
+///
+/// Used as the stream and address of [`Merge`] listener.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Either<A, B> {
+    Left(A),
+    Right(B),
+}
+
+impl<A, B> std::fmt::Display for Either<A, B>
+where
+    A: std::fmt::Display,
+    B: std::fmt::Display,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Left(a) => a.fmt(f),
+            Self::Right(b) => b.fmt(f),
+        }
+    }
+}
+
+macro_rules! project {
+    ($self:ident, $id:ident => $e:expr) => {
+        // SAFETY: the variant is never moved
+        match unsafe { $self.get_unchecked_mut() } {
+            Either::Left($id) => {
+                let $id = unsafe { Pin::new_unchecked($id) };
+                $e
+            }
+            Either::Right($id) => {
+                let $id = unsafe { Pin::new_unchecked($id) };
+                $e
+            }
+        }
+    };
+}
+
+impl<A, B> tokio::io::AsyncRead for Either<A, B>
+where
+    A: tokio::io::AsyncRead,
+    B: tokio::io::AsyncRead,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        project!(self, io => io.poll_read(cx, buf))
+    }
+}
+
+impl<A, B> tokio::io::AsyncWrite for Either<A, B>
+where
+    A: tokio::io::AsyncWrite,
+    B: tokio::io::AsyncWrite,
+{
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        project!(self, io => io.poll_write(cx, buf))
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        project!(self, io => io.poll_write_vectored(cx, bufs))
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        match self {
+            Self::Left(io) => io.is_write_vectored(),
+            Self::Right(io) => io.is_write_vectored(),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> Poll<io::Result<()>> {
+        project!(self, io => io.poll_flush(cx))
+    }
+
+    fn poll_shutdown(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        project!(self, io => io.poll_shutdown(cx))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::server::MemoryListener;
+    use crate::testing::block_on;
+
+    #[test]
+    fn test_merge() {
+        block_on(async {
+            let (left, left_connector) = MemoryListener::new();
+            let (right, right_connector) = MemoryListener::new();
+            let mut listener = left.merge(right);
+
+            let _left = left_connector.connect().unwrap();
+            let _right = right_connector.connect().unwrap();
+            let _right = right_connector.connect().unwrap();
+
+            // listeners are polled in turn
+            let mut accepted = Vec::new();
+            for _ in 0..3 {
+                let (_, addr) = std::future::poll_fn(|cx| Pin::new(&mut listener).poll_accept(cx))
+                    .await
+                    .unwrap();
+                accepted.push(addr);
+            }
+            assert_eq!(accepted, [Either::Right(()), Either::Left(()), Either::Right(())]);
+        });
+    }
+}
@@ -0,0 +1,107 @@
+use std::{io, net::SocketAddr, pin::Pin, task::Poll};
+use tokio::net::{TcpListener, TcpStream};
+use tcio::io::{AsyncRead, AsyncWrite};
+
+use crate::http::ConnectionInfo;
+
+#[cfg(unix)]
+use tokio::net::{UnixListener, UnixStream};
+
+mod merge;
+mod map;
+mod proxy;
+mod memory;
+#[cfg(unix)]
+mod fd;
+
+pub use merge::{Either, Merge};
+pub use map::{MapStream, MappedAddr};
+pub use proxy::{ProxyAddr, ProxyListener, ProxyStream};
+pub use memory::{MemoryConnector, MemoryListener};
+#[cfg(unix)]
+pub use fd::{ListenFd, ListenFdError, listen_fds};
+
+// ===== Listener =====
+
+pub trait Listener {
+    type Stream: AsyncRead + AsyncWrite;
+
+    type Addr;
+
+    fn poll_accept(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context,
+    ) -> Poll<io::Result<(Self::Stream, Self::Addr)>>;
+
+    /// Returns the [`ConnectionInfo`] of an accepted stream.
+    ///
+    /// The default implementation returns empty connection info.
+    #[inline]
+    fn connection_info(stream: &Self::Stream, addr: &Self::Addr) -> ConnectionInfo {
+        let _ = (stream, addr);
+        ConnectionInfo::default()
+    }
+
+    /// Accept from both this listener and `other`.
+    ///
+    /// The stream and address of each listener is wrapped in [`Either`].
+    #[inline]
+    fn merge<L>(self, other: L) -> Merge<Self, L>
+    where
+        Self: Sized,
+        L: Listener,
+    {
+        Merge::new(self, other)
+    }
+
+    /// Map each accepted stream with an async function, such as handshake wrapper.
+    #[inline]
+    fn map_stream<F, Fut>(self, f: F) -> MapStream<Self, F, Fut>
+    where
+        Self: Sized,
+        F: FnMut(Self::Stream) -> Fut,
+    {
+        MapStream::new(self, f)
+    }
+}
+
+// ===== impl Listener =====
+
+impl Listener for TcpListener {
+    type Stream = TcpStream;
+
+    type Addr = SocketAddr;
+
+    #[inline]
+    fn poll_accept(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context,
+    ) -> Poll<io::Result<(Self::Stream, Self::Addr)>> {
+        TcpListener::poll_accept(&self, cx)
+    }
+
+    #[inline]
+    fn connection_info(stream: &Self::Stream, addr: &Self::Addr) -> ConnectionInfo {
+        ConnectionInfo::new(stream.local_addr().ok().map(Into::into), Some((*addr).into()))
+    }
+}
+
+#[cfg(unix)]
+impl Listener for UnixListener {
+    type Stream = UnixStream;
+
+    type Addr = tokio::net::unix::SocketAddr;
+
+    #[inline]
+    fn poll_accept(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context,
+    ) -> Poll<io::Result<(Self::Stream, Self::Addr)>> {
+        UnixListener::poll_accept(&self, cx)
+    }
+
+    #[inline]
+    fn connection_info(stream: &Self::Stream, addr: &Self::Addr) -> ConnectionInfo {
+        ConnectionInfo::new(stream.local_addr().ok().map(Into::into), Some(addr.clone().into()))
+    }
+}
@@ -0,0 +1,609 @@
+//! [PROXY protocol] listener.
+//!
+//! [PROXY protocol]: <https://www.haproxy.org/download/2.9/doc/proxy-protocol.txt>
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Poll, ready};
+use std::time::Duration;
+use tcio::bytes::{Buf, BytesMut};
+use tcio::io::{AsyncRead, AsyncWrite};
+
+use super::Listener;
+use crate::http::ConnectionInfo;
+use crate::rt::{Sleep, Timer, TokioTimer};
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+const DEFAULT_MAX_PENDING: usize = 128;
+
+const V1_PREFIX: &[u8; 6] = b"PROXY ";
+
+/// Maximum length of v1 header, including the CRLF.
+const V1_MAX_LEN: usize = 107;
+
+const V2_SIGNATURE: &[u8; 12] = b"\r\n\r\n\0\r\nQUIT\n";
+
+const V2_HEADER_LEN: usize = 16;
+
+// ===== ProxyListener =====
+
+/// A [`Listener`] that read PROXY protocol header from each accepted stream.
+///
+/// Both v1 (text) and v2 (binary) header are supported. The original client and destination
+/// address is available in [`ProxyAddr`], and in the [`ConnectionInfo`] of each request.
+///
+/// Stream with malformed header, or which header is not received within the timeout, is rejected
+/// with [`ConnectionAborted`][io::ErrorKind::ConnectionAborted] error.
+pub struct ProxyListener<L>
+where
+    L: Listener,
+{
+    listener: L,
+    timeout: Duration,
+    timer: Arc<dyn Timer + Send + Sync>,
+    pending: Vec<Handshake<L::Stream, L::Addr>>,
+    max_pending: usize,
+}
+
+impl<L> ProxyListener<L>
+where
+    L: Listener,
+{
+    /// Create new [`ProxyListener`].
+    pub fn new(listener: L) -> Self {
+        Self {
+            listener,
+            timeout: DEFAULT_TIMEOUT,
+            timer: Arc::new(TokioTimer),
+            pending: Vec::new(),
+            max_pending: DEFAULT_MAX_PENDING,
+        }
+    }
+
+    /// Set the maximum duration to receive the header, default to 10 seconds.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Set the [`Timer`] used for header timeout, default to [`TokioTimer`].
+    pub fn timer<T>(mut self, timer: T) -> Self
+    where
+        T: Timer + Send + Sync + 'static,
+    {
+        self.timer = Arc::new(timer);
+        self
+    }
+
+    /// Set the maximum number of streams that are reading the header at the same time, default
+    /// to 128.
+    ///
+    /// When the limit is reached, no more stream is accepted until one of the header is read.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max` is zero.
+    pub fn max_pending(mut self, max: usize) -> Self {
+        assert!(max != 0, "maximum pending streams must be greater than zero");
+        self.max_pending = max;
+        self
+    }
+}
+
+impl<L> Listener for ProxyListener<L>
+where
+    L: Listener<Stream: Unpin>,
+    ProxyStream<L::Stream>: AsyncRead + AsyncWrite,
+{
+    type Stream = ProxyStream<L::Stream>;
+
+    type Addr = ProxyAddr<L::Addr>;
+
+    fn poll_accept(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context,
+    ) -> Poll<io::Result<(Self::Stream, Self::Addr)>> {
+        // SAFETY: `listener` is never moved
+        let me = unsafe { self.get_unchecked_mut() };
+        let mut listener = unsafe { Pin::new_unchecked(&mut me.listener) };
+
+        while me.pending.len() < me.max_pending {
+            let (stream, addr) = match listener.as_mut().poll_accept(cx) {
+                Poll::Ready(Ok(ok)) => ok,
+                Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+                Poll::Pending => break,
+            };
+            let info = L::connection_info(&stream, &addr);
+            me.pending.push(Handshake {
+                stream: Some(stream),
+                addr: Some(addr),
+                info,
+                read_buffer: BytesMut::with_capacity(V1_MAX_LEN),
+                sleep: me.timer.sleep(me.timeout),
+            });
+        }
+
+        for i in 0..me.pending.len() {
+            let Poll::Ready(result) = me.pending[i].poll(cx) else {
+                continue;
+            };
+            me.pending.swap_remove(i);
+            return Poll::Ready(result.map_err(|err| {
+                io::Error::new(io::ErrorKind::ConnectionAborted, err)
+            }));
+        }
+
+        Poll::Pending
+    }
+
+    #[inline]
+    fn connection_info(_: &Self::Stream, addr: &Self::Addr) -> ConnectionInfo {
+        addr.info.clone()
+    }
+}
+
+impl<L> std::fmt::Debug for ProxyListener<L>
+where
+    L: Listener + std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProxyListener")
+            .field("listener", &self.listener)
+            .field("timeout", &self.timeout)
+            .field("pending", &self.pending.len())
+            .field("max_pending", &self.max_pending)
+            .finish_non_exhaustive()
+    }
+}
+
+// ===== Handshake =====
+
+struct Handshake<S, A> {
+    stream: Option<S>,
+    addr: Option<A>,
+    info: ConnectionInfo,
+    read_buffer: BytesMut,
+    sleep: Pin<Box<dyn Sleep>>,
+}
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+impl<S, A> Handshake<S, A>
+where
+    S: AsyncRead + Unpin,
+{
+    fn poll(
+        &mut self,
+        cx: &mut std::task::Context,
+    ) -> Poll<Result<(ProxyStream<S>, ProxyAddr<A>), BoxError>> {
+        let stream = self.stream.as_mut().expect("poll after complete");
+
+        let (addrs, len) = loop {
+            if let Some(header) = parse_header(&self.read_buffer)? {
+                break header;
+            }
+            match Pin::new(&mut *stream).poll_read(&mut self.read_buffer, cx) {
+                Poll::Ready(Ok(0)) => return Poll::Ready(Err(ProxyError::Eof.into())),
+                Poll::Ready(Ok(_)) => {}
+                Poll::Ready(Err(err)) => return Poll::Ready(Err(err.into())),
+                Poll::Pending => {
+                    ready!(self.sleep.as_mut().poll(cx));
+                    return Poll::Ready(Err(ProxyError::Timeout.into()));
+                }
+            }
+        };
+
+        self.read_buffer.advance(len);
+
+        let (source, destination) = match addrs {
+            Some((source, destination)) => (Some(source), Some(destination)),
+            None => (None, None),
+        };
+        let info = ConnectionInfo::new(
+            destination.map(Into::into).or_else(|| self.info.local_addr().cloned()),
+            source.map(Into::into).or_else(|| self.info.peer_addr().cloned()),
+        );
+        let stream = ProxyStream {
+            prefix: self.read_buffer.split(),
+            inner: self.stream.take().expect("checked"),
+        };
+        let addr = ProxyAddr {
+            source,
+            destination,
+            addr: self.addr.take().expect("poll after complete"),
+            info,
+        };
+        Poll::Ready(Ok((stream, addr)))
+    }
+}
+
+// ===== ProxyAddr =====
+
+/// Address of a stream accepted by [`ProxyListener`].
+#[derive(Debug, Clone)]
+pub struct ProxyAddr<A> {
+    source: Option<SocketAddr>,
+    destination: Option<SocketAddr>,
+    addr: A,
+    info: ConnectionInfo,
+}
+
+impl<A> ProxyAddr<A> {
+    /// Returns the original client address.
+    ///
+    /// Returns `None` if the header does not contains address, such as the `LOCAL` command or
+    /// `UNKNOWN` protocol.
+    #[inline]
+    pub fn source(&self) -> Option<&SocketAddr> {
+        self.source.as_ref()
+    }
+
+    /// Returns the original destination address.
+    ///
+    /// Returns `None` if the header does not contains address, such as the `LOCAL` command or
+    /// `UNKNOWN` protocol.
+    #[inline]
+    pub fn destination(&self) -> Option<&SocketAddr> {
+        self.destination.as_ref()
+    }
+
+    /// Returns the address of the proxy, as accepted by the inner listener.
+    #[inline]
+    pub fn proxy_addr(&self) -> &A {
+        &self.addr
+    }
+}
+
+// ===== ProxyStream =====
+
+/// A stream accepted by [`ProxyListener`].
+///
+/// Bytes that are read after the header are returned first before reading from the inner stream.
+#[derive(Debug)]
+pub struct ProxyStream<S> {
+    prefix: BytesMut,
+    inner: S,
+}
+
+impl<S> ProxyStream<S> {
+    /// Returns a reference to the inner stream.
+    #[inline]
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+}
+
+impl<S> tokio::io::AsyncRead for ProxyStream<S>
+where
+    S: tokio::io::AsyncRead,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        // SAFETY: `inner` is never moved
+        let me = unsafe { self.get_unchecked_mut() };
+        if !me.prefix.is_empty() {
+            let len = me.prefix.len().min(buf.remaining());
+            buf.put_slice(&me.prefix[..len]);
+            me.prefix.advance(len);
+            return Poll::Ready(Ok(()));
+        }
+        unsafe { Pin::new_unchecked(&mut me.inner) }.poll_read(cx, buf)
+    }
+}
+
+impl<S> tokio::io::AsyncWrite for ProxyStream<S>
+where
+    S: tokio::io::AsyncWrite,
+{
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        // SAFETY: `inner` is never moved
+        unsafe { self.map_unchecked_mut(|me| &mut me.inner) }.poll_write(cx, buf)
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        // SAFETY: `inner` is never moved
+        unsafe { self.map_unchecked_mut(|me| &mut me.inner) }.poll_write_vectored(cx, bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.inner.is_write_vectored()
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> Poll<io::Result<()>> {
+        // SAFETY: `inner` is never moved
+        unsafe { self.map_unchecked_mut(|me| &mut me.inner) }.poll_flush(cx)
+    }
+
+    fn poll_shutdown(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        // SAFETY: `inner` is never moved
+        unsafe { self.map_unchecked_mut(|me| &mut me.inner) }.poll_shutdown(cx)
+    }
+}
+
+// ===== Parser =====
+
+type Addresses = Option<(SocketAddr, SocketAddr)>;
+
+/// Parse PROXY protocol header.
+///
+/// Returns the source and destination address, and the length of the header. Returns `None` if
+/// more bytes is required.
+fn parse_header(bytes: &[u8]) -> Result<Option<(Addresses, usize)>, ProxyError> {
+    if is_prefix(bytes, V2_SIGNATURE) {
+        if bytes.len() < V2_HEADER_LEN {
+            return Ok(None);
+        }
+        parse_v2(bytes)
+    } else if is_prefix(bytes, V1_PREFIX) {
+        if bytes.len() < V1_PREFIX.len() {
+            return Ok(None);
+        }
+        parse_v1(bytes)
+    } else {
+        Err(ProxyError::InvalidSignature)
+    }
+}
+
+fn is_prefix(bytes: &[u8], signature: &[u8]) -> bool {
+    let len = bytes.len().min(signature.len());
+    bytes[..len] == signature[..len]
+}
+
+fn parse_v1(bytes: &[u8]) -> Result<Option<(Addresses, usize)>, ProxyError> {
+    let Some(end) = bytes.windows(2).position(|w| w == b"\r\n") else {
+        return if bytes.len() < V1_MAX_LEN { Ok(None) } else { Err(ProxyError::Malformed) };
+    };
+    let len = end + 2;
+    if len > V1_MAX_LEN {
+        return Err(ProxyError::Malformed);
+    }
+
+    let line = str::from_utf8(&bytes[V1_PREFIX.len()..end]).map_err(|_| ProxyError::Malformed)?;
+    let mut fields = line.split(' ');
+
+    let is_ipv4 = match fields.next() {
+        Some("TCP4") => true,
+        Some("TCP6") => false,
+        // the rest of the line is ignored
+        Some("UNKNOWN") => return Ok(Some((None, len))),
+        _ => return Err(ProxyError::Malformed),
+    };
+
+    let mut next = || fields.next().ok_or(ProxyError::Malformed);
+    let source: IpAddr = next()?.parse().map_err(|_| ProxyError::Malformed)?;
+    let destination: IpAddr = next()?.parse().map_err(|_| ProxyError::Malformed)?;
+    let source_port: u16 = next()?.parse().map_err(|_| ProxyError::Malformed)?;
+    let destination_port: u16 = next()?.parse().map_err(|_| ProxyError::Malformed)?;
+
+    if fields.next().is_some() || source.is_ipv4() != is_ipv4 || destination.is_ipv4() != is_ipv4 {
+        return Err(ProxyError::Malformed);
+    }
+
+    let addrs = (
+        SocketAddr::new(source, source_port),
+        SocketAddr::new(destination, destination_port),
+    );
+    Ok(Some((Some(addrs), len)))
+}
+
+fn parse_v2(bytes: &[u8]) -> Result<Option<(Addresses, usize)>, ProxyError> {
+    let version = bytes[12] >> 4;
+    let command = bytes[12] & 0x0F;
+    let family = bytes[13] >> 4;
+    let len = V2_HEADER_LEN + u16::from_be_bytes([bytes[14], bytes[15]]) as usize;
+
+    if version != 2 {
+        return Err(ProxyError::UnsupportedVersion);
+    }
+    let Some(payload) = bytes.get(V2_HEADER_LEN..len) else {
+        return Ok(None);
+    };
+
+    let addrs = match (command, family) {
+        // LOCAL command, the connection is established by the proxy itself
+        (0x0, _) => None,
+        // AF_INET
+        (0x1, 0x1) => {
+            let Some(addrs) = payload.first_chunk::<12>() else {
+                return Err(ProxyError::Malformed);
+            };
+            let ip = |i: usize| IpAddr::V4(Ipv4Addr::from(<[u8; 4]>::try_from(&addrs[i..i + 4]).unwrap()));
+            let port = |i: usize| u16::from_be_bytes([addrs[i], addrs[i + 1]]);
+            Some((
+                SocketAddr::new(ip(0), port(8)),
+                SocketAddr::new(ip(4), port(10)),
+            ))
+        }
+        // AF_INET6
+        (0x1, 0x2) => {
+            let Some(addrs) = payload.first_chunk::<36>() else {
+                return Err(ProxyError::Malformed);
+            };
+            let ip = |i: usize| IpAddr::V6(Ipv6Addr::from(<[u8; 16]>::try_from(&addrs[i..i + 16]).unwrap()));
+            let port = |i: usize| u16::from_be_bytes([addrs[i], addrs[i + 1]]);
+            Some((
+                SocketAddr::new(ip(0), port(32)),
+                SocketAddr::new(ip(16), port(34)),
+            ))
+        }
+        // AF_UNSPEC and AF_UNIX, the address is not representable
+        (0x1, 0x0 | 0x3) => None,
+        _ => return Err(ProxyError::Malformed),
+    };
+
+    Ok(Some((addrs, len)))
+}
+
+// ===== Error =====
+
+#[derive(Debug)]
+enum ProxyError {
+    InvalidSignature,
+    UnsupportedVersion,
+    Malformed,
+    Timeout,
+    Eof,
+}
+
+impl std::error::Error for ProxyError {}
+
+impl std::fmt::Display for ProxyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::InvalidSignature => "invalid PROXY protocol signature",
+            Self::UnsupportedVersion => "unsupported PROXY protocol version",
+            Self::Malformed => "malformed PROXY protocol header",
+            Self::Timeout => "PROXY protocol header timeout",
+            Self::Eof => "stream closed before PROXY protocol header is complete",
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt, DuplexStream};
+
+    use super::*;
+    use crate::server::MemoryListener;
+    use crate::testing::{MockTimer, block_on};
+
+    type Accept = io::Result<(ProxyStream<DuplexStream>, ProxyAddr<()>)>;
+
+    fn poll_accept(listener: &mut ProxyListener<MemoryListener>) -> Poll<Accept> {
+        let mut cx = std::task::Context::from_waker(std::task::Waker::noop());
+        Pin::new(listener).poll_accept(&mut cx)
+    }
+
+    fn assert_aborted(result: Poll<Accept>) {
+        match result {
+            Poll::Ready(Err(err)) => assert_eq!(err.kind(), io::ErrorKind::ConnectionAborted),
+            Poll::Ready(Ok(_)) => panic!("stream should be rejected"),
+            Poll::Pending => panic!("stream should be rejected without waiting"),
+        }
+    }
+
+    #[test]
+    fn test_parse_v1() {
+        let header = b"PROXY TCP4 192.168.0.1 192.168.0.11 56324 443\r\nGET / HTTP/1.1\r\n";
+        let (addrs, len) = parse_header(header).unwrap().unwrap();
+        let (source, destination) = addrs.unwrap();
+        assert_eq!(source, "192.168.0.1:56324".parse().unwrap());
+        assert_eq!(destination, "192.168.0.11:443".parse().unwrap());
+        assert_eq!(&header[len..], b"GET / HTTP/1.1\r\n");
+
+        let header = b"PROXY TCP6 ::1 ::2 1 2\r\n";
+        let (addrs, len) = parse_header(header).unwrap().unwrap();
+        assert_eq!(addrs.unwrap().0, "[::1]:1".parse().unwrap());
+        assert_eq!(len, header.len());
+
+        let (addrs, _) = parse_header(b"PROXY UNKNOWN ffff::1 ffff::2 1 2\r\n").unwrap().unwrap();
+        assert!(addrs.is_none());
+
+        // incomplete
+        assert!(parse_header(b"").unwrap().is_none());
+        assert!(parse_header(b"PRO").unwrap().is_none());
+        assert!(parse_header(b"PROXY TCP4 192.168.0.1").unwrap().is_none());
+
+        // malformed
+        assert!(parse_header(b"GET / HTTP/1.1\r\n").is_err());
+        assert!(parse_header(b"PROXY TCP4 ::1 ::2 1 2\r\n").is_err());
+        assert!(parse_header(b"PROXY TCP4 192.168.0.1 192.168.0.11 56324\r\n").is_err());
+        assert!(parse_header(b"PROXY TCP4 192.168.0.1 192.168.0.11 56324 443 1\r\n").is_err());
+        assert!(parse_header(&[b'P', b'R', b'O', b'X', b'Y', b' '].repeat(20)).is_err());
+    }
+
+    #[test]
+    fn test_parse_v2() {
+        let mut header = V2_SIGNATURE.to_vec();
+        header.extend_from_slice(&[0x21, 0x11, 0, 12]);
+        header.extend_from_slice(&[127, 0, 0, 1, 10, 0, 0, 1, 0x1F, 0x90, 0x01, 0xBB]);
+        header.extend_from_slice(b"GET");
+
+        // incomplete
+        for len in 0..header.len() - 3 {
+            assert!(parse_header(&header[..len]).unwrap().is_none());
+        }
+
+        let (addrs, len) = parse_header(&header).unwrap().unwrap();
+        let (source, destination) = addrs.unwrap();
+        assert_eq!(source, "127.0.0.1:8080".parse().unwrap());
+        assert_eq!(destination, "10.0.0.1:443".parse().unwrap());
+        assert_eq!(&header[len..], b"GET");
+
+        // LOCAL command
+        let mut header = V2_SIGNATURE.to_vec();
+        header.extend_from_slice(&[0x20, 0x00, 0, 0]);
+        assert_eq!(parse_header(&header).unwrap().unwrap(), (None, 16));
+
+        // unsupported version
+        let mut header = V2_SIGNATURE.to_vec();
+        header.extend_from_slice(&[0x11, 0x11, 0, 0]);
+        assert!(parse_header(&header).is_err());
+
+        // address too short
+        let mut header = V2_SIGNATURE.to_vec();
+        header.extend_from_slice(&[0x21, 0x21, 0, 12]);
+        header.extend_from_slice(&[0; 12]);
+        assert!(parse_header(&header).is_err());
+    }
+
+    #[test]
+    fn test_proxy_listener() {
+        let timer = MockTimer::new();
+        let (listener, connector) = MemoryListener::new();
+        let mut listener = ProxyListener::new(listener).timer(timer.clone());
+
+        // bytes after the header are preserved
+        let mut client = connector.connect().unwrap();
+        let header = b"PROXY TCP4 192.168.0.1 192.168.0.11 56324 443\r\nGET";
+        block_on(client.write_all(header)).unwrap();
+        let Poll::Ready(Ok((mut stream, addr))) = poll_accept(&mut listener) else {
+            panic!("header should be accepted")
+        };
+        assert_eq!(addr.source(), Some(&"192.168.0.1:56324".parse().unwrap()));
+        assert_eq!(addr.destination(), Some(&"192.168.0.11:443".parse().unwrap()));
+        let mut rest = [0; 3];
+        block_on(stream.read_exact(&mut rest)).unwrap();
+        assert_eq!(&rest, b"GET");
+
+        // malformed
+        let mut client = connector.connect().unwrap();
+        block_on(client.write_all(b"GET / HTTP/1.1\r\n")).unwrap();
+        assert_aborted(poll_accept(&mut listener));
+
+        // eof before the header
+        let mut client = connector.connect().unwrap();
+        block_on(client.write_all(b"PROXY TCP4")).unwrap();
+        drop(client);
+        assert_aborted(poll_accept(&mut listener));
+
+        // header never arrives
+        let _client = connector.connect().unwrap();
+        assert!(poll_accept(&mut listener).is_pending());
+        timer.advance(DEFAULT_TIMEOUT);
+        assert_aborted(poll_accept(&mut listener));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_max_pending_zero() {
+        let (listener, _connector) = MemoryListener::new();
+        ProxyListener::new(listener).max_pending(0);
+    }
+}
@@ -0,0 +1,455 @@
+//! All in one API to run a http server.
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Poll, ready};
+use std::time::Duration;
+use tcio::io::{AsyncRead, AsyncWrite};
+
+use crate::{h1, h2};
+use crate::event::{LogObserver, Observer, SharedObserver};
+use crate::http::ConnectionInfo;
+use crate::rt::{Executor, Sleep, Timer, TokioExecutor, TokioTimer};
+use crate::service::HttpService;
+
+mod listener;
+mod shutdown;
+mod limit;
+mod auto;
+mod client;
+#[cfg(unix)]
+mod reuseport;
+
+pub use listener::{
+    Either, Listener, MapStream, MappedAddr, MemoryConnector, MemoryListener, Merge, ProxyAddr,
+    ProxyListener, ProxyStream,
+};
+#[cfg(unix)]
+pub use listener::{ListenFd, ListenFdError, listen_fds};
+pub use shutdown::{GracefulShutdown, Shutdown, Watched};
+pub use auto::{Auto, AutoConnection};
+pub use client::TestClient;
+#[cfg(unix)]
+pub use reuseport::{BoundReusePortServer, ReusePortServer, SocketConfig};
+
+use shutdown::Watch;
+use limit::ConnectionLimit;
+
+// ===== Server =====
+
+pub type Http1Server<S, L> = Server<S, L, Http1>;
+
+pub type Http2Server<S, L> = Server<S, L, Http2>;
+
+pub type AutoServer<S, L> = Server<S, L, Auto>;
+
+pub struct Server<S, L, D, E = TokioExecutor> {
+    service: S,
+    listener: L,
+    driver: D,
+    executor: E,
+    timer: Arc<dyn Timer + Send + Sync>,
+    observer: SharedObserver,
+    shutdown: Shutdown,
+    watch: Watch,
+    shutdown_timeout: Option<Duration>,
+    deadline: Option<Pin<Box<dyn Sleep>>>,
+    is_draining: bool,
+    limit: Option<ConnectionLimit>,
+    backoff: Option<Duration>,
+    backoff_sleep: Option<Pin<Box<dyn Sleep>>>,
+}
+
+impl<S, L, D: Default> Server<S, L, D> {
+    #[inline]
+    pub fn new(service: S, listener: L) -> Self {
+        Self::with_driver(service, listener, D::default())
+    }
+}
+
+impl<S, L, D> Server<S, L, D> {
+    /// Create new server with given [`Driver`].
+    pub fn with_driver(service: S, listener: L, driver: D) -> Self {
+        let shutdown = Shutdown::new();
+        Self {
+            service,
+            listener,
+            driver,
+            executor: TokioExecutor,
+            timer: Arc::new(TokioTimer),
+            observer: LogObserver::shared(),
+            watch: shutdown.watch(),
+            shutdown,
+            shutdown_timeout: None,
+            deadline: None,
+            is_draining: false,
+            limit: None,
+            backoff: None,
+            backoff_sleep: None,
+        }
+    }
+}
+
+impl<S, L, D, E> Server<S, L, D, E> {
+    /// Set the [`Executor`] used to spawn connection futures, default to [`TokioExecutor`].
+    ///
+    /// Use [`LocalExecutor`][crate::rt::LocalExecutor] to serve `!Send` service.
+    #[inline]
+    pub fn executor<E2>(self, executor: E2) -> Server<S, L, D, E2> {
+        Server {
+            service: self.service,
+            listener: self.listener,
+            driver: self.driver,
+            executor,
+            timer: self.timer,
+            observer: self.observer,
+            shutdown: self.shutdown,
+            watch: self.watch,
+            shutdown_timeout: self.shutdown_timeout,
+            deadline: self.deadline,
+            is_draining: self.is_draining,
+            limit: self.limit,
+            backoff: self.backoff,
+            backoff_sleep: self.backoff_sleep,
+        }
+    }
+
+    /// Set the [`Timer`] used for shutdown timeout and accept backoff, default to
+    /// [`TokioTimer`].
+    #[inline]
+    pub fn timer<T>(mut self, timer: T) -> Self
+    where
+        T: Timer + Send + Sync + 'static,
+    {
+        self.timer = Arc::new(timer);
+        self
+    }
+
+    /// Set the [`Observer`] of server and connection events, default to [`LogObserver`].
+    #[inline]
+    pub fn observer<O>(mut self, observer: O) -> Self
+    where
+        O: Observer + Send + Sync + 'static,
+    {
+        self.observer = Arc::new(observer);
+        self
+    }
+
+    /// Use given [`Shutdown`] handle to signal graceful shutdown.
+    ///
+    /// This allows multiple servers to be shutdown with the same handle.
+    #[inline]
+    pub fn with_shutdown(mut self, shutdown: Shutdown) -> Self {
+        self.watch = shutdown.watch();
+        self.shutdown = shutdown;
+        self
+    }
+
+    /// Set the maximum duration to wait for connections to close after graceful shutdown is
+    /// signaled.
+    ///
+    /// When the timeout expires, all remaining connections are closed immediately. By default,
+    /// server will wait until all connections are closed.
+    #[inline]
+    pub fn shutdown_timeout(mut self, timeout: Duration) -> Self {
+        self.shutdown_timeout = Some(timeout);
+        self
+    }
+
+    /// Set the maximum number of concurrent connections.
+    ///
+    /// When the limit is reached, server stops accepting new connection until an existing
+    /// connection is closed. By default, there is no limit.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max` is zero.
+    #[inline]
+    pub fn max_connections(mut self, max: usize) -> Self {
+        assert!(max != 0, "maximum connections must be greater than zero");
+        self.limit = Some(ConnectionLimit::new(max));
+        self
+    }
+
+    /// Returns the [`Shutdown`] handle to signal graceful shutdown.
+    #[inline]
+    pub fn shutdown_handle(&self) -> Shutdown {
+        self.shutdown.clone()
+    }
+}
+
+impl<S, L, D, E> std::fmt::Debug for Server<S, L, D, E>
+where
+    S: std::fmt::Debug,
+    L: std::fmt::Debug,
+    D: std::fmt::Debug,
+    E: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Server")
+            .field("service", &self.service)
+            .field("listener", &self.listener)
+            .field("driver", &self.driver)
+            .field("executor", &self.executor)
+            .field("observer", &self.observer)
+            .field("shutdown", &self.shutdown)
+            .field("shutdown_timeout", &self.shutdown_timeout)
+            .field("is_draining", &self.is_draining)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<S, L, D, E> Future for Server<S, L, D, E>
+where
+    S: Clone,
+    L: Listener<Stream: AsyncRead + AsyncWrite>,
+    D: Driver<S, L::Stream, Future: GracefulShutdown>,
+    E: Executor<Watched<D::Future>>,
+{
+    type Output = ();
+
+    #[inline]
+    fn poll(self: Pin<&mut Self>, cx: &mut std::task::Context) -> Poll<Self::Output> {
+        let me = unsafe { self.get_unchecked_mut() };
+        // SAFETY: `self` is pinned
+        let mut listener = unsafe { Pin::new_unchecked(&mut me.listener) };
+
+        if !me.is_draining {
+            loop {
+                if me.watch.poll_shutdown(cx).is_ready() {
+                    break;
+                }
+
+                if let Some(sleep) = &mut me.backoff_sleep {
+                    ready!(sleep.as_mut().poll(cx));
+                    me.backoff_sleep = None;
+                }
+
+                if let Some(limit) = &me.limit {
+                    ready!(limit.poll_ready(cx));
+                }
+
+                let (io, addr) = match ready!(listener.as_mut().poll_accept(cx)) {
+                    Ok(ok) => ok,
+                    Err(err) => {
+                        // errors like EMFILE or ENFILE will persist until other connections
+                        // are closed, retrying immediately will only spin
+                        if !limit::is_connection_error(&err) {
+                            let backoff = limit::next_backoff(me.backoff);
+                            me.backoff = Some(backoff);
+                            me.backoff_sleep = Some(me.timer.sleep(backoff));
+                        }
+                        me.observer.on_accept_error(&err);
+                        continue;
+                    }
+                };
+                me.backoff = None;
+
+                let permit = me.limit.as_ref().map(ConnectionLimit::acquire);
+                let info = L::connection_info(&io, &addr);
+                me.observer.on_accept(&info);
+                let connection = me.driver.call(me.service.clone(), io, info, me.observer.clone());
+                me.executor.execute(Watched::new(connection, me.shutdown.track(), permit));
+            }
+
+            me.is_draining = true;
+            me.deadline = me.shutdown_timeout.map(|timeout| me.timer.sleep(timeout));
+        }
+
+        if me.watch.poll_drain(cx).is_ready() {
+            return Poll::Ready(());
+        }
+
+        if let Some(deadline) = &mut me.deadline {
+            ready!(deadline.as_mut().poll(cx));
+            me.shutdown.abort();
+            return Poll::Ready(());
+        }
+
+        Poll::Pending
+    }
+}
+
+// ===== trait Driver =====
+
+pub trait Driver<S, IO> {
+    type Future;
+
+    /// Create the connection future, which should report its events to `observer`.
+    fn call(&self, service: S, io: IO, info: ConnectionInfo, observer: SharedObserver) -> Self::Future;
+}
+
+// ===== Http1 Driver =====
+
+#[derive(Debug, Clone, Default)]
+pub struct Http1 {
+    config: Arc<h1::Config>,
+}
+
+impl Http1 {
+    /// Create new [`Http1`] driver with given connection [`Config`][h1::Config].
+    #[inline]
+    pub fn new(config: h1::Config) -> Self {
+        Self {
+            config: Arc::new(config),
+        }
+    }
+}
+
+impl<S, IO> Driver<S, IO> for Http1
+where
+    S: HttpService,
+{
+    type Future = h1::Connection<S, IO>;
+
+    #[inline]
+    fn call(&self, service: S, io: IO, info: ConnectionInfo, observer: SharedObserver) -> Self::Future {
+        h1::Connection::with_config(service, io, info, self.config.clone()).with_observer(observer)
+    }
+}
+
+// ===== Http2 Driver =====
+
+#[derive(Debug, Clone, Default)]
+pub struct Http2;
+
+impl<S, IO> Driver<S, IO> for Http2
+where
+    S: HttpService,
+{
+    type Future = h2::Connection<S, IO>;
+
+    #[inline]
+    fn call(&self, service: S, io: IO, info: ConnectionInfo, observer: SharedObserver) -> Self::Future {
+        h2::Connection::with_info(service, io, info).with_observer(observer)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::VecDeque;
+    use std::io;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tcio::bytes::Bytes;
+    use tokio::io::DuplexStream;
+
+    use super::*;
+    use crate::body::{Full, Incoming};
+    use crate::http::{Request, Response, StatusCode};
+    use crate::service::from_fn;
+    use crate::testing::{MockTimer, block_on, settle};
+
+    async fn hello(_: Request<Incoming>) -> Response<Full<Bytes>> {
+        Response::from_parts(Default::default(), Full::new(Bytes::from_static(b"hello")))
+    }
+
+    /// Listener that returns the given errors, then pending forever.
+    struct Failing {
+        errors: VecDeque<io::Error>,
+        attempts: Arc<AtomicUsize>,
+    }
+
+    impl Failing {
+        fn new<const N: usize>(errors: [io::Error; N]) -> (Self, Arc<AtomicUsize>) {
+            let attempts = Arc::new(AtomicUsize::new(0));
+            let listener = Self {
+                errors: errors.into(),
+                attempts: attempts.clone(),
+            };
+            (listener, attempts)
+        }
+    }
+
+    impl Listener for Failing {
+        type Stream = DuplexStream;
+
+        type Addr = ();
+
+        fn poll_accept(
+            self: Pin<&mut Self>,
+            _: &mut std::task::Context,
+        ) -> Poll<io::Result<(Self::Stream, Self::Addr)>> {
+            let me = self.get_mut();
+            me.attempts.fetch_add(1, Ordering::Relaxed);
+            match me.errors.pop_front() {
+                Some(err) => Poll::Ready(Err(err)),
+                None => Poll::Pending,
+            }
+        }
+    }
+
+    #[test]
+    fn test_accept_backoff() {
+        block_on(async {
+            // EMFILE
+            let (listener, attempts) = Failing::new([
+                io::Error::from_raw_os_error(24),
+                io::Error::from_raw_os_error(24),
+            ]);
+            let timer = MockTimer::new();
+            tokio::spawn(Http1Server::new(from_fn(hello), listener).timer(timer.clone()));
+
+            settle().await;
+            assert_eq!(attempts.load(Ordering::Relaxed), 1);
+
+            timer.advance(Duration::from_millis(5));
+            settle().await;
+            assert_eq!(attempts.load(Ordering::Relaxed), 2);
+
+            // backoff is doubled on consecutive error
+            timer.advance(Duration::from_millis(5));
+            settle().await;
+            assert_eq!(attempts.load(Ordering::Relaxed), 2);
+
+            timer.advance(Duration::from_millis(5));
+            settle().await;
+            assert_eq!(attempts.load(Ordering::Relaxed), 3);
+        });
+    }
+
+    #[test]
+    fn test_accept_retry() {
+        block_on(async {
+            let (listener, attempts) = Failing::new([
+                io::ErrorKind::ConnectionAborted.into(),
+                io::ErrorKind::ConnectionAborted.into(),
+            ]);
+            let timer = MockTimer::new();
+            tokio::spawn(Http1Server::new(from_fn(hello), listener).timer(timer.clone()));
+
+            // connection error is retried immediately
+            settle().await;
+            assert_eq!(attempts.load(Ordering::Relaxed), 3);
+        });
+    }
+
+    #[test]
+    fn test_max_connections() {
+        block_on(async {
+            let (listener, connector) = MemoryListener::new();
+            tokio::spawn(Http1Server::new(from_fn(hello), listener).max_connections(1));
+
+            let mut client1 = TestClient::new(connector.connect().unwrap());
+            let response = client1.send(Request::<Full<Bytes>>::default()).await.unwrap();
+            assert_eq!(response.status(), &StatusCode::OK);
+
+            // second connection is not accepted while the first is open
+            let mut client2 = TestClient::new(connector.connect().unwrap());
+            let pending = tokio::spawn(async move {
+                client2.send(Request::<Full<Bytes>>::default()).await
+            });
+            settle().await;
+            assert!(!pending.is_finished());
+
+            drop(client1);
+            let response = pending.await.unwrap().unwrap();
+            assert_eq!(response.status(), &StatusCode::OK);
+        });
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_max_connections_zero() {
+        let (listener, _connector) = MemoryListener::new();
+        Http1Server::new(from_fn(hello), listener).max_connections(0);
+    }
+}
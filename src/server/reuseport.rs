@@ -0,0 +1,494 @@
+//! Multi acceptor server with `SO_REUSEPORT`.
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::task::{Poll, ready};
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpSocket, TcpStream};
+
+use crate::event::{LogObserver, Observer, SharedObserver};
+use crate::http::ConnectionInfo;
+use crate::log::debug;
+use crate::rt::LocalExecutor;
+use crate::server::{Driver, GracefulShutdown, Http1, Listener, Server, Shutdown};
+
+const DEFAULT_BACKLOG: u32 = 1024;
+
+// ===== SocketConfig =====
+
+/// TCP socket options.
+#[derive(Debug, Clone)]
+pub struct SocketConfig {
+    nodelay: bool,
+    keepalive: bool,
+    backlog: u32,
+    recv_buffer_size: Option<u32>,
+    send_buffer_size: Option<u32>,
+}
+
+impl Default for SocketConfig {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SocketConfig {
+    /// Create new [`SocketConfig`] with `TCP_NODELAY` enabled and backlog of 1024.
+    pub fn new() -> Self {
+        Self {
+            nodelay: true,
+            keepalive: false,
+            backlog: DEFAULT_BACKLOG,
+            recv_buffer_size: None,
+            send_buffer_size: None,
+        }
+    }
+
+    /// Set `TCP_NODELAY` of accepted streams.
+    #[inline]
+    pub fn nodelay(mut self, nodelay: bool) -> Self {
+        self.nodelay = nodelay;
+        self
+    }
+
+    /// Set `SO_KEEPALIVE`, which is inherited by accepted streams.
+    #[inline]
+    pub fn keepalive(mut self, keepalive: bool) -> Self {
+        self.keepalive = keepalive;
+        self
+    }
+
+    /// Set the maximum number of pending connections of each listener.
+    #[inline]
+    pub fn backlog(mut self, backlog: u32) -> Self {
+        self.backlog = backlog;
+        self
+    }
+
+    /// Set `SO_RCVBUF`, which is inherited by accepted streams.
+    #[inline]
+    pub fn recv_buffer_size(mut self, size: u32) -> Self {
+        self.recv_buffer_size = Some(size);
+        self
+    }
+
+    /// Set `SO_SNDBUF`, which is inherited by accepted streams.
+    #[inline]
+    pub fn send_buffer_size(mut self, size: u32) -> Self {
+        self.send_buffer_size = Some(size);
+        self
+    }
+
+    /// Create a socket bound to `addr` with `SO_REUSEPORT`.
+    fn bind(&self, addr: SocketAddr) -> io::Result<TcpSocket> {
+        let socket = match addr {
+            SocketAddr::V4(_) => TcpSocket::new_v4()?,
+            SocketAddr::V6(_) => TcpSocket::new_v6()?,
+        };
+        socket.set_reuseaddr(true)?;
+        socket.set_reuseport(true)?;
+        socket.set_keepalive(self.keepalive)?;
+        // buffer size should be set before `listen` to take effect on window scaling
+        if let Some(size) = self.recv_buffer_size {
+            socket.set_recv_buffer_size(size)?;
+        }
+        if let Some(size) = self.send_buffer_size {
+            socket.set_send_buffer_size(size)?;
+        }
+        socket.bind(addr)?;
+        Ok(socket)
+    }
+}
+
+// ===== ReusePortServer =====
+
+/// A server that accept connections with multiple worker threads.
+///
+/// Each worker binds its own `SO_REUSEPORT` listener to the same address, and runs its own accept
+/// loop on a current thread runtime, thus the kernel distributes incoming connections between
+/// workers. Connections are spawned in the worker that accept it, so the service future is not
+/// required to be `Send`.
+///
+/// Each worker use a clone of the service and the [`Driver`].
+///
+/// All workers share the same [`Shutdown`] handle.
+pub struct ReusePortServer<S, D = Http1> {
+    addr: SocketAddr,
+    service: S,
+    driver: D,
+    workers: usize,
+    socket: SocketConfig,
+    observer: SharedObserver,
+    shutdown: Shutdown,
+    shutdown_timeout: Option<Duration>,
+    max_connections: Option<usize>,
+}
+
+impl<S, D: Default> ReusePortServer<S, D> {
+    #[inline]
+    pub fn new(addr: SocketAddr, service: S) -> Self {
+        Self::with_driver(addr, service, D::default())
+    }
+}
+
+impl<S, D> ReusePortServer<S, D> {
+    /// Create new server with given [`Driver`].
+    ///
+    /// The number of workers default to [`available_parallelism`][std::thread::available_parallelism].
+    pub fn with_driver(addr: SocketAddr, service: S, driver: D) -> Self {
+        Self {
+            addr,
+            service,
+            driver,
+            workers: std::thread::available_parallelism().map_or(1, Into::into),
+            socket: SocketConfig::new(),
+            observer: LogObserver::shared(),
+            shutdown: Shutdown::new(),
+            shutdown_timeout: None,
+            max_connections: None,
+        }
+    }
+
+    /// Set the number of worker threads.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `workers` is zero.
+    #[inline]
+    pub fn workers(mut self, workers: usize) -> Self {
+        assert!(workers != 0, "number of workers must be greater than zero");
+        self.workers = workers;
+        self
+    }
+
+    /// Set the [`SocketConfig`] of each listener.
+    #[inline]
+    pub fn socket(mut self, socket: SocketConfig) -> Self {
+        self.socket = socket;
+        self
+    }
+
+    /// Set the [`Observer`] of server and connection events, default to [`LogObserver`].
+    #[inline]
+    pub fn observer<O>(mut self, observer: O) -> Self
+    where
+        O: Observer + Send + Sync + 'static,
+    {
+        self.observer = std::sync::Arc::new(observer);
+        self
+    }
+
+    /// Use given [`Shutdown`] handle to signal graceful shutdown.
+    #[inline]
+    pub fn with_shutdown(mut self, shutdown: Shutdown) -> Self {
+        self.shutdown = shutdown;
+        self
+    }
+
+    /// Set the maximum duration to wait for connections to close after graceful shutdown is
+    /// signaled.
+    ///
+    /// See [`Server::shutdown_timeout`].
+    #[inline]
+    pub fn shutdown_timeout(mut self, timeout: Duration) -> Self {
+        self.shutdown_timeout = Some(timeout);
+        self
+    }
+
+    /// Set the maximum number of concurrent connections of each worker.
+    ///
+    /// See [`Server::max_connections`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max` is zero.
+    #[inline]
+    pub fn max_connections(mut self, max: usize) -> Self {
+        assert!(max != 0, "maximum connections must be greater than zero");
+        self.max_connections = Some(max);
+        self
+    }
+
+    /// Returns the [`Shutdown`] handle to signal graceful shutdown.
+    #[inline]
+    pub fn shutdown_handle(&self) -> Shutdown {
+        self.shutdown.clone()
+    }
+
+    /// Bind the listener of each worker without starting the workers.
+    ///
+    /// The bound address is available before the server is run, such as when the port of `addr`
+    /// is 0.
+    pub fn bind(self) -> io::Result<BoundReusePortServer<S, D>> {
+        let first = self.socket.bind(self.addr)?;
+        // if the port is 0, the rest of the listeners should use the assigned port
+        let addr = first.local_addr()?;
+        let mut sockets = vec![first];
+        for _ in 1..self.workers {
+            sockets.push(self.socket.bind(addr)?);
+        }
+        Ok(BoundReusePortServer {
+            server: self,
+            addr,
+            sockets,
+        })
+    }
+}
+
+impl<S, D> ReusePortServer<S, D>
+where
+    S: Clone + Send + 'static,
+    D: Driver<S, TcpStream, Future: GracefulShutdown + 'static> + Clone + Send + 'static,
+{
+    /// Run the server, blocking the current thread until all workers are stopped.
+    ///
+    /// All listeners are bound before any worker is started, thus binding error is returned
+    /// immediately. If a worker fails, shutdown is signaled to the other workers, and the first
+    /// error is returned.
+    #[inline]
+    pub fn run(self) -> io::Result<()> {
+        self.bind()?.run()
+    }
+}
+
+impl<S, D> std::fmt::Debug for ReusePortServer<S, D>
+where
+    S: std::fmt::Debug,
+    D: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ReusePortServer")
+            .field("addr", &self.addr)
+            .field("service", &self.service)
+            .field("driver", &self.driver)
+            .field("workers", &self.workers)
+            .field("socket", &self.socket)
+            .field("shutdown", &self.shutdown)
+            .field("shutdown_timeout", &self.shutdown_timeout)
+            .finish_non_exhaustive()
+    }
+}
+
+// ===== BoundReusePortServer =====
+
+/// A [`ReusePortServer`] which listeners are bound, created with [`ReusePortServer::bind`].
+pub struct BoundReusePortServer<S, D = Http1> {
+    server: ReusePortServer<S, D>,
+    addr: SocketAddr,
+    sockets: Vec<TcpSocket>,
+}
+
+impl<S, D> BoundReusePortServer<S, D> {
+    /// Returns the address that the listeners are bound to.
+    #[inline]
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Returns the [`Shutdown`] handle to signal graceful shutdown.
+    #[inline]
+    pub fn shutdown_handle(&self) -> Shutdown {
+        self.server.shutdown.clone()
+    }
+}
+
+impl<S, D> BoundReusePortServer<S, D>
+where
+    S: Clone + Send + 'static,
+    D: Driver<S, TcpStream, Future: GracefulShutdown + 'static> + Clone + Send + 'static,
+{
+    /// Run the server, blocking the current thread until all workers are stopped.
+    ///
+    /// If a worker fails, shutdown is signaled to the other workers, and the first error is
+    /// returned.
+    pub fn run(self) -> io::Result<()> {
+        let Self { server, sockets, .. } = self;
+        let mut handles = Vec::with_capacity(sockets.len());
+        let mut result = Ok(());
+
+        for (i, socket) in sockets.into_iter().enumerate() {
+            let worker = Worker {
+                service: server.service.clone(),
+                driver: server.driver.clone(),
+                socket: server.socket.clone(),
+                observer: server.observer.clone(),
+                shutdown: server.shutdown.clone(),
+                shutdown_timeout: server.shutdown_timeout,
+                max_connections: server.max_connections,
+            };
+            let spawn = std::thread::Builder::new()
+                .name(format!("tsue-worker-{i}"))
+                .spawn(move || worker.run(socket));
+            match spawn {
+                Ok(handle) => handles.push(handle),
+                Err(err) => {
+                    server.shutdown.shutdown();
+                    result = Err(err);
+                    break;
+                }
+            }
+        }
+
+        for handle in handles {
+            match handle.join() {
+                Ok(Ok(())) => {}
+                Ok(Err(err)) => {
+                    if result.is_ok() {
+                        result = Err(err);
+                    }
+                }
+                Err(panic) => std::panic::resume_unwind(panic),
+            }
+        }
+
+        result
+    }
+}
+
+impl<S, D> std::fmt::Debug for BoundReusePortServer<S, D>
+where
+    S: std::fmt::Debug,
+    D: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BoundReusePortServer")
+            .field("server", &self.server)
+            .field("addr", &self.addr)
+            .finish_non_exhaustive()
+    }
+}
+
+// ===== Worker =====
+
+struct Worker<S, D> {
+    service: S,
+    driver: D,
+    socket: SocketConfig,
+    observer: SharedObserver,
+    shutdown: Shutdown,
+    shutdown_timeout: Option<Duration>,
+    max_connections: Option<usize>,
+}
+
+impl<S, D> Worker<S, D>
+where
+    S: Clone,
+    D: Driver<S, TcpStream, Future: GracefulShutdown + 'static>,
+{
+    fn run(self, socket: TcpSocket) -> io::Result<()> {
+        let Self {
+            service,
+            driver,
+            socket: config,
+            observer,
+            shutdown,
+            shutdown_timeout,
+            max_connections,
+        } = self;
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        let local = tokio::task::LocalSet::new();
+
+        let result = local.block_on(&runtime, async {
+            let listener = Acceptor {
+                listener: socket.listen(config.backlog)?,
+                nodelay: config.nodelay,
+            };
+
+            let mut server = Server::with_driver(service, listener, driver)
+                .executor(LocalExecutor)
+                .with_shutdown(shutdown.clone());
+            server.observer = observer;
+            server.shutdown_timeout = shutdown_timeout;
+            if let Some(max) = max_connections {
+                server = server.max_connections(max);
+            }
+
+            server.await;
+            Ok(())
+        });
+
+        // stop the other workers
+        if result.is_err() {
+            shutdown.shutdown();
+        }
+        result
+    }
+}
+
+// ===== Acceptor =====
+
+/// [`TcpListener`] that apply socket options to accepted streams.
+#[derive(Debug)]
+struct Acceptor {
+    listener: TcpListener,
+    nodelay: bool,
+}
+
+impl Listener for Acceptor {
+    type Stream = TcpStream;
+
+    type Addr = SocketAddr;
+
+    #[allow(unused_variables, reason = "used by logger")]
+    fn poll_accept(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context,
+    ) -> Poll<io::Result<(Self::Stream, Self::Addr)>> {
+        let (stream, addr) = ready!(self.listener.poll_accept(cx))?;
+        // the failure only affects this stream, which can still be served
+        if let Err(err) = stream.set_nodelay(self.nodelay) {
+            debug!("failed to set TCP_NODELAY, peer: {addr}: {err}");
+        }
+        Poll::Ready(Ok((stream, addr)))
+    }
+
+    #[inline]
+    fn connection_info(stream: &Self::Stream, addr: &Self::Addr) -> ConnectionInfo {
+        <TcpListener as Listener>::connection_info(stream, addr)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::{Read, Write};
+    use tcio::bytes::Bytes;
+
+    use super::*;
+    use crate::body::{Full, Incoming};
+    use crate::http::{Request, Response};
+    use crate::service::from_fn;
+
+    async fn hello(_: Request<Incoming>) -> Response<Full<Bytes>> {
+        Response::from_parts(Default::default(), Full::new(Bytes::from_static(b"hello")))
+    }
+
+    #[test]
+    fn test_reuseport_server() {
+        let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+        let server = ReusePortServer::<_, Http1>::new(addr, from_fn(hello))
+            .workers(2)
+            .bind()
+            .unwrap();
+        let addr = server.local_addr();
+        assert_ne!(addr.port(), 0);
+        let shutdown = server.shutdown_handle();
+        let handle = std::thread::spawn(move || server.run());
+
+        for _ in 0..2 {
+            let mut stream = std::net::TcpStream::connect(addr).unwrap();
+            stream
+                .write_all(b"GET / HTTP/1.1\r\nhost: localhost\r\nconnection: close\r\n\r\n")
+                .unwrap();
+            let mut response = Vec::new();
+            stream.read_to_end(&mut response).unwrap();
+            assert!(response.starts_with(b"HTTP/1.1 200 "));
+            assert!(response.ends_with(b"hello"));
+        }
+
+        shutdown.shutdown();
+        handle.join().unwrap().unwrap();
+    }
+}
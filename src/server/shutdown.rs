@@ -0,0 +1,393 @@
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{Poll, Waker};
+
+use crate::{h1, h2};
+use crate::server::limit::Permit;
+use crate::service::HttpService;
+
+// ===== Shutdown =====
+
+/// A handle to gracefully shutdown a [`Server`][super::Server].
+///
+/// When [`shutdown`][Shutdown::shutdown] is called, the server stops accepting new connection, and
+/// all connections are asked to finish their in-flight requests. The server resolves when all
+/// connections are closed or the shutdown timeout expires.
+///
+/// The handle can be cloned and shared by multiple servers.
+#[derive(Clone, Debug)]
+pub struct Shutdown {
+    shared: Arc<Shared>,
+}
+
+#[derive(Debug)]
+struct Shared {
+    state: Mutex<State>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    is_shutdown: bool,
+    is_aborted: bool,
+    connections: usize,
+    /// registered waker for each [`Watch`], indexed by its slot
+    wakers: Vec<Option<Waker>>,
+    /// unused slot in `wakers`
+    free: Vec<usize>,
+}
+
+impl Default for Shutdown {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Shutdown {
+    /// Creates new [`Shutdown`] handle.
+    pub fn new() -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                state: Mutex::new(State::default()),
+            }),
+        }
+    }
+
+    /// Signal graceful shutdown.
+    ///
+    /// Calling this method more than once have no effect.
+    pub fn shutdown(&self) {
+        let mut state = self.shared.lock();
+        if !state.is_shutdown {
+            state.is_shutdown = true;
+            state.wake_all();
+        }
+    }
+
+    /// Returns `true` if graceful shutdown is signaled.
+    pub fn is_shutdown(&self) -> bool {
+        self.shared.lock().is_shutdown
+    }
+
+    /// Returns the number of connections that still in progress.
+    pub fn connections(&self) -> usize {
+        self.shared.lock().connections
+    }
+
+    /// Force all remaining connections to close.
+    pub(crate) fn abort(&self) {
+        let mut state = self.shared.lock();
+        state.is_shutdown = true;
+        state.is_aborted = true;
+        state.wake_all();
+    }
+
+    /// Register a watcher.
+    pub(crate) fn watch(&self) -> Watch {
+        Watch::new(self.shared.clone(), false)
+    }
+
+    /// Register a watcher that is counted as connection.
+    pub(crate) fn track(&self) -> Watch {
+        Watch::new(self.shared.clone(), true)
+    }
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        // the state is always left consistent, thus poisoning can be ignored
+        self.state.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl State {
+    fn wake_all(&mut self) {
+        for waker in self.wakers.iter_mut().filter_map(Option::take) {
+            waker.wake();
+        }
+    }
+}
+
+// ===== Watch =====
+
+/// A registered shutdown watcher.
+#[derive(Debug)]
+pub(crate) struct Watch {
+    shared: Arc<Shared>,
+    slot: usize,
+    is_connection: bool,
+}
+
+impl Watch {
+    fn new(shared: Arc<Shared>, is_connection: bool) -> Self {
+        let mut state = shared.lock();
+        let slot = match state.free.pop() {
+            Some(slot) => slot,
+            None => {
+                state.wakers.push(None);
+                state.wakers.len() - 1
+            }
+        };
+        if is_connection {
+            state.connections += 1;
+        }
+        drop(state);
+        Self {
+            shared,
+            slot,
+            is_connection,
+        }
+    }
+
+    /// Returns `Ready` when graceful shutdown is signaled.
+    pub(crate) fn poll_shutdown(&self, cx: &mut std::task::Context) -> Poll<()> {
+        let mut state = self.shared.lock();
+        if state.is_shutdown {
+            return Poll::Ready(());
+        }
+        state.wakers[self.slot] = Some(cx.waker().clone());
+        Poll::Pending
+    }
+
+    /// Returns `Ready` when all connections is closed.
+    pub(crate) fn poll_drain(&self, cx: &mut std::task::Context) -> Poll<()> {
+        let mut state = self.shared.lock();
+        if state.connections == 0 {
+            return Poll::Ready(());
+        }
+        state.wakers[self.slot] = Some(cx.waker().clone());
+        Poll::Pending
+    }
+
+    /// Returns `Ready` when remaining connections should be closed immediately.
+    pub(crate) fn poll_abort(&self, cx: &mut std::task::Context) -> Poll<()> {
+        let mut state = self.shared.lock();
+        if state.is_aborted {
+            return Poll::Ready(());
+        }
+        state.wakers[self.slot] = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl Drop for Watch {
+    fn drop(&mut self) {
+        let mut state = self.shared.lock();
+        state.wakers[self.slot] = None;
+        state.free.push(self.slot);
+        if self.is_connection {
+            state.connections -= 1;
+            if state.connections == 0 && state.is_shutdown {
+                state.wake_all();
+            }
+        }
+    }
+}
+
+// ===== GracefulShutdown =====
+
+/// A connection that can be gracefully shutdown.
+pub trait GracefulShutdown: Future<Output = ()> {
+    /// Start graceful shutdown.
+    ///
+    /// Connection should stop receiving new request, and resolve after in-flight requests is
+    /// complete.
+    fn graceful_shutdown(self: Pin<&mut Self>);
+}
+
+impl<S, IO> GracefulShutdown for h1::Connection<S, IO>
+where
+    S: HttpService,
+    IO: tcio::io::AsyncRead + tcio::io::AsyncWrite,
+{
+    #[inline]
+    fn graceful_shutdown(self: Pin<&mut Self>) {
+        h1::Connection::graceful_shutdown(self);
+    }
+}
+
+impl<S, IO> GracefulShutdown for h2::Connection<S, IO>
+where
+    S: HttpService,
+    IO: tcio::io::AsyncRead + tcio::io::AsyncWrite,
+{
+    #[inline]
+    fn graceful_shutdown(self: Pin<&mut Self>) {
+        h2::Connection::graceful_shutdown(self);
+    }
+}
+
+// ===== Watched =====
+
+/// A connection future that watch for graceful shutdown signal.
+///
+/// This is the future spawned by [`Server`][super::Server] with its [`Executor`]. The connection
+/// limit permit, if any, is released when the connection is dropped.
+///
+/// [`Executor`]: crate::rt::Executor
+#[derive(Debug)]
+pub struct Watched<F> {
+    future: F,
+    watch: Watch,
+    is_signaled: bool,
+    _permit: Option<Permit>,
+}
+
+impl<F> Watched<F> {
+    pub(crate) fn new(future: F, watch: Watch, permit: Option<Permit>) -> Self {
+        Self {
+            future,
+            watch,
+            is_signaled: false,
+            _permit: permit,
+        }
+    }
+}
+
+impl<F> Future for Watched<F>
+where
+    F: GracefulShutdown,
+{
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut std::task::Context) -> Poll<Self::Output> {
+        // SAFETY: `future` is never moved
+        let me = unsafe { self.get_unchecked_mut() };
+        let mut future = unsafe { Pin::new_unchecked(&mut me.future) };
+
+        if !me.is_signaled && me.watch.poll_shutdown(cx).is_ready() {
+            me.is_signaled = true;
+            future.as_mut().graceful_shutdown();
+        }
+
+        if me.is_signaled && me.watch.poll_abort(cx).is_ready() {
+            return Poll::Ready(());
+        }
+
+        future.poll(cx)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::task::Context;
+    use std::time::Duration;
+    use tcio::bytes::Bytes;
+    use tokio::sync::Notify;
+
+    use super::*;
+    use crate::body::{Full, Incoming};
+    use crate::headers::{HeaderValue, standard};
+    use crate::http::{Request, Response, StatusCode};
+    use crate::server::{Http1Server, MemoryListener, TestClient};
+    use crate::service::from_fn;
+    use crate::testing::{MockTimer, block_on, settle};
+
+    #[test]
+    fn test_watch() {
+        let mut cx = Context::from_waker(Waker::noop());
+        let shutdown = Shutdown::new();
+        let watch = shutdown.watch();
+        let connection = shutdown.track();
+        assert_eq!(shutdown.connections(), 1);
+
+        assert!(watch.poll_shutdown(&mut cx).is_pending());
+        shutdown.shutdown();
+        assert!(shutdown.is_shutdown());
+        assert!(watch.poll_shutdown(&mut cx).is_ready());
+
+        assert!(watch.poll_drain(&mut cx).is_pending());
+        drop(connection);
+        assert!(watch.poll_drain(&mut cx).is_ready());
+        assert!(watch.poll_abort(&mut cx).is_pending());
+    }
+
+    #[test]
+    fn test_shutdown_after_response() {
+        block_on(async {
+            let started = Arc::new(Notify::new());
+            let release = Arc::new(Notify::new());
+            let service = {
+                let (started, release) = (started.clone(), release.clone());
+                from_fn(move |_: Request<Incoming>| {
+                    let (started, release) = (started.clone(), release.clone());
+                    async move {
+                        started.notify_one();
+                        release.notified().await;
+                        Response::from_parts(Default::default(), Full::new(Bytes::from_static(b"done")))
+                    }
+                })
+            };
+            let (listener, connector) = MemoryListener::new();
+            let server = Http1Server::new(service, listener);
+            let shutdown = server.shutdown_handle();
+            let server = tokio::spawn(server);
+
+            let mut client = TestClient::new(connector.connect().unwrap());
+            let in_flight = tokio::spawn(async move {
+                let response = client.send(Request::<Full<Bytes>>::default()).await;
+                (client, response)
+            });
+            started.notified().await;
+
+            shutdown.shutdown();
+            settle().await;
+            assert_eq!(shutdown.connections(), 1);
+            assert!(!server.is_finished());
+
+            // in-flight request is still responded
+            release.notify_one();
+            let (mut client, response) = in_flight.await.unwrap();
+            let response = response.unwrap();
+            assert_eq!(response.status(), &StatusCode::OK);
+            let connection = response.headers().get(standard::CONNECTION);
+            assert_eq!(connection.map(HeaderValue::as_str), Some("close"));
+
+            // keep-alive connection is closed after the response
+            assert!(client.send(Request::<Full<Bytes>>::default()).await.is_err());
+            server.await.unwrap();
+            assert_eq!(shutdown.connections(), 0);
+        });
+    }
+
+    #[test]
+    fn test_shutdown_timeout() {
+        block_on(async {
+            let started = Arc::new(Notify::new());
+            let service = {
+                let started = started.clone();
+                from_fn(move |_: Request<Incoming>| {
+                    let started = started.clone();
+                    async move {
+                        started.notify_one();
+                        std::future::pending::<Response<Full<Bytes>>>().await
+                    }
+                })
+            };
+            let timer = MockTimer::new();
+            let (listener, connector) = MemoryListener::new();
+            let server = Http1Server::new(service, listener)
+                .timer(timer.clone())
+                .shutdown_timeout(Duration::from_secs(5));
+            let shutdown = server.shutdown_handle();
+            let server = tokio::spawn(server);
+
+            let mut client = TestClient::new(connector.connect().unwrap());
+            let in_flight = tokio::spawn(async move {
+                client.send(Request::<Full<Bytes>>::default()).await
+            });
+            started.notified().await;
+
+            shutdown.shutdown();
+            settle().await;
+            timer.advance(Duration::from_secs(4));
+            settle().await;
+            assert!(!server.is_finished());
+
+            // remaining connection is closed when the deadline expires
+            timer.advance(Duration::from_secs(1));
+            server.await.unwrap();
+            assert!(in_flight.await.unwrap().is_err());
+        });
+    }
+}
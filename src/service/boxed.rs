@@ -0,0 +1,158 @@
+//! Type erased service.
+use std::pin::Pin;
+use std::task::Poll;
+
+use super::Service;
+
+/// Type erased future returned from [`BoxService`] and [`BoxCloneService`].
+pub type BoxFuture<T, E> = Pin<Box<dyn Future<Output = Result<T, E>> + Send>>;
+
+// ===== BoxService =====
+
+/// A type erased [`Service`], created with [`ServiceExt::boxed`][super::ServiceExt::boxed].
+pub struct BoxService<Request, T, E> {
+    inner: Box<dyn Service<Request, Response = T, Error = E, Future = BoxFuture<T, E>> + Send>,
+}
+
+impl<Request, T, E> BoxService<Request, T, E> {
+    /// Create new [`BoxService`].
+    pub fn new<S>(service: S) -> Self
+    where
+        S: Service<Request, Response = T, Error = E> + Send + 'static,
+        S::Future: Send + 'static,
+    {
+        Self {
+            inner: Box::new(Boxed(service)),
+        }
+    }
+}
+
+impl<Request, T, E> Service<Request> for BoxService<Request, T, E> {
+    type Response = T;
+
+    type Error = E;
+
+    type Future = BoxFuture<T, E>;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut std::task::Context) -> Poll<()> {
+        self.inner.poll_ready(cx)
+    }
+
+    #[inline]
+    fn call(&self, request: Request) -> Self::Future {
+        self.inner.call(request)
+    }
+}
+
+impl<Request, T, E> std::fmt::Debug for BoxService<Request, T, E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BoxService").finish_non_exhaustive()
+    }
+}
+
+// ===== BoxCloneService =====
+
+/// A type erased [`Service`] that implements [`Clone`], created with
+/// [`ServiceExt::boxed_clone`][super::ServiceExt::boxed_clone].
+pub struct BoxCloneService<Request, T, E> {
+    inner: Box<dyn CloneService<Request, Response = T, Error = E, Future = BoxFuture<T, E>> + Send>,
+}
+
+impl<Request, T, E> BoxCloneService<Request, T, E> {
+    /// Create new [`BoxCloneService`].
+    pub fn new<S>(service: S) -> Self
+    where
+        S: Service<Request, Response = T, Error = E> + Clone + Send + 'static,
+        S::Future: Send + 'static,
+    {
+        Self {
+            inner: Box::new(Boxed(service)),
+        }
+    }
+}
+
+impl<Request, T, E> Service<Request> for BoxCloneService<Request, T, E> {
+    type Response = T;
+
+    type Error = E;
+
+    type Future = BoxFuture<T, E>;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut std::task::Context) -> Poll<()> {
+        self.inner.poll_ready(cx)
+    }
+
+    #[inline]
+    fn call(&self, request: Request) -> Self::Future {
+        self.inner.call(request)
+    }
+}
+
+impl<Request, T, E> Clone for BoxCloneService<Request, T, E> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone_box(),
+        }
+    }
+}
+
+impl<Request, T, E> std::fmt::Debug for BoxCloneService<Request, T, E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BoxCloneService").finish_non_exhaustive()
+    }
+}
+
+trait CloneService<Request>: Service<Request> {
+    fn clone_box(
+        &self,
+    ) -> Box<
+        dyn CloneService<Request, Response = Self::Response, Error = Self::Error, Future = Self::Future>
+            + Send,
+    >;
+}
+
+impl<S, Request> CloneService<Request> for Boxed<S>
+where
+    S: Service<Request> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    fn clone_box(
+        &self,
+    ) -> Box<
+        dyn CloneService<Request, Response = Self::Response, Error = Self::Error, Future = Self::Future>
+            + Send,
+    > {
+        Box::new(Boxed(self.0.clone()))
+    }
+}
+
+// ===== Boxed =====
+
+/// Box the future of a service.
+#[derive(Clone)]
+pub(crate) struct Boxed<S>(pub(crate) S);
+
+impl<S, Request> Service<Request> for Boxed<S>
+where
+    S: Service<Request>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+
+    type Error = S::Error;
+
+    type Future = BoxFuture<S::Response, S::Error>;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut std::task::Context) -> Poll<()> {
+        self.0.poll_ready(cx)
+    }
+
+    #[inline]
+    fn call(&self, request: Request) -> Self::Future {
+        Box::pin(self.0.call(request))
+    }
+}
@@ -0,0 +1,436 @@
+//! Panic recovery middleware.
+use std::any::Any;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::Poll;
+
+use super::{Layer, Service};
+use crate::body::Body;
+use crate::http::{Response, StatusCode};
+use crate::log::error;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+type Hook = Arc<dyn Fn(&(dyn Any + Send)) + Send + Sync>;
+
+// ===== CatchPanic =====
+
+/// Catch panics of the inner service.
+///
+/// Panics from [`Service::call`], polling its future, and polling the response body are caught.
+/// If the panic occurs before the response is returned, the response is replaced with empty
+/// `500 Internal Server Error`. If the panic occurs while polling the response body, the
+/// response head is already written, thus the body returns an error, which aborts the stream.
+///
+/// The panic payload is reported to the [`on_panic`][CatchPanic::on_panic] hook, which log the
+/// panic message by default.
+///
+/// Only unwinding panics can be caught, panics with `panic = "abort"` still abort the process.
+///
+/// # Examples
+///
+/// ```
+/// use tsue::body::{Full, Incoming};
+/// use tsue::bytes::Bytes;
+/// use tsue::http::{Request, Response};
+/// use tsue::service::{CatchPanic, from_fn};
+///
+/// async fn handler(_: Request<Incoming>) -> Response<Full<Bytes>> {
+///     panic!("oops")
+/// }
+///
+/// let service = CatchPanic::new(from_fn(handler)).on_panic(|payload| {
+///     eprintln!("handler panicked: {:?}", payload.downcast_ref::<&str>());
+/// });
+/// ```
+pub struct CatchPanic<S> {
+    inner: S,
+    hook: Hook,
+}
+
+impl<S> CatchPanic<S> {
+    /// Wrap `inner` service.
+    #[inline]
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner,
+            hook: Arc::new(log_panic),
+        }
+    }
+
+    /// Set the function called with the panic payload.
+    #[inline]
+    pub fn on_panic<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&(dyn Any + Send)) + Send + Sync + 'static,
+    {
+        self.hook = Arc::new(hook);
+        self
+    }
+
+    /// Returns the inner service.
+    #[inline]
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S, Req, B> Service<Req> for CatchPanic<S>
+where
+    S: Service<Req, Response = Response<B>>,
+    B: Body,
+    B::Error: Into<BoxError>,
+{
+    type Response = Response<CatchPanicBody<B>>;
+
+    type Error = S::Error;
+
+    type Future = CatchPanicFuture<S::Future>;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut std::task::Context) -> Poll<()> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&self, request: Req) -> Self::Future {
+        let future = match panic::catch_unwind(AssertUnwindSafe(|| self.inner.call(request))) {
+            Ok(future) => Some(future),
+            Err(payload) => {
+                (self.hook)(&*payload);
+                None
+            }
+        };
+        CatchPanicFuture {
+            future,
+            hook: Some(self.hook.clone()),
+        }
+    }
+}
+
+impl<S: Clone> Clone for CatchPanic<S> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            hook: self.hook.clone(),
+        }
+    }
+}
+
+impl<S: std::fmt::Debug> std::fmt::Debug for CatchPanic<S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CatchPanic")
+            .field("inner", &self.inner)
+            .finish_non_exhaustive()
+    }
+}
+
+// ===== CatchPanicLayer =====
+
+/// [`Layer`] that wraps service with [`CatchPanic`].
+#[derive(Clone)]
+pub struct CatchPanicLayer {
+    hook: Hook,
+}
+
+impl Default for CatchPanicLayer {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CatchPanicLayer {
+    /// Create new [`CatchPanicLayer`].
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            hook: Arc::new(log_panic),
+        }
+    }
+
+    /// Set the function called with the panic payload.
+    #[inline]
+    pub fn on_panic<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&(dyn Any + Send)) + Send + Sync + 'static,
+    {
+        self.hook = Arc::new(hook);
+        self
+    }
+}
+
+impl<S> Layer<S> for CatchPanicLayer {
+    type Service = CatchPanic<S>;
+
+    #[inline]
+    fn layer(&self, inner: S) -> Self::Service {
+        CatchPanic {
+            inner,
+            hook: self.hook.clone(),
+        }
+    }
+}
+
+impl std::fmt::Debug for CatchPanicLayer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CatchPanicLayer").finish_non_exhaustive()
+    }
+}
+
+// ===== CatchPanicFuture =====
+
+/// Future returned from [`CatchPanic`] service.
+pub struct CatchPanicFuture<Fut> {
+    /// `None` if `Service::call` panicked
+    future: Option<Fut>,
+    /// `None` if the future is completed
+    hook: Option<Hook>,
+}
+
+impl<Fut, B, E> Future for CatchPanicFuture<Fut>
+where
+    Fut: Future<Output = Result<Response<B>, E>>,
+{
+    type Output = Result<Response<CatchPanicBody<B>>, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut std::task::Context) -> Poll<Self::Output> {
+        // SAFETY: `future` is never moved, it is only dropped in place
+        let me = unsafe { self.get_unchecked_mut() };
+        let mut future = unsafe { Pin::new_unchecked(&mut me.future) };
+
+        if let Some(inner) = future.as_mut().as_pin_mut() {
+            let result = match panic::catch_unwind(AssertUnwindSafe(|| inner.poll(cx))) {
+                Ok(Poll::Ready(result)) => result,
+                Ok(Poll::Pending) => return Poll::Pending,
+                Err(payload) => {
+                    future.set(None);
+                    let hook = me.hook.take().expect("poll after complete");
+                    hook(&*payload);
+                    return Poll::Ready(Ok(internal_error()));
+                }
+            };
+            let hook = me.hook.take().expect("poll after complete");
+            return Poll::Ready(result.map(|response| {
+                let (parts, body) = response.into_parts();
+                Response::from_parts(
+                    parts,
+                    CatchPanicBody {
+                        inner: Some(body),
+                        hook: Some(hook),
+                    },
+                )
+            }));
+        }
+
+        me.hook.take().expect("poll after complete");
+        Poll::Ready(Ok(internal_error()))
+    }
+}
+
+impl<Fut> std::fmt::Debug for CatchPanicFuture<Fut> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CatchPanicFuture").finish_non_exhaustive()
+    }
+}
+
+fn internal_error<B>() -> Response<CatchPanicBody<B>> {
+    let body = CatchPanicBody {
+        inner: None,
+        hook: None,
+    };
+    let mut response = Response::from_parts(Default::default(), body);
+    *response.status_mut() = StatusCode::INTERNAL_SERVER_ERROR;
+    response
+}
+
+// ===== CatchPanicBody =====
+
+/// Response body of [`CatchPanic`] service.
+///
+/// If polling the inner body panics, an error is returned and the body ends.
+pub struct CatchPanicBody<B> {
+    /// `None` if the response is replaced, or the inner body panicked
+    inner: Option<B>,
+    hook: Option<Hook>,
+}
+
+impl<B> Body for CatchPanicBody<B>
+where
+    B: Body,
+    B::Error: Into<BoxError>,
+{
+    type Data = B::Data;
+
+    type Error = BoxError;
+
+    fn poll_data(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        // SAFETY: `inner` is never moved, it is only dropped in place
+        let me = unsafe { self.get_unchecked_mut() };
+        let mut body = unsafe { Pin::new_unchecked(&mut me.inner) };
+
+        let Some(inner) = body.as_mut().as_pin_mut() else {
+            return Poll::Ready(None);
+        };
+        match panic::catch_unwind(AssertUnwindSafe(|| inner.poll_data(cx))) {
+            Ok(poll) => poll.map(|option| option.map(|result| result.map_err(Into::into))),
+            Err(payload) => {
+                body.set(None);
+                if let Some(hook) = &me.hook {
+                    hook(&*payload);
+                }
+                Poll::Ready(Some(Err(Panicked.into())))
+            }
+        }
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.as_ref().is_none_or(B::is_end_stream)
+    }
+
+    fn size_hint(&self) -> (u64, Option<u64>) {
+        match &self.inner {
+            Some(inner) => inner.size_hint(),
+            None => (0, Some(0)),
+        }
+    }
+}
+
+impl<B: std::fmt::Debug> std::fmt::Debug for CatchPanicBody<B> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CatchPanicBody")
+            .field("inner", &self.inner)
+            .finish_non_exhaustive()
+    }
+}
+
+// ===== Panicked =====
+
+/// Error returned from [`CatchPanicBody`] when the inner body panicked.
+#[derive(Debug)]
+struct Panicked;
+
+impl std::error::Error for Panicked { }
+
+impl std::fmt::Display for Panicked {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("response body panicked")
+    }
+}
+
+// ===== Hook =====
+
+#[allow(unused_variables, reason = "used by logger")]
+fn log_panic(payload: &(dyn Any + Send)) {
+    let message = if let Some(message) = payload.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.as_str()
+    } else {
+        "Box<dyn Any>"
+    };
+    error!("service panicked: {message}");
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tcio::bytes::Bytes;
+
+    use super::*;
+    use crate::body::Full;
+    use crate::service::from_fn;
+    use crate::testing::block_on;
+
+    #[test]
+    fn test_catch_panic() {
+        let count = Arc::new(AtomicUsize::new(0));
+        let hook = {
+            let count = count.clone();
+            move |payload: &(dyn Any + Send)| {
+                assert_eq!(payload.downcast_ref::<&str>(), Some(&"oops"));
+                count.fetch_add(1, Ordering::Relaxed);
+            }
+        };
+
+        let service = CatchPanic::new(from_fn(|panics: bool| async move {
+            if panics {
+                panic!("oops");
+            }
+            Response::from_parts(Default::default(), Full::new(Bytes::from_static(b"ok")))
+        }))
+        .on_panic(hook.clone());
+
+        let response = block_on(service.call(false)).unwrap();
+        assert_eq!(response.status(), &StatusCode::OK);
+        assert_eq!(count.load(Ordering::Relaxed), 0);
+
+        let response = block_on(service.call(true)).unwrap();
+        assert_eq!(response.status(), &StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(response.body().is_end_stream());
+        assert_eq!(count.load(Ordering::Relaxed), 1);
+
+        // panic in `Service::call`
+        let service = CatchPanic::new(PanicOnCall).on_panic(hook);
+        let response = block_on(service.call(())).unwrap();
+        assert_eq!(response.status(), &StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(count.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn test_catch_panic_body() {
+        let service = CatchPanic::new(from_fn(|_: ()| async {
+            Response::from_parts(Default::default(), PanicBody)
+        }))
+        .on_panic(|_| {});
+
+        let response = block_on(service.call(())).unwrap();
+        assert_eq!(response.status(), &StatusCode::OK);
+
+        let mut body = std::pin::pin!(response.into_body());
+        let result = block_on(std::future::poll_fn(|cx| body.as_mut().poll_data(cx)));
+        assert!(matches!(result, Some(Err(_))));
+        assert!(body.is_end_stream());
+    }
+
+    struct PanicOnCall;
+
+    impl Service<()> for PanicOnCall {
+        type Response = Response<Full<Bytes>>;
+
+        type Error = std::convert::Infallible;
+
+        type Future = std::future::Ready<Result<Self::Response, Self::Error>>;
+
+        fn call(&self, _: ()) -> Self::Future {
+            panic!("oops")
+        }
+    }
+
+    struct PanicBody;
+
+    impl Body for PanicBody {
+        type Data = Bytes;
+
+        type Error = std::convert::Infallible;
+
+        fn poll_data(
+            self: Pin<&mut Self>,
+            _: &mut std::task::Context,
+        ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+            panic!("oops")
+        }
+
+        fn is_end_stream(&self) -> bool {
+            false
+        }
+
+        fn size_hint(&self) -> (u64, Option<u64>) {
+            (0, None)
+        }
+    }
+}
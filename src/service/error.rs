@@ -0,0 +1,234 @@
+//! Service error rendering.
+use std::error::Error;
+use std::sync::Arc;
+use tcio::bytes::Bytes;
+
+use crate::headers::{HeaderValue, standard};
+use crate::http::{Response, StatusCode};
+
+/// Shared [`ErrorHandler`].
+pub type SharedErrorHandler = Arc<dyn ErrorHandler + Send + Sync>;
+
+// ===== ErrorHandler =====
+
+/// Render a service error into a response.
+///
+/// When the service future returns an error, the connection writes the rendered response instead
+/// of closing the connection. The error is also reported to
+/// [`Observer::on_service_error`][crate::event::Observer::on_service_error].
+///
+/// This is implemented for functions with the same signature as [`ErrorHandler::render`].
+pub trait ErrorHandler {
+    /// Render the `error` into a response.
+    fn render(&self, error: &(dyn Error + 'static)) -> Response<Bytes>;
+}
+
+impl<F> ErrorHandler for F
+where
+    F: Fn(&(dyn Error + 'static)) -> Response<Bytes>,
+{
+    #[inline]
+    fn render(&self, error: &(dyn Error + 'static)) -> Response<Bytes> {
+        self(error)
+    }
+}
+
+impl std::fmt::Debug for dyn ErrorHandler + Send + Sync {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ErrorHandler")
+    }
+}
+
+// ===== InternalError =====
+
+/// [`ErrorHandler`] that responds with empty `500 Internal Server Error`.
+///
+/// This is the default error handler.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct InternalError;
+
+impl ErrorHandler for InternalError {
+    fn render(&self, _: &(dyn Error + 'static)) -> Response<Bytes> {
+        let mut response = Response::<Bytes>::default();
+        *response.status_mut() = StatusCode::INTERNAL_SERVER_ERROR;
+        response
+    }
+}
+
+impl InternalError {
+    pub(crate) fn shared() -> SharedErrorHandler {
+        Arc::new(InternalError)
+    }
+}
+
+// ===== ProblemDetails =====
+
+/// [`ErrorHandler`] that responds with `500 Internal Server Error` in [Problem Details] JSON
+/// format.
+///
+/// By default, the error message is not included because it may expose internal details. Use
+/// [`with_detail`][ProblemDetails::with_detail] to include it as the `detail` member.
+///
+/// # Examples
+///
+/// ```
+/// use tsue::service::{ErrorHandler, ProblemDetails};
+///
+/// let error = std::io::Error::other("database unavailable");
+/// let response = ProblemDetails::new().with_detail(true).render(&error);
+///
+/// assert_eq!(
+///     &response.body()[..],
+///     br#"{"type":"about:blank","title":"Internal Server Error","status":500,"detail":"database unavailable"}"#,
+/// );
+/// ```
+///
+/// [Problem Details]: <https://www.rfc-editor.org/rfc/rfc9457.html>
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ProblemDetails {
+    detail: bool,
+}
+
+impl ProblemDetails {
+    /// Create new [`ProblemDetails`] renderer.
+    #[inline]
+    pub const fn new() -> Self {
+        Self { detail: false }
+    }
+
+    /// Include the error message as the `detail` member.
+    #[inline]
+    pub const fn with_detail(mut self, detail: bool) -> Self {
+        self.detail = detail;
+        self
+    }
+}
+
+impl ErrorHandler for ProblemDetails {
+    fn render(&self, error: &(dyn Error + 'static)) -> Response<Bytes> {
+        let status = StatusCode::INTERNAL_SERVER_ERROR;
+
+        let mut json = String::from(r#"{"type":"about:blank","title":"#);
+        write_json_str(&mut json, status.reason());
+        json.push_str(r#","status":"#);
+        json.push_str(status.code_str());
+        if self.detail {
+            json.push_str(r#","detail":"#);
+            write_json_str(&mut json, &error.to_string());
+        }
+        json.push('}');
+
+        let mut response = Response::from_parts(Default::default(), Bytes::from(json));
+        *response.status_mut() = status;
+        let content_type = HeaderValue::from_static(b"application/problem+json");
+        response.headers_mut().insert(standard::CONTENT_TYPE, content_type);
+        response
+    }
+}
+
+/// Write `value` as JSON string, including the quotes.
+fn write_json_str(buf: &mut String, value: &str) {
+    use std::fmt::Write;
+
+    buf.push('"');
+    for ch in value.chars() {
+        match ch {
+            '"' => buf.push_str("\\\""),
+            '\\' => buf.push_str("\\\\"),
+            '\n' => buf.push_str("\\n"),
+            '\r' => buf.push_str("\\r"),
+            '\t' => buf.push_str("\\t"),
+            ch if ch.is_control() => {
+                let _ = write!(buf, "\\u{:04x}", ch as u32);
+            }
+            ch => buf.push(ch),
+        }
+    }
+    buf.push('"');
+}
+
+#[cfg(test)]
+mod test {
+    use std::future::{Ready, ready};
+    use std::io;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::body::{Full, Incoming};
+    use crate::h1;
+    use crate::http::Request;
+    use crate::server::{Http1, MemoryListener, Server, TestClient};
+    use crate::service::Service;
+    use crate::testing::{Recorder, block_on, settle};
+
+    /// Service that fails the first request.
+    #[derive(Clone, Default)]
+    struct Flaky {
+        calls: Arc<AtomicUsize>,
+    }
+
+    impl Service<Request<Incoming>> for Flaky {
+        type Response = Response<Full<Bytes>>;
+
+        type Error = io::Error;
+
+        type Future = Ready<Result<Self::Response, Self::Error>>;
+
+        fn call(&self, _: Request<Incoming>) -> Self::Future {
+            ready(match self.calls.fetch_add(1, Ordering::Relaxed) {
+                0 => Err(io::Error::other("failed")),
+                _ => Ok(Response::from_parts(Default::default(), Full::new(Bytes::from_static(b"hello")))),
+            })
+        }
+    }
+
+    #[test]
+    fn test_problem_details() {
+        let error = std::io::Error::other("say \"hi\"\n\u{1}");
+
+        let response = ProblemDetails::new().render(&error);
+        assert_eq!(response.status(), &StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(
+            response.headers().get(standard::CONTENT_TYPE).unwrap(),
+            "application/problem+json"
+        );
+        assert_eq!(
+            &response.body()[..],
+            br#"{"type":"about:blank","title":"Internal Server Error","status":500}"#
+        );
+
+        let response = ProblemDetails::new().with_detail(true).render(&error);
+        assert_eq!(
+            &response.body()[..],
+            br#"{"type":"about:blank","title":"Internal Server Error","status":500,"detail":"say \"hi\"\n\u0001"}"#
+        );
+    }
+
+    #[test]
+    fn test_error_response() {
+        block_on(async {
+            let recorder = Recorder::default();
+            let config = h1::Config::new().error_handler(ProblemDetails::new());
+            let (listener, connector) = MemoryListener::new();
+            let server = Server::with_driver(Flaky::default(), listener, Http1::new(config))
+                .observer(recorder.clone());
+            tokio::spawn(server);
+
+            let mut client = TestClient::new(connector.connect().unwrap());
+            let response = client.send(Request::<Full<Bytes>>::default()).await.unwrap();
+            assert_eq!(response.status(), &StatusCode::INTERNAL_SERVER_ERROR);
+            assert_eq!(
+                response.headers().get(standard::CONTENT_TYPE).unwrap(),
+                "application/problem+json"
+            );
+
+            // the connection is still reused
+            let response = client.send(Request::<Full<Bytes>>::default()).await.unwrap();
+            assert_eq!(response.status(), &StatusCode::OK);
+            assert_eq!(response.body(), &Bytes::from_static(b"hello"));
+
+            settle().await;
+            assert_eq!(recorder.events(), ["accept", "service_error", "request_end", "request_end"]);
+        });
+    }
+}
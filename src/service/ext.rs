@@ -0,0 +1,476 @@
+//! Service combinators.
+use std::pin::Pin;
+use std::task::{Poll, ready};
+
+use super::{BoxCloneService, BoxService, Service};
+
+// ===== ServiceExt =====
+
+/// Extension trait for [`Service`].
+pub trait ServiceExt<Request>: Service<Request> {
+    /// Map the request before it is passed to the service.
+    #[inline]
+    fn map_request<F, R>(self, f: F) -> MapRequest<Self, F>
+    where
+        Self: Sized,
+        F: Fn(R) -> Request,
+    {
+        MapRequest { inner: self, f }
+    }
+
+    /// Map the successful response of the service.
+    #[inline]
+    fn map_response<F, R>(self, f: F) -> MapResponse<Self, F>
+    where
+        Self: Sized,
+        F: Fn(Self::Response) -> R + Clone,
+    {
+        MapResponse { inner: self, f }
+    }
+
+    /// Map the error of the service.
+    #[inline]
+    fn map_err<F, E>(self, f: F) -> MapErr<Self, F>
+    where
+        Self: Sized,
+        F: Fn(Self::Error) -> E + Clone,
+    {
+        MapErr { inner: self, f }
+    }
+
+    /// Call an async function with the successful response of the service.
+    #[inline]
+    fn and_then<F, Fut>(self, f: F) -> AndThen<Self, F>
+    where
+        Self: Sized,
+        F: Fn(Self::Response) -> Fut + Clone,
+    {
+        AndThen { inner: self, f }
+    }
+
+    /// Call an async function with the result of the service.
+    #[inline]
+    fn then<F, Fut>(self, f: F) -> Then<Self, F>
+    where
+        Self: Sized,
+        F: Fn(Result<Self::Response, Self::Error>) -> Fut + Clone,
+    {
+        Then { inner: self, f }
+    }
+
+    /// Erase the type of the service.
+    #[inline]
+    fn boxed(self) -> BoxService<Request, Self::Response, Self::Error>
+    where
+        Self: Sized + Send + 'static,
+        Self::Future: Send + 'static,
+    {
+        BoxService::new(self)
+    }
+
+    /// Erase the type of the service, while keeping it [`Clone`].
+    ///
+    /// This is required to serve type erased service with [`Server`][crate::server::Server],
+    /// which clone the service for each connection.
+    #[inline]
+    fn boxed_clone(self) -> BoxCloneService<Request, Self::Response, Self::Error>
+    where
+        Self: Sized + Clone + Send + 'static,
+        Self::Future: Send + 'static,
+    {
+        BoxCloneService::new(self)
+    }
+}
+
+impl<S, Request> ServiceExt<Request> for S where S: Service<Request> + ?Sized { }
+
+// ===== MapRequest =====
+
+/// Service returned from [`ServiceExt::map_request`].
+#[derive(Debug, Clone)]
+pub struct MapRequest<S, F> {
+    inner: S,
+    f: F,
+}
+
+impl<S, F, R, Request> Service<R> for MapRequest<S, F>
+where
+    S: Service<Request>,
+    F: Fn(R) -> Request,
+{
+    type Response = S::Response;
+
+    type Error = S::Error;
+
+    type Future = S::Future;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut std::task::Context) -> Poll<()> {
+        self.inner.poll_ready(cx)
+    }
+
+    #[inline]
+    fn call(&self, request: R) -> Self::Future {
+        self.inner.call((self.f)(request))
+    }
+}
+
+// ===== MapResponse =====
+
+/// Service returned from [`ServiceExt::map_response`].
+#[derive(Debug, Clone)]
+pub struct MapResponse<S, F> {
+    inner: S,
+    f: F,
+}
+
+impl<S, F, R, Request> Service<Request> for MapResponse<S, F>
+where
+    S: Service<Request>,
+    F: Fn(S::Response) -> R + Clone,
+{
+    type Response = R;
+
+    type Error = S::Error;
+
+    type Future = MapResponseFuture<S::Future, F>;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut std::task::Context) -> Poll<()> {
+        self.inner.poll_ready(cx)
+    }
+
+    #[inline]
+    fn call(&self, request: Request) -> Self::Future {
+        MapResponseFuture {
+            future: self.inner.call(request),
+            f: Some(self.f.clone()),
+        }
+    }
+}
+
+/// Future returned from [`MapResponse`] service.
+#[derive(Debug)]
+pub struct MapResponseFuture<Fut, F> {
+    future: Fut,
+    f: Option<F>,
+}
+
+impl<Fut, F, T, E, R> Future for MapResponseFuture<Fut, F>
+where
+    Fut: Future<Output = Result<T, E>>,
+    F: FnOnce(T) -> R,
+{
+    type Output = Result<R, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut std::task::Context) -> Poll<Self::Output> {
+        // SAFETY: `future` is never moved
+        let me = unsafe { self.get_unchecked_mut() };
+        let result = ready!(unsafe { Pin::new_unchecked(&mut me.future) }.poll(cx));
+        let f = me.f.take().expect("poll after complete");
+        Poll::Ready(result.map(f))
+    }
+}
+
+// ===== MapErr =====
+
+/// Service returned from [`ServiceExt::map_err`].
+#[derive(Debug, Clone)]
+pub struct MapErr<S, F> {
+    inner: S,
+    f: F,
+}
+
+impl<S, F, E, Request> Service<Request> for MapErr<S, F>
+where
+    S: Service<Request>,
+    F: Fn(S::Error) -> E + Clone,
+{
+    type Response = S::Response;
+
+    type Error = E;
+
+    type Future = MapErrFuture<S::Future, F>;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut std::task::Context) -> Poll<()> {
+        self.inner.poll_ready(cx)
+    }
+
+    #[inline]
+    fn call(&self, request: Request) -> Self::Future {
+        MapErrFuture {
+            future: self.inner.call(request),
+            f: Some(self.f.clone()),
+        }
+    }
+}
+
+/// Future returned from [`MapErr`] service.
+#[derive(Debug)]
+pub struct MapErrFuture<Fut, F> {
+    future: Fut,
+    f: Option<F>,
+}
+
+impl<Fut, F, T, E, E2> Future for MapErrFuture<Fut, F>
+where
+    Fut: Future<Output = Result<T, E>>,
+    F: FnOnce(E) -> E2,
+{
+    type Output = Result<T, E2>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut std::task::Context) -> Poll<Self::Output> {
+        // SAFETY: `future` is never moved
+        let me = unsafe { self.get_unchecked_mut() };
+        let result = ready!(unsafe { Pin::new_unchecked(&mut me.future) }.poll(cx));
+        let f = me.f.take().expect("poll after complete");
+        Poll::Ready(result.map_err(f))
+    }
+}
+
+// ===== AndThen =====
+
+/// Service returned from [`ServiceExt::and_then`].
+#[derive(Debug, Clone)]
+pub struct AndThen<S, F> {
+    inner: S,
+    f: F,
+}
+
+impl<S, F, Fut, R, Request> Service<Request> for AndThen<S, F>
+where
+    S: Service<Request>,
+    F: Fn(S::Response) -> Fut + Clone,
+    Fut: Future<Output = Result<R, S::Error>>,
+{
+    type Response = R;
+
+    type Error = S::Error;
+
+    type Future = AndThenFuture<S::Future, Fut, F>;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut std::task::Context) -> Poll<()> {
+        self.inner.poll_ready(cx)
+    }
+
+    #[inline]
+    fn call(&self, request: Request) -> Self::Future {
+        AndThenFuture {
+            phase: Phase::First(self.inner.call(request), Some(self.f.clone())),
+        }
+    }
+}
+
+/// Future returned from [`AndThen`] service.
+#[derive(Debug)]
+pub struct AndThenFuture<Fut1, Fut2, F> {
+    phase: Phase<Fut1, Fut2, F>,
+}
+
+impl<Fut1, Fut2, F, T, R, E> Future for AndThenFuture<Fut1, Fut2, F>
+where
+    Fut1: Future<Output = Result<T, E>>,
+    Fut2: Future<Output = Result<R, E>>,
+    F: FnOnce(T) -> Fut2,
+{
+    type Output = Result<R, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut std::task::Context) -> Poll<Self::Output> {
+        // SAFETY: `self` is pinned, thus `self.phase` is also pinned
+        let phase = unsafe { &mut self.get_unchecked_mut().phase };
+        loop {
+            match phase {
+                Phase::First(future, f) => {
+                    let result = ready!(unsafe { Pin::new_unchecked(future) }.poll(cx));
+                    let f = f.take().expect("poll after complete");
+                    match result {
+                        Ok(ok) => *phase = Phase::Second(f(ok)),
+                        Err(err) => return Poll::Ready(Err(err)),
+                    }
+                }
+                Phase::Second(future) => return unsafe { Pin::new_unchecked(future) }.poll(cx),
+            }
+        }
+    }
+}
+
+// ===== Then =====
+
+/// Service returned from [`ServiceExt::then`].
+#[derive(Debug, Clone)]
+pub struct Then<S, F> {
+    inner: S,
+    f: F,
+}
+
+impl<S, F, Fut, R, E, Request> Service<Request> for Then<S, F>
+where
+    S: Service<Request>,
+    F: Fn(Result<S::Response, S::Error>) -> Fut + Clone,
+    Fut: Future<Output = Result<R, E>>,
+{
+    type Response = R;
+
+    type Error = E;
+
+    type Future = ThenFuture<S::Future, Fut, F>;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut std::task::Context) -> Poll<()> {
+        self.inner.poll_ready(cx)
+    }
+
+    #[inline]
+    fn call(&self, request: Request) -> Self::Future {
+        ThenFuture {
+            phase: Phase::First(self.inner.call(request), Some(self.f.clone())),
+        }
+    }
+}
+
+/// Future returned from [`Then`] service.
+#[derive(Debug)]
+pub struct ThenFuture<Fut1, Fut2, F> {
+    phase: Phase<Fut1, Fut2, F>,
+}
+
+impl<Fut1, Fut2, F> Future for ThenFuture<Fut1, Fut2, F>
+where
+    Fut1: Future,
+    Fut2: Future,
+    F: FnOnce(Fut1::Output) -> Fut2,
+{
+    type Output = Fut2::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut std::task::Context) -> Poll<Self::Output> {
+        // SAFETY: `self` is pinned, thus `self.phase` is also pinned
+        let phase = unsafe { &mut self.get_unchecked_mut().phase };
+        loop {
+            match phase {
+                Phase::First(future, f) => {
+                    let result = ready!(unsafe { Pin::new_unchecked(future) }.poll(cx));
+                    let f = f.take().expect("poll after complete");
+                    *phase = Phase::Second(f(result));
+                }
+                Phase::Second(future) => return unsafe { Pin::new_unchecked(future) }.poll(cx),
+            }
+        }
+    }
+}
+
+// ===== Phase =====
+
+/// Two futures chained with a function.
+#[derive(Debug)]
+enum Phase<Fut1, Fut2, F> {
+    First(Fut1, Option<F>),
+    Second(Fut2),
+}
+
+#[cfg(test)]
+mod test {
+    use std::future::{Ready, ready};
+    use std::task::{Context, Waker};
+
+    use super::*;
+    use crate::testing::block_on;
+
+    /// Service that returns the request, or error if it is negative.
+    #[derive(Clone)]
+    struct Check {
+        is_ready: bool,
+    }
+
+    const READY: Check = Check { is_ready: true };
+
+    const BUSY: Check = Check { is_ready: false };
+
+    impl Service<i32> for Check {
+        type Response = i32;
+
+        type Error = String;
+
+        type Future = Ready<Result<i32, String>>;
+
+        fn poll_ready(&self, _: &mut std::task::Context) -> Poll<()> {
+            if self.is_ready {
+                Poll::Ready(())
+            } else {
+                Poll::Pending
+            }
+        }
+
+        fn call(&self, n: i32) -> Self::Future {
+            ready(if n < 0 { Err(format!("negative {n}")) } else { Ok(n) })
+        }
+    }
+
+    fn call<S: Service<R>, R>(service: &S, request: R) -> Result<S::Response, S::Error> {
+        block_on(service.call(request))
+    }
+
+    fn is_ready<S: Service<i32>>(service: &S) -> bool {
+        service.poll_ready(&mut Context::from_waker(Waker::noop())).is_ready()
+    }
+
+    #[test]
+    fn test_map_request() {
+        let service = READY.map_request(|s: &str| s.len() as i32);
+        assert_eq!(call(&service, "abc"), Ok(3));
+
+        let service = BUSY.map_request(|s: &str| s.len() as i32);
+        let is_ready = service.poll_ready(&mut Context::from_waker(Waker::noop())).is_ready();
+        assert!(!is_ready);
+    }
+
+    #[test]
+    fn test_map_response() {
+        assert_eq!(call(&READY.map_response(|n| n * 2), 2), Ok(4));
+        assert_eq!(call(&READY.map_response(|n| n * 2), -1), Err("negative -1".into()));
+        assert!(!is_ready(&BUSY.map_response(|n| n * 2)));
+    }
+
+    #[test]
+    fn test_map_err() {
+        assert_eq!(call(&READY.map_err(|e| e.len()), 2), Ok(2));
+        assert_eq!(call(&READY.map_err(|e| e.len()), -1), Err(11));
+        assert!(!is_ready(&BUSY.map_err(|e| e.len())));
+    }
+
+    #[test]
+    fn test_and_then() {
+        let add = |n: i32| async move { n.checked_add(1).ok_or_else(|| String::from("overflow")) };
+        assert_eq!(call(&READY.and_then(add), 1), Ok(2));
+        assert_eq!(call(&READY.and_then(add), i32::MAX), Err("overflow".into()));
+        // the function is not called on error
+        assert_eq!(call(&READY.and_then(add), -1), Err("negative -1".into()));
+        assert!(!is_ready(&BUSY.and_then(add)));
+    }
+
+    #[test]
+    fn test_then() {
+        let is_ok = |result: Result<i32, String>| async move { Ok::<_, String>(result.is_ok()) };
+        assert_eq!(call(&READY.then(is_ok), 1), Ok(true));
+        assert_eq!(call(&READY.then(is_ok), -1), Ok(false));
+        assert!(!is_ready(&BUSY.then(is_ok)));
+    }
+
+    #[test]
+    fn test_boxed() {
+        let service = READY.map_response(|n| n * 2).boxed();
+        assert_eq!(call(&service, 2), Ok(4));
+        assert_eq!(call(&service, -1), Err("negative -1".into()));
+        assert!(!is_ready(&BUSY.boxed()));
+    }
+
+    #[test]
+    fn test_boxed_clone() {
+        let service = READY.map_response(|n| n * 2).boxed_clone();
+        let cloned = service.clone();
+        assert_eq!(call(&service, 2), Ok(4));
+        assert_eq!(call(&cloned, 3), Ok(6));
+        assert!(!is_ready(&BUSY.boxed_clone()));
+    }
+}
@@ -0,0 +1,256 @@
+//! Middleware composition.
+
+// ===== Layer =====
+
+/// Wrap a service with another service, such as middleware.
+///
+/// The wrapped service is an [`HttpService`][super::HttpService] as long as it implements
+/// [`Service`][super::Service] for HTTP request and response, thus it can be served directly with
+/// [`Server`][crate::server::Server].
+///
+/// # Examples
+///
+/// ```
+/// use tsue::service::{Layer, Service};
+///
+/// struct Log<S>(S);
+///
+/// impl<S, R> Service<R> for Log<S>
+/// where
+///     S: Service<R>,
+/// {
+///     type Response = S::Response;
+///     type Error = S::Error;
+///     type Future = S::Future;
+///
+///     fn poll_ready(&self, cx: &mut std::task::Context) -> std::task::Poll<()> {
+///         self.0.poll_ready(cx)
+///     }
+///
+///     fn call(&self, request: R) -> Self::Future {
+///         println!("request received");
+///         self.0.call(request)
+///     }
+/// }
+///
+/// struct LogLayer;
+///
+/// impl<S> Layer<S> for LogLayer {
+///     type Service = Log<S>;
+///
+///     fn layer(&self, inner: S) -> Self::Service {
+///         Log(inner)
+///     }
+/// }
+/// ```
+pub trait Layer<S> {
+    /// The wrapping service.
+    type Service;
+
+    /// Wrap the `inner` service.
+    fn layer(&self, inner: S) -> Self::Service;
+}
+
+impl<L, S> Layer<S> for &L
+where
+    L: Layer<S> + ?Sized,
+{
+    type Service = L::Service;
+
+    #[inline]
+    fn layer(&self, inner: S) -> Self::Service {
+        L::layer(self, inner)
+    }
+}
+
+// ===== Identity =====
+
+/// A [`Layer`] that returns the service as is.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Identity;
+
+impl<S> Layer<S> for Identity {
+    type Service = S;
+
+    #[inline]
+    fn layer(&self, inner: S) -> Self::Service {
+        inner
+    }
+}
+
+// ===== Stack =====
+
+/// Two [`Layer`] applied in order, `inner` first, then `outer`.
+#[derive(Debug, Clone, Default)]
+pub struct Stack<Inner, Outer> {
+    inner: Inner,
+    outer: Outer,
+}
+
+impl<Inner, Outer> Stack<Inner, Outer> {
+    /// Create new [`Stack`].
+    #[inline]
+    pub fn new(inner: Inner, outer: Outer) -> Self {
+        Self { inner, outer }
+    }
+}
+
+impl<S, Inner, Outer> Layer<S> for Stack<Inner, Outer>
+where
+    Inner: Layer<S>,
+    Outer: Layer<Inner::Service>,
+{
+    type Service = Outer::Service;
+
+    #[inline]
+    fn layer(&self, inner: S) -> Self::Service {
+        self.outer.layer(self.inner.layer(inner))
+    }
+}
+
+// ===== LayerFn =====
+
+/// Create a [`Layer`] from a function.
+#[inline]
+pub fn layer_fn<F>(f: F) -> LayerFn<F> {
+    LayerFn { f }
+}
+
+/// A [`Layer`] from a function, created with [`layer_fn`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LayerFn<F> {
+    f: F,
+}
+
+impl<F, S, Out> Layer<S> for LayerFn<F>
+where
+    F: Fn(S) -> Out,
+{
+    type Service = Out;
+
+    #[inline]
+    fn layer(&self, inner: S) -> Self::Service {
+        (self.f)(inner)
+    }
+}
+
+// ===== ServiceBuilder =====
+
+/// Stack multiple [`Layer`] around a service.
+///
+/// Layers are applied in the order they are added, the first added layer is the outermost
+/// service, thus it sees the request first and the response last.
+///
+/// # Examples
+///
+/// ```
+/// use tsue::service::{ServiceBuilder, layer_fn};
+///
+/// let service = ServiceBuilder::new()
+///     .layer_fn(|inner: String| format!("outer({inner})"))
+///     .layer_fn(|inner: String| format!("inner({inner})"))
+///     .service(String::from("service"));
+///
+/// assert_eq!(service, "outer(inner(service))");
+/// ```
+#[derive(Debug, Clone)]
+pub struct ServiceBuilder<L> {
+    layer: L,
+}
+
+impl Default for ServiceBuilder<Identity> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ServiceBuilder<Identity> {
+    /// Create new empty [`ServiceBuilder`].
+    #[inline]
+    pub fn new() -> Self {
+        Self { layer: Identity }
+    }
+}
+
+impl<L> ServiceBuilder<L> {
+    /// Add a [`Layer`], which wraps all layers that added after it.
+    #[inline]
+    pub fn layer<T>(self, layer: T) -> ServiceBuilder<Stack<T, L>> {
+        ServiceBuilder {
+            layer: Stack::new(layer, self.layer),
+        }
+    }
+
+    /// Add a [`Layer`] from a function.
+    ///
+    /// This is shorthand for `.layer(layer_fn(f))`.
+    #[inline]
+    pub fn layer_fn<F>(self, f: F) -> ServiceBuilder<Stack<LayerFn<F>, L>> {
+        self.layer(layer_fn(f))
+    }
+
+    /// Wrap `service` with all the layers.
+    #[inline]
+    pub fn service<S>(&self, service: S) -> L::Service
+    where
+        L: Layer<S>,
+    {
+        self.layer.layer(service)
+    }
+
+    /// Returns the stacked layers.
+    #[inline]
+    pub fn into_inner(self) -> L {
+        self.layer
+    }
+}
+
+impl<S, L> Layer<S> for ServiceBuilder<L>
+where
+    L: Layer<S>,
+{
+    type Service = L::Service;
+
+    #[inline]
+    fn layer(&self, inner: S) -> Self::Service {
+        self.layer.layer(inner)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn wrap(name: &'static str) -> LayerFn<impl Fn(String) -> String> {
+        layer_fn(move |inner: String| format!("{name}({inner})"))
+    }
+
+    #[test]
+    fn test_identity() {
+        assert_eq!(Identity.layer(String::from("service")), "service");
+        assert_eq!(ServiceBuilder::new().service(String::from("service")), "service");
+    }
+
+    #[test]
+    fn test_stack() {
+        // `inner` is applied first
+        let stack = Stack::new(wrap("inner"), wrap("outer"));
+        assert_eq!(stack.layer(String::from("service")), "outer(inner(service))");
+    }
+
+    #[test]
+    fn test_service_builder() {
+        // the first added layer is the outermost
+        let builder = ServiceBuilder::new()
+            .layer(wrap("a"))
+            .layer(wrap("b"))
+            .layer_fn(|inner: String| format!("c({inner})"));
+        assert_eq!(builder.service(String::from("service")), "a(b(c(service)))");
+
+        // builder as a layer
+        let builder = ServiceBuilder::new().layer(wrap("outer")).layer(builder);
+        assert_eq!(builder.service(String::from("service")), "outer(a(b(c(service))))");
+        assert_eq!(builder.into_inner().layer(String::from("service")), "outer(a(b(c(service))))");
+    }
+}
@@ -0,0 +1,702 @@
+//! Concurrency limit middleware.
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::net::IpAddr;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Poll, ready};
+use std::time::Duration;
+use tokio::sync::{AcquireError, OwnedSemaphorePermit, Semaphore};
+
+use super::Service;
+use crate::body::{Body, Incoming};
+use crate::headers::{HeaderName, HeaderValue, standard};
+use crate::http::{Request, Response, StatusCode};
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+type KeyFn<K> = Arc<dyn Fn(&Request<Incoming>) -> Option<K> + Send + Sync>;
+
+type Release = Box<dyn FnOnce(&Arc<Limiter>) + Send + Sync>;
+
+// ===== ConcurrencyLimit =====
+
+/// Limit the number of in-flight requests of the inner service.
+///
+/// The limit is shared between all clones of the service, thus it applies across connections. A
+/// request is in-flight from the time the service is called until the response body is dropped.
+///
+/// When the limit is reached, requests wait in a first-in first-out queue. When the queue is
+/// also full, the request is shed with `503 Service Unavailable` and the `Retry-After` header.
+/// The queue depth is 0 by default.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use tsue::body::{Full, Incoming};
+/// use tsue::bytes::Bytes;
+/// use tsue::http::{Request, Response};
+/// use tsue::service::{ConcurrencyLimit, from_fn};
+///
+/// async fn handler(_: Request<Incoming>) -> Response<Full<Bytes>> {
+///     Response::default()
+/// }
+///
+/// let service = ConcurrencyLimit::new(from_fn(handler), 256)
+///     .queue(1024)
+///     .retry_after(Duration::from_secs(5));
+/// ```
+pub struct ConcurrencyLimit<S> {
+    inner: S,
+    limiter: Arc<Limiter>,
+    config: Config,
+}
+
+impl<S> ConcurrencyLimit<S> {
+    /// Wrap `inner` service with maximum `max` in-flight requests.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max` is zero.
+    #[inline]
+    pub fn new(inner: S, max: usize) -> Self {
+        assert!(max != 0, "maximum in-flight requests must be greater than zero");
+        Self {
+            inner,
+            limiter: Arc::new(Limiter::new(max)),
+            config: Config::new(max),
+        }
+    }
+
+    /// Set the maximum number of requests waiting for the limit, default to 0.
+    #[inline]
+    pub fn queue(mut self, depth: usize) -> Self {
+        self.config.queue = depth;
+        self
+    }
+
+    /// Set the `Retry-After` of shed response, default to 1 second.
+    ///
+    /// The duration is truncated to seconds.
+    #[inline]
+    pub fn retry_after(mut self, duration: Duration) -> Self {
+        self.config.retry_after = retry_after(duration);
+        self
+    }
+
+    /// Returns the number of in-flight requests.
+    #[inline]
+    pub fn in_flight(&self) -> usize {
+        self.config.max - self.limiter.semaphore.available_permits()
+    }
+
+    /// Returns the inner service.
+    #[inline]
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S, Req, B> Service<Req> for ConcurrencyLimit<S>
+where
+    S: Service<Req, Response = Response<B>> + Clone,
+    B: Body,
+    B::Error: Into<BoxError>,
+{
+    type Response = Response<ConcurrencyLimitBody<B>>;
+
+    type Error = S::Error;
+
+    type Future = ConcurrencyLimitFuture<S, Req>;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut std::task::Context) -> Poll<()> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&self, request: Req) -> Self::Future {
+        let slot = Slot {
+            limiter: self.limiter.clone(),
+            is_queued: false,
+            release: None,
+        };
+        ConcurrencyLimitFuture {
+            state: State::start(&self.inner, request, slot, &self.config),
+        }
+    }
+}
+
+impl<S: Clone> Clone for ConcurrencyLimit<S> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            limiter: self.limiter.clone(),
+            config: self.config.clone(),
+        }
+    }
+}
+
+impl<S: std::fmt::Debug> std::fmt::Debug for ConcurrencyLimit<S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ConcurrencyLimit")
+            .field("inner", &self.inner)
+            .field("max", &self.config.max)
+            .field("queue", &self.config.queue)
+            .finish_non_exhaustive()
+    }
+}
+
+// ===== KeyedConcurrencyLimit =====
+
+/// Limit the number of in-flight requests of the inner service per key.
+///
+/// This is like [`ConcurrencyLimit`], but each key, such as the peer address, has its own limit
+/// and queue. This prevents a single client from exhausting the capacity of others. Requests
+/// without a key are not limited.
+///
+/// # Examples
+///
+/// ```
+/// use tsue::body::{Full, Incoming};
+/// use tsue::bytes::Bytes;
+/// use tsue::headers::HeaderName;
+/// use tsue::http::{Request, Response};
+/// use tsue::service::{KeyedConcurrencyLimit, from_fn};
+///
+/// async fn handler(_: Request<Incoming>) -> Response<Full<Bytes>> {
+///     Response::default()
+/// }
+///
+/// // limit by peer ip address
+/// let service = KeyedConcurrencyLimit::by_peer_ip(from_fn(handler), 16);
+///
+/// // limit by api key
+/// let service = KeyedConcurrencyLimit::by_header(
+///     from_fn(handler),
+///     HeaderName::from_static(b"x-api-key"),
+///     16,
+/// );
+/// ```
+pub struct KeyedConcurrencyLimit<S, K> {
+    inner: S,
+    key: KeyFn<K>,
+    limiters: Arc<Mutex<HashMap<K, Arc<Limiter>>>>,
+    config: Config,
+}
+
+impl<S, K> KeyedConcurrencyLimit<S, K> {
+    /// Wrap `inner` service with maximum `max` in-flight requests for each key returned by
+    /// `key`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max` is zero.
+    pub fn new<F>(inner: S, max: usize, key: F) -> Self
+    where
+        F: Fn(&Request<Incoming>) -> Option<K> + Send + Sync + 'static,
+    {
+        assert!(max != 0, "maximum in-flight requests must be greater than zero");
+        Self {
+            inner,
+            key: Arc::new(key),
+            limiters: Arc::default(),
+            config: Config::new(max),
+        }
+    }
+
+    /// Set the maximum number of requests waiting for the limit of each key, default to 0.
+    #[inline]
+    pub fn queue(mut self, depth: usize) -> Self {
+        self.config.queue = depth;
+        self
+    }
+
+    /// Set the `Retry-After` of shed response, default to 1 second.
+    ///
+    /// The duration is truncated to seconds.
+    #[inline]
+    pub fn retry_after(mut self, duration: Duration) -> Self {
+        self.config.retry_after = retry_after(duration);
+        self
+    }
+
+    /// Returns the inner service.
+    #[inline]
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S> KeyedConcurrencyLimit<S, IpAddr> {
+    /// Limit requests per peer ip address.
+    ///
+    /// Requests with unknown peer address are not limited.
+    pub fn by_peer_ip(inner: S, max: usize) -> Self {
+        Self::new(inner, max, |request| request.info().peer_addr().and_then(|addr| addr.ip()))
+    }
+}
+
+impl<S> KeyedConcurrencyLimit<S, Vec<u8>> {
+    /// Limit requests per value of the header `name`.
+    ///
+    /// Requests without the header are not limited.
+    pub fn by_header(inner: S, name: HeaderName, max: usize) -> Self {
+        Self::new(inner, max, move |request| {
+            request.headers().get(&name).map(|value| value.as_bytes().to_vec())
+        })
+    }
+}
+
+impl<S, K, B> Service<Request<Incoming>> for KeyedConcurrencyLimit<S, K>
+where
+    S: Service<Request<Incoming>, Response = Response<B>> + Clone,
+    K: Hash + Eq + Clone + Send + Sync + 'static,
+    B: Body,
+    B::Error: Into<BoxError>,
+{
+    type Response = Response<ConcurrencyLimitBody<B>>;
+
+    type Error = S::Error;
+
+    type Future = ConcurrencyLimitFuture<S, Request<Incoming>>;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut std::task::Context) -> Poll<()> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&self, request: Request<Incoming>) -> Self::Future {
+        let Some(key) = (self.key)(&request) else {
+            return ConcurrencyLimitFuture {
+                state: State::Called {
+                    future: self.inner.call(request),
+                    permit: None,
+                },
+            };
+        };
+
+        let limiter = self
+            .limiters
+            .lock()
+            .unwrap()
+            .entry(key.clone())
+            .or_insert_with(|| Arc::new(Limiter::new(self.config.max)))
+            .clone();
+
+        // remove the limiter when the last request of the key is released
+        let limiters = self.limiters.clone();
+        let release = Box::new(move |limiter: &Arc<Limiter>| {
+            let mut limiters = limiters.lock().unwrap();
+            // the map and the released slot
+            if Arc::strong_count(limiter) == 2 {
+                limiters.remove(&key);
+            }
+        });
+
+        let slot = Slot {
+            limiter,
+            is_queued: false,
+            release: Some(release),
+        };
+        ConcurrencyLimitFuture {
+            state: State::start(&self.inner, request, slot, &self.config),
+        }
+    }
+}
+
+impl<S: Clone, K> Clone for KeyedConcurrencyLimit<S, K> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            key: self.key.clone(),
+            limiters: self.limiters.clone(),
+            config: self.config.clone(),
+        }
+    }
+}
+
+impl<S: std::fmt::Debug, K> std::fmt::Debug for KeyedConcurrencyLimit<S, K> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("KeyedConcurrencyLimit")
+            .field("inner", &self.inner)
+            .field("max", &self.config.max)
+            .field("queue", &self.config.queue)
+            .finish_non_exhaustive()
+    }
+}
+
+// ===== Config =====
+
+#[derive(Clone)]
+struct Config {
+    max: usize,
+    queue: usize,
+    retry_after: HeaderValue,
+}
+
+impl Config {
+    fn new(max: usize) -> Self {
+        Self {
+            max,
+            queue: 0,
+            retry_after: HeaderValue::from_static(b"1"),
+        }
+    }
+}
+
+fn retry_after(duration: Duration) -> HeaderValue {
+    HeaderValue::from_slice(duration.as_secs().to_string()).expect("digits are valid header value")
+}
+
+// ===== Limiter =====
+
+struct Limiter {
+    semaphore: Arc<Semaphore>,
+    /// number of requests waiting for permit
+    queued: AtomicUsize,
+}
+
+impl Limiter {
+    fn new(max: usize) -> Self {
+        Self {
+            semaphore: Arc::new(Semaphore::new(max)),
+            queued: AtomicUsize::new(0),
+        }
+    }
+}
+
+/// A request that is queued or in-flight.
+struct Slot {
+    limiter: Arc<Limiter>,
+    is_queued: bool,
+    release: Option<Release>,
+}
+
+impl Slot {
+    /// Returns `false` if the queue is full.
+    fn enqueue(&mut self, depth: usize) -> bool {
+        let queued = &self.limiter.queued;
+        if queued.fetch_add(1, Ordering::AcqRel) >= depth {
+            queued.fetch_sub(1, Ordering::AcqRel);
+            return false;
+        }
+        self.is_queued = true;
+        true
+    }
+
+    fn dequeue(&mut self) {
+        if std::mem::take(&mut self.is_queued) {
+            self.limiter.queued.fetch_sub(1, Ordering::AcqRel);
+        }
+    }
+}
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        self.dequeue();
+        if let Some(release) = self.release.take() {
+            release(&self.limiter);
+        }
+    }
+}
+
+/// Permit of an in-flight request.
+struct Permit {
+    // permit is released before the slot
+    _permit: OwnedSemaphorePermit,
+    _slot: Slot,
+}
+
+// ===== ConcurrencyLimitFuture =====
+
+type Acquire = Pin<Box<dyn Future<Output = Result<OwnedSemaphorePermit, AcquireError>> + Send>>;
+
+/// Future returned from [`ConcurrencyLimit`] and [`KeyedConcurrencyLimit`] services.
+pub struct ConcurrencyLimitFuture<S, Req>
+where
+    S: Service<Req>,
+{
+    state: State<S, Req>,
+}
+
+enum State<S, Req>
+where
+    S: Service<Req>,
+{
+    Queued {
+        acquire: Acquire,
+        inner: S,
+        request: Option<Req>,
+        slot: Option<Slot>,
+    },
+    Called {
+        future: S::Future,
+        permit: Option<Permit>,
+    },
+    Shed {
+        retry_after: HeaderValue,
+    },
+}
+
+impl<S, Req> State<S, Req>
+where
+    S: Service<Req> + Clone,
+{
+    fn start(inner: &S, request: Req, mut slot: Slot, config: &Config) -> Self {
+        let semaphore = slot.limiter.semaphore.clone();
+        if let Ok(permit) = semaphore.clone().try_acquire_owned() {
+            return State::Called {
+                future: inner.call(request),
+                permit: Some(Permit {
+                    _permit: permit,
+                    _slot: slot,
+                }),
+            };
+        }
+
+        if !slot.enqueue(config.queue) {
+            return State::Shed {
+                retry_after: config.retry_after.clone(),
+            };
+        }
+        State::Queued {
+            acquire: Box::pin(semaphore.acquire_owned()),
+            inner: inner.clone(),
+            request: Some(request),
+            slot: Some(slot),
+        }
+    }
+}
+
+impl<S, Req, B> Future for ConcurrencyLimitFuture<S, Req>
+where
+    S: Service<Req, Response = Response<B>>,
+{
+    type Output = Result<Response<ConcurrencyLimitBody<B>>, S::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut std::task::Context) -> Poll<Self::Output> {
+        // SAFETY: `future` is never moved, the state is only replaced when it is queued
+        let state = unsafe { &mut self.get_unchecked_mut().state };
+        loop {
+            match state {
+                State::Queued { acquire, inner, request, slot } => {
+                    let permit = ready!(acquire.as_mut().poll(cx));
+                    let permit = permit.expect("semaphore is never closed");
+                    let mut slot = slot.take().expect("poll after complete");
+                    slot.dequeue();
+                    let request = request.take().expect("poll after complete");
+                    *state = State::Called {
+                        future: inner.call(request),
+                        permit: Some(Permit {
+                            _permit: permit,
+                            _slot: slot,
+                        }),
+                    };
+                }
+                State::Called { future, permit } => {
+                    let result = ready!(unsafe { Pin::new_unchecked(future) }.poll(cx));
+                    let permit = permit.take();
+                    return Poll::Ready(result.map(|response| {
+                        let (parts, body) = response.into_parts();
+                        let body = ConcurrencyLimitBody {
+                            inner: Some(body),
+                            _permit: permit,
+                        };
+                        Response::from_parts(parts, body)
+                    }));
+                }
+                State::Shed { retry_after } => {
+                    let body = ConcurrencyLimitBody {
+                        inner: None,
+                        _permit: None,
+                    };
+                    let mut response = Response::from_parts(Default::default(), body);
+                    *response.status_mut() = StatusCode::SERVICE_UNAVAILABLE;
+                    response.headers_mut().insert(standard::RETRY_AFTER, retry_after.clone());
+                    return Poll::Ready(Ok(response));
+                }
+            }
+        }
+    }
+}
+
+impl<S, Req> std::fmt::Debug for ConcurrencyLimitFuture<S, Req>
+where
+    S: Service<Req>,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let state = match self.state {
+            State::Queued { .. } => "Queued",
+            State::Called { .. } => "Called",
+            State::Shed { .. } => "Shed",
+        };
+        f.debug_struct("ConcurrencyLimitFuture")
+            .field("state", &state)
+            .finish()
+    }
+}
+
+// ===== ConcurrencyLimitBody =====
+
+/// Response body of concurrency limited service.
+///
+/// The request is in-flight until this body is dropped.
+pub struct ConcurrencyLimitBody<B> {
+    /// `None` if the request is shed
+    inner: Option<B>,
+    _permit: Option<Permit>,
+}
+
+impl<B> Body for ConcurrencyLimitBody<B>
+where
+    B: Body,
+    B::Error: Into<BoxError>,
+{
+    type Data = B::Data;
+
+    type Error = BoxError;
+
+    fn poll_data(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        // SAFETY: `inner` is never moved
+        let inner = unsafe { Pin::new_unchecked(&mut self.get_unchecked_mut().inner) };
+        match inner.as_pin_mut() {
+            Some(inner) => inner
+                .poll_data(cx)
+                .map(|option| option.map(|result| result.map_err(Into::into))),
+            None => Poll::Ready(None),
+        }
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.as_ref().is_none_or(B::is_end_stream)
+    }
+
+    fn size_hint(&self) -> (u64, Option<u64>) {
+        match &self.inner {
+            Some(inner) => inner.size_hint(),
+            None => (0, Some(0)),
+        }
+    }
+}
+
+impl<B: std::fmt::Debug> std::fmt::Debug for ConcurrencyLimitBody<B> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ConcurrencyLimitBody")
+            .field("inner", &self.inner)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::task::{Context, Waker};
+    use tcio::bytes::Bytes;
+    use tokio::sync::oneshot;
+
+    use super::*;
+    use crate::body::Full;
+    use crate::service::from_fn;
+
+    type Res = Response<Full<Bytes>>;
+
+    fn poll<F: Future>(future: &mut Pin<Box<F>>) -> Poll<F::Output> {
+        future.as_mut().poll(&mut Context::from_waker(Waker::noop()))
+    }
+
+    async fn wait(rx: oneshot::Receiver<()>) -> Res {
+        let _ = rx.await;
+        Res::default()
+    }
+
+    #[test]
+    fn test_concurrency_limit() {
+        let service = ConcurrencyLimit::new(from_fn(wait), 1)
+            .queue(1)
+            .retry_after(Duration::from_secs(5));
+        let (tx1, rx1) = oneshot::channel();
+        let (tx2, rx2) = oneshot::channel();
+        let (_tx3, rx3) = oneshot::channel();
+
+        let mut f1 = Box::pin(service.call(rx1));
+        let mut f2 = Box::pin(service.clone().call(rx2));
+        let mut f3 = Box::pin(service.call(rx3));
+        assert!(poll(&mut f1).is_pending());
+        assert!(poll(&mut f2).is_pending());
+        assert_eq!(service.in_flight(), 1);
+
+        let Poll::Ready(Ok(response)) = poll(&mut f3) else {
+            panic!("request should be shed")
+        };
+        assert_eq!(response.status(), &StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.headers().get(standard::RETRY_AFTER).unwrap(), "5");
+
+        tx1.send(()).unwrap();
+        let Poll::Ready(Ok(response)) = poll(&mut f1) else {
+            panic!("request should complete")
+        };
+        assert_eq!(response.status(), &StatusCode::OK);
+
+        // the permit is held by the response body
+        assert!(poll(&mut f2).is_pending());
+        drop(response);
+
+        tx2.send(()).unwrap();
+        assert!(poll(&mut f2).is_ready());
+    }
+
+    #[test]
+    fn test_keyed_concurrency_limit() {
+        let service = KeyedConcurrencyLimit::by_header(
+            from_fn(|_: Request<Incoming>| std::future::pending::<Res>()),
+            standard::HOST,
+            1,
+        );
+        let request = |host: Option<&'static [u8]>| {
+            let mut request = Request::<Incoming>::default();
+            if let Some(host) = host {
+                request.headers_mut().insert(standard::HOST, HeaderValue::from_static(host));
+            }
+            request
+        };
+
+        let mut a1 = Box::pin(service.call(request(Some(b"a"))));
+        let mut a2 = Box::pin(service.call(request(Some(b"a"))));
+        let mut b1 = Box::pin(service.call(request(Some(b"b"))));
+        let mut none = Box::pin(service.call(request(None)));
+        assert!(poll(&mut a1).is_pending());
+        let Poll::Ready(Ok(response)) = poll(&mut a2) else {
+            panic!("request should be shed")
+        };
+        assert_eq!(response.status(), &StatusCode::SERVICE_UNAVAILABLE);
+        assert!(poll(&mut b1).is_pending());
+        assert!(poll(&mut none).is_pending());
+        assert_eq!(service.limiters.lock().unwrap().len(), 2);
+
+        drop(a1);
+        drop(a2);
+        drop(b1);
+        assert!(service.limiters.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_concurrency_limit_zero() {
+        ConcurrencyLimit::new(from_fn(wait), 0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_keyed_concurrency_limit_zero() {
+        KeyedConcurrencyLimit::by_peer_ip(
+            from_fn(|_: Request<Incoming>| std::future::pending::<Res>()),
+            0,
+        );
+    }
+}
@@ -0,0 +1,114 @@
+//! Service trait, middleware composition, and error rendering.
+use std::convert::Infallible;
+use std::task::Poll;
+use tcio::futures::{Map, map};
+
+use crate::body::{Body, Incoming};
+use crate::http::{Request, Response};
+
+mod layer;
+mod ext;
+mod boxed;
+mod error;
+mod catch_panic;
+mod timeout;
+mod limit;
+
+pub use layer::{Identity, Layer, LayerFn, ServiceBuilder, Stack, layer_fn};
+pub use ext::{
+    AndThen, AndThenFuture, MapErr, MapErrFuture, MapRequest, MapResponse, MapResponseFuture,
+    ServiceExt, Then, ThenFuture,
+};
+pub use boxed::{BoxCloneService, BoxFuture, BoxService};
+pub(crate) use boxed::Boxed;
+pub use error::{ErrorHandler, InternalError, ProblemDetails, SharedErrorHandler};
+pub use catch_panic::{CatchPanic, CatchPanicBody, CatchPanicFuture, CatchPanicLayer};
+pub use timeout::{Timeout, TimeoutBody, TimeoutFuture, TimeoutLayer};
+pub use limit::{
+    ConcurrencyLimit, ConcurrencyLimitBody, ConcurrencyLimitFuture, KeyedConcurrencyLimit,
+};
+
+// ===== Service =====
+
+pub trait Service<Request> {
+    type Response;
+
+    type Error;
+
+    type Future: Future<Output = Result<Self::Response, Self::Error>>;
+
+    /// Returns `Ready` when the service is able to process a request.
+    ///
+    /// Connection polls this before dispatching a new request. When the service is overloaded,
+    /// it can returns `Pending` and wake the `cx` waker when it is ready again. HTTP/1.1
+    /// connection delays reading the next request, while its header read and keep-alive timeouts
+    /// still apply.
+    ///
+    /// Unlike [`call`][Service::call], this method may be polled from multiple connections, and a
+    /// `Ready` does not reserve capacity for the next request.
+    ///
+    /// The default implementation is always ready.
+    #[inline]
+    fn poll_ready(&self, cx: &mut std::task::Context) -> Poll<()> {
+        let _ = cx;
+        Poll::Ready(())
+    }
+
+    fn call(&self, request: Request) -> Self::Future;
+}
+
+// ===== HttpService =====
+
+pub trait HttpService:
+    Service<
+        Request<Incoming>,
+        Response = Response<Self::ResBody>,
+        Error: Into<Box<dyn std::error::Error + Send + Sync + 'static>>,
+    >
+where
+    <Self::ResBody as Body>::Error: Into<Box<dyn std::error::Error + Send + Sync + 'static>>,
+{
+    type ResBody: Body;
+}
+
+impl<S, B> HttpService for S
+where
+    S: Service<
+            Request<Incoming>,
+            Response = Response<B>,
+            Error: Into<Box<dyn std::error::Error + Send + Sync + 'static>>,
+        >,
+    B: Body,
+    B::Error: Into<Box<dyn std::error::Error + Send + Sync + 'static>>,
+{
+    type ResBody = B;
+}
+
+// ===== FromFn =====
+
+#[inline]
+pub fn from_fn<F>(f: F) -> FromFn<F> {
+    FromFn { f }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct FromFn<F> {
+    f: F,
+}
+
+impl<F, Fut, Req, Res> Service<Req> for FromFn<F>
+where
+    F: Fn(Req) -> Fut,
+    Fut: Future<Output = Res>,
+{
+    type Response = Res;
+
+    type Error = Infallible;
+
+    type Future = Map<Fut, fn(Res) -> Result<Res, Infallible>>;
+
+    #[inline]
+    fn call(&self, request: Req) -> Self::Future {
+        map((self.f)(request), Ok)
+    }
+}
@@ -0,0 +1,493 @@
+//! Request timeout middleware.
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Poll, ready};
+use std::time::Duration;
+
+use super::{Layer, Service};
+use crate::body::Body;
+use crate::http::{Response, StatusCode};
+use crate::rt::{Sleep, Timer, TokioTimer};
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+type SharedTimer = Arc<dyn Timer + Send + Sync>;
+
+// ===== Timeout =====
+
+/// Bound the time the inner service takes to respond.
+///
+/// The timeout starts when [`Service::call`] is called. If the service future does not complete
+/// in time, it is dropped and an empty response with the timeout status is returned, which is
+/// `503 Service Unavailable` by default.
+///
+/// Optionally, [`body_timeout`][Timeout::body_timeout] bounds the total time spent streaming the
+/// response body, starting from the first poll of the body. Because the response head is already
+/// written, the body returns an error when it expires, which aborts the stream.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use tsue::body::{Full, Incoming};
+/// use tsue::bytes::Bytes;
+/// use tsue::http::{Request, Response, StatusCode};
+/// use tsue::service::{Timeout, from_fn};
+///
+/// async fn handler(_: Request<Incoming>) -> Response<Full<Bytes>> {
+///     Response::default()
+/// }
+///
+/// let service = Timeout::new(from_fn(handler), Duration::from_secs(30))
+///     .status(StatusCode::GATEWAY_TIMEOUT)
+///     .body_timeout(Duration::from_secs(60));
+/// ```
+pub struct Timeout<S> {
+    inner: S,
+    config: Config,
+}
+
+impl<S> Timeout<S> {
+    /// Wrap `inner` service with the given `timeout`.
+    #[inline]
+    pub fn new(inner: S, timeout: Duration) -> Self {
+        Self {
+            inner,
+            config: Config::new(timeout),
+        }
+    }
+
+    /// Set the response status when the service times out, default to
+    /// `503 Service Unavailable`.
+    #[inline]
+    pub fn status(mut self, status: StatusCode) -> Self {
+        self.config.status = status;
+        self
+    }
+
+    /// Set the maximum total time spent streaming the response body, default to `None`.
+    #[inline]
+    pub fn body_timeout(mut self, timeout: Duration) -> Self {
+        self.config.body_timeout = Some(timeout);
+        self
+    }
+
+    /// Set the [`Timer`] used for timeouts, default to [`TokioTimer`].
+    #[inline]
+    pub fn timer<T>(mut self, timer: T) -> Self
+    where
+        T: Timer + Send + Sync + 'static,
+    {
+        self.config.timer = Arc::new(timer);
+        self
+    }
+
+    /// Returns the inner service.
+    #[inline]
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S, Req, B> Service<Req> for Timeout<S>
+where
+    S: Service<Req, Response = Response<B>>,
+    B: Body,
+    B::Error: Into<BoxError>,
+{
+    type Response = Response<TimeoutBody<B>>;
+
+    type Error = S::Error;
+
+    type Future = TimeoutFuture<S::Future>;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut std::task::Context) -> Poll<()> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&self, request: Req) -> Self::Future {
+        TimeoutFuture {
+            future: self.inner.call(request),
+            sleep: self.config.timer.sleep(self.config.timeout),
+            config: Some(self.config.clone()),
+        }
+    }
+}
+
+impl<S: Clone> Clone for Timeout<S> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            config: self.config.clone(),
+        }
+    }
+}
+
+impl<S: std::fmt::Debug> std::fmt::Debug for Timeout<S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Timeout")
+            .field("inner", &self.inner)
+            .field("timeout", &self.config.timeout)
+            .field("body_timeout", &self.config.body_timeout)
+            .field("status", &self.config.status)
+            .finish_non_exhaustive()
+    }
+}
+
+// ===== TimeoutLayer =====
+
+/// [`Layer`] that wraps service with [`Timeout`].
+#[derive(Clone)]
+pub struct TimeoutLayer {
+    config: Config,
+}
+
+impl TimeoutLayer {
+    /// Create new [`TimeoutLayer`] with the given `timeout`.
+    #[inline]
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            config: Config::new(timeout),
+        }
+    }
+
+    /// Set the response status when the service times out, default to
+    /// `503 Service Unavailable`.
+    #[inline]
+    pub fn status(mut self, status: StatusCode) -> Self {
+        self.config.status = status;
+        self
+    }
+
+    /// Set the maximum total time spent streaming the response body, default to `None`.
+    #[inline]
+    pub fn body_timeout(mut self, timeout: Duration) -> Self {
+        self.config.body_timeout = Some(timeout);
+        self
+    }
+
+    /// Set the [`Timer`] used for timeouts, default to [`TokioTimer`].
+    #[inline]
+    pub fn timer<T>(mut self, timer: T) -> Self
+    where
+        T: Timer + Send + Sync + 'static,
+    {
+        self.config.timer = Arc::new(timer);
+        self
+    }
+}
+
+impl<S> Layer<S> for TimeoutLayer {
+    type Service = Timeout<S>;
+
+    #[inline]
+    fn layer(&self, inner: S) -> Self::Service {
+        Timeout {
+            inner,
+            config: self.config.clone(),
+        }
+    }
+}
+
+impl std::fmt::Debug for TimeoutLayer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TimeoutLayer")
+            .field("timeout", &self.config.timeout)
+            .field("body_timeout", &self.config.body_timeout)
+            .field("status", &self.config.status)
+            .finish_non_exhaustive()
+    }
+}
+
+// ===== Config =====
+
+#[derive(Clone)]
+struct Config {
+    timeout: Duration,
+    body_timeout: Option<Duration>,
+    status: StatusCode,
+    timer: SharedTimer,
+}
+
+impl Config {
+    fn new(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            body_timeout: None,
+            status: StatusCode::SERVICE_UNAVAILABLE,
+            timer: Arc::new(TokioTimer),
+        }
+    }
+}
+
+// ===== TimeoutFuture =====
+
+/// Future returned from [`Timeout`] service.
+pub struct TimeoutFuture<Fut> {
+    future: Fut,
+    sleep: Pin<Box<dyn Sleep>>,
+    /// `None` if the future is completed
+    config: Option<Config>,
+}
+
+impl<Fut, B, E> Future for TimeoutFuture<Fut>
+where
+    Fut: Future<Output = Result<Response<B>, E>>,
+{
+    type Output = Result<Response<TimeoutBody<B>>, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut std::task::Context) -> Poll<Self::Output> {
+        // SAFETY: `future` is never moved
+        let me = unsafe { self.get_unchecked_mut() };
+        let future = unsafe { Pin::new_unchecked(&mut me.future) };
+
+        if let Poll::Ready(result) = future.poll(cx) {
+            let config = me.config.take().expect("poll after complete");
+            return Poll::Ready(result.map(|response| {
+                let (parts, body) = response.into_parts();
+                let body = TimeoutBody {
+                    inner: Some(body),
+                    sleep: None,
+                    timeout: config.body_timeout.map(|timeout| (timeout, config.timer)),
+                };
+                Response::from_parts(parts, body)
+            }));
+        }
+
+        ready!(me.sleep.as_mut().poll(cx));
+
+        let config = me.config.take().expect("poll after complete");
+        let body = TimeoutBody {
+            inner: None,
+            sleep: None,
+            timeout: None,
+        };
+        let mut response = Response::from_parts(Default::default(), body);
+        *response.status_mut() = config.status;
+        Poll::Ready(Ok(response))
+    }
+}
+
+impl<Fut> std::fmt::Debug for TimeoutFuture<Fut> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TimeoutFuture").finish_non_exhaustive()
+    }
+}
+
+// ===== TimeoutBody =====
+
+/// Response body of [`Timeout`] service.
+///
+/// If the body timeout expires, an error is returned and the body ends.
+pub struct TimeoutBody<B> {
+    /// `None` if the response is replaced, or the body timed out
+    inner: Option<B>,
+    /// armed on the first poll
+    sleep: Option<Pin<Box<dyn Sleep>>>,
+    timeout: Option<(Duration, SharedTimer)>,
+}
+
+impl<B> Body for TimeoutBody<B>
+where
+    B: Body,
+    B::Error: Into<BoxError>,
+{
+    type Data = B::Data;
+
+    type Error = BoxError;
+
+    fn poll_data(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        // SAFETY: `inner` is never moved, it is only dropped in place
+        let me = unsafe { self.get_unchecked_mut() };
+        let mut body = unsafe { Pin::new_unchecked(&mut me.inner) };
+
+        let Some(inner) = body.as_mut().as_pin_mut() else {
+            return Poll::Ready(None);
+        };
+        if me.sleep.is_none()
+            && let Some((timeout, timer)) = &me.timeout
+        {
+            me.sleep = Some(timer.sleep(*timeout));
+        }
+        if let Poll::Ready(data) = inner.poll_data(cx) {
+            return Poll::Ready(data.map(|result| result.map_err(Into::into)));
+        }
+
+        let Some(sleep) = &mut me.sleep else {
+            return Poll::Pending;
+        };
+        ready!(sleep.as_mut().poll(cx));
+
+        body.set(None);
+        Poll::Ready(Some(Err(Elapsed.into())))
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.as_ref().is_none_or(B::is_end_stream)
+    }
+
+    fn size_hint(&self) -> (u64, Option<u64>) {
+        match &self.inner {
+            Some(inner) => inner.size_hint(),
+            None => (0, Some(0)),
+        }
+    }
+}
+
+impl<B: std::fmt::Debug> std::fmt::Debug for TimeoutBody<B> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TimeoutBody")
+            .field("inner", &self.inner)
+            .finish_non_exhaustive()
+    }
+}
+
+// ===== Elapsed =====
+
+/// Error returned from [`TimeoutBody`] when the body timeout expires.
+#[derive(Debug)]
+struct Elapsed;
+
+impl std::error::Error for Elapsed { }
+
+impl std::fmt::Display for Elapsed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("response body timed out")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::future::{Ready, pending, ready};
+    use tcio::bytes::Bytes;
+
+    use super::*;
+    use crate::body::Full;
+    use crate::service::from_fn;
+    use crate::testing::{Expired, MockTimer, Never, block_on};
+
+    fn ok(_: ()) -> Ready<Response<Full<Bytes>>> {
+        let body = Full::new(Bytes::from_static(b"ok"));
+        ready(Response::from_parts(Default::default(), body))
+    }
+
+    #[test]
+    fn test_timeout() {
+        let service = Timeout::new(from_fn(ok), Duration::ZERO).timer(Expired);
+        let response = block_on(service.call(())).unwrap();
+        assert_eq!(response.status(), &StatusCode::OK);
+
+        let never = from_fn(|_: ()| pending::<Response<Full<Bytes>>>());
+        let service = Timeout::new(never, Duration::ZERO).timer(Expired);
+        let response = block_on(service.call(())).unwrap();
+        assert_eq!(response.status(), &StatusCode::SERVICE_UNAVAILABLE);
+        assert!(response.body().is_end_stream());
+
+        let service = service.status(StatusCode::GATEWAY_TIMEOUT);
+        let response = block_on(service.call(())).unwrap();
+        assert_eq!(response.status(), &StatusCode::GATEWAY_TIMEOUT);
+    }
+
+    #[test]
+    fn test_body_timeout() {
+        struct PendingBody;
+
+        impl Body for PendingBody {
+            type Data = Bytes;
+
+            type Error = std::convert::Infallible;
+
+            fn poll_data(
+                self: Pin<&mut Self>,
+                _: &mut std::task::Context,
+            ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+                Poll::Pending
+            }
+
+            fn is_end_stream(&self) -> bool {
+                false
+            }
+
+            fn size_hint(&self) -> (u64, Option<u64>) {
+                (0, None)
+            }
+        }
+
+        let service = Timeout::new(
+            from_fn(|_: ()| ready(Response::from_parts(Default::default(), PendingBody))),
+            Duration::ZERO,
+        )
+        .body_timeout(Duration::ZERO)
+        .timer(Expired);
+
+        let response = block_on(service.call(())).unwrap();
+        assert_eq!(response.status(), &StatusCode::OK);
+
+        let mut body = std::pin::pin!(response.into_body());
+        let result = block_on(std::future::poll_fn(|cx| body.as_mut().poll_data(cx)));
+        assert!(matches!(result, Some(Err(_))));
+        assert!(body.is_end_stream());
+
+        // body is not bounded without body timeout
+        let service = Timeout::new(from_fn(ok), Duration::ZERO).timer(Never);
+        let response = block_on(service.call(())).unwrap();
+        let mut body = std::pin::pin!(response.into_body());
+        let result = block_on(std::future::poll_fn(|cx| body.as_mut().poll_data(cx)));
+        assert_eq!(&result.unwrap().unwrap()[..], b"ok");
+    }
+
+    #[test]
+    fn test_body_timeout_starts_on_first_poll() {
+        /// Body that yields one chunk, then never completes.
+        struct SlowBody(bool);
+
+        impl Body for SlowBody {
+            type Data = Bytes;
+
+            type Error = std::convert::Infallible;
+
+            fn poll_data(
+                self: Pin<&mut Self>,
+                _: &mut std::task::Context,
+            ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+                let me = self.get_mut();
+                if std::mem::replace(&mut me.0, false) {
+                    Poll::Ready(Some(Ok(Bytes::from_static(b"ok"))))
+                } else {
+                    Poll::Pending
+                }
+            }
+
+            fn is_end_stream(&self) -> bool {
+                false
+            }
+
+            fn size_hint(&self) -> (u64, Option<u64>) {
+                (0, None)
+            }
+        }
+
+        let timer = MockTimer::new();
+        let service = Timeout::new(
+            from_fn(|_: ()| ready(Response::from_parts(Default::default(), SlowBody(true)))),
+            Duration::from_secs(5),
+        )
+        .body_timeout(Duration::from_secs(1))
+        .timer(timer.clone());
+
+        let response = block_on(service.call(())).unwrap();
+        let mut body = std::pin::pin!(response.into_body());
+        let result = block_on(std::future::poll_fn(|cx| body.as_mut().poll_data(cx)));
+        assert_eq!(&result.unwrap().unwrap()[..], b"ok");
+
+        // the timer is armed by the first poll, even though it returns data
+        timer.advance(Duration::from_secs(1));
+        let result = block_on(std::future::poll_fn(|cx| body.as_mut().poll_data(cx)));
+        assert!(matches!(result, Some(Err(_))));
+    }
+}
@@ -0,0 +1,293 @@
+//! Shared test utilities.
+use std::convert::Infallible;
+use std::error::Error;
+use std::future::{Pending, Ready, pending, ready};
+use std::io;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{Poll, Waker};
+use std::time::{Duration, Instant};
+use tcio::bytes::Bytes;
+use tokio::io::{AsyncReadExt, DuplexStream};
+
+use crate::body::{Full, Incoming};
+use crate::event::{Observer, SharedObserver};
+use crate::http::{ConnectionInfo, Request, Response, StatusCode};
+use crate::rt::{Sleep, Timer};
+use crate::service::Service;
+
+/// Run `future` to completion in a current thread runtime.
+pub(crate) fn block_on<F: Future>(future: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap()
+        .block_on(future)
+}
+
+/// Let spawned tasks run until they are blocked.
+pub(crate) async fn settle() {
+    for _ in 0..32 {
+        tokio::task::yield_now().await;
+    }
+}
+
+// ===== Timers =====
+
+impl Sleep for Ready<()> { }
+
+impl Sleep for Pending<()> { }
+
+/// Timer that expires immediately.
+pub(crate) struct Expired;
+
+impl Timer for Expired {
+    fn sleep(&self, _: Duration) -> Pin<Box<dyn Sleep>> {
+        Box::pin(ready(()))
+    }
+
+    fn sleep_until(&self, _: Instant) -> Pin<Box<dyn Sleep>> {
+        Box::pin(ready(()))
+    }
+}
+
+/// Timer that never expires.
+pub(crate) struct Never;
+
+impl Timer for Never {
+    fn sleep(&self, _: Duration) -> Pin<Box<dyn Sleep>> {
+        Box::pin(pending())
+    }
+
+    fn sleep_until(&self, _: Instant) -> Pin<Box<dyn Sleep>> {
+        Box::pin(pending())
+    }
+}
+
+/// Timer with a paused clock that is only moved by [`advance`][MockTimer::advance].
+#[derive(Clone)]
+pub(crate) struct MockTimer {
+    clock: Arc<Mutex<Clock>>,
+}
+
+struct Clock {
+    start: Instant,
+    elapsed: Duration,
+    wakers: Vec<Waker>,
+}
+
+impl MockTimer {
+    pub(crate) fn new() -> Self {
+        Self {
+            clock: Arc::new(Mutex::new(Clock {
+                start: Instant::now(),
+                elapsed: Duration::ZERO,
+                wakers: Vec::new(),
+            })),
+        }
+    }
+
+    /// Move the clock forward, and wake all pending sleeps.
+    pub(crate) fn advance(&self, duration: Duration) {
+        let mut clock = lock(&self.clock);
+        clock.elapsed += duration;
+        for waker in clock.wakers.drain(..) {
+            waker.wake();
+        }
+    }
+
+    fn sleep_at(&self, deadline: Duration) -> Pin<Box<dyn Sleep>> {
+        Box::pin(MockSleep {
+            clock: self.clock.clone(),
+            deadline,
+        })
+    }
+}
+
+impl Timer for MockTimer {
+    fn sleep(&self, duration: Duration) -> Pin<Box<dyn Sleep>> {
+        let elapsed = lock(&self.clock).elapsed;
+        self.sleep_at(elapsed + duration)
+    }
+
+    fn sleep_until(&self, deadline: Instant) -> Pin<Box<dyn Sleep>> {
+        let start = lock(&self.clock).start;
+        self.sleep_at(deadline.saturating_duration_since(start))
+    }
+}
+
+struct MockSleep {
+    clock: Arc<Mutex<Clock>>,
+    /// elapsed time of the clock when the sleep expires
+    deadline: Duration,
+}
+
+impl Future for MockSleep {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut std::task::Context) -> Poll<Self::Output> {
+        let mut clock = lock(&self.clock);
+        if clock.elapsed >= self.deadline {
+            return Poll::Ready(());
+        }
+        clock.wakers.push(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl Sleep for MockSleep { }
+
+// ===== Observer =====
+
+/// [`Observer`] that records the name of each connection event.
+#[derive(Clone, Default)]
+pub(crate) struct Recorder {
+    events: Arc<Mutex<Vec<&'static str>>>,
+}
+
+impl Recorder {
+    pub(crate) fn shared(&self) -> SharedObserver {
+        Arc::new(self.clone())
+    }
+
+    /// Returns the recorded events.
+    pub(crate) fn events(&self) -> Vec<&'static str> {
+        lock(&self.events).clone()
+    }
+
+    fn record(&self, event: &'static str) {
+        lock(&self.events).push(event);
+    }
+}
+
+impl Observer for Recorder {
+    fn on_accept(&self, _: &ConnectionInfo) {
+        self.record("accept");
+    }
+
+    fn on_accept_error(&self, _: &io::Error) {
+        self.record("accept_error");
+    }
+
+    fn on_close(&self, _: &ConnectionInfo) {
+        self.record("close");
+    }
+
+    fn on_abort(&self, _: &ConnectionInfo, _: &(dyn Error + 'static)) {
+        self.record("abort");
+    }
+
+    fn on_protocol_error(&self, _: &ConnectionInfo, _: &(dyn Error + 'static)) {
+        self.record("protocol_error");
+    }
+
+    fn on_service_error(&self, _: &ConnectionInfo, _: &(dyn Error + 'static)) {
+        self.record("service_error");
+    }
+
+    fn on_request_end(&self, _: &ConnectionInfo, _: StatusCode) {
+        self.record("request_end");
+    }
+}
+
+// ===== Service =====
+
+/// Service that is not ready until it is opened.
+#[derive(Clone, Default)]
+pub(crate) struct Gate {
+    state: Arc<Mutex<(bool, Option<Waker>)>>,
+}
+
+impl Gate {
+    pub(crate) fn open(&self) {
+        let mut state = lock(&self.state);
+        state.0 = true;
+        if let Some(waker) = state.1.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl Service<Request<Incoming>> for Gate {
+    type Response = Response<Full<Bytes>>;
+
+    type Error = Infallible;
+
+    type Future = Ready<Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&self, cx: &mut std::task::Context) -> Poll<()> {
+        let mut state = lock(&self.state);
+        if state.0 {
+            return Poll::Ready(());
+        }
+        state.1 = Some(cx.waker().clone());
+        Poll::Pending
+    }
+
+    fn call(&self, _: Request<Incoming>) -> Self::Future {
+        ready(Ok(Response::from_parts(Default::default(), Full::new(Bytes::from_static(b"hello")))))
+    }
+}
+
+// ===== HTTP/2 =====
+
+/// HTTP/2 frame read by the client.
+pub(crate) struct Frame {
+    pub(crate) ty: u8,
+    pub(crate) flags: u8,
+    pub(crate) stream_id: u32,
+    pub(crate) payload: Vec<u8>,
+}
+
+/// Read a single HTTP/2 frame.
+pub(crate) async fn read_frame(io: &mut DuplexStream) -> Frame {
+    let mut header = [0; 9];
+    io.read_exact(&mut header).await.unwrap();
+    let len = u32::from_be_bytes([0, header[0], header[1], header[2]]) as usize;
+    let mut payload = vec![0; len];
+    io.read_exact(&mut payload).await.unwrap();
+    Frame {
+        ty: header[3],
+        flags: header[4],
+        stream_id: u32::from_be_bytes([header[5], header[6], header[7], header[8]]),
+        payload,
+    }
+}
+
+/// Read the response of a stream, returns the header block and the data.
+///
+/// Frames of other streams are skipped.
+pub(crate) async fn read_response(io: &mut DuplexStream, stream_id: u32) -> (Vec<u8>, Vec<u8>) {
+    const DATA: u8 = 0x0;
+    const HEADERS: u8 = 0x1;
+    const END_STREAM: u8 = 0x1;
+
+    let mut block = None;
+    let mut data = Vec::new();
+    loop {
+        let frame = read_frame(io).await;
+        if frame.stream_id != stream_id {
+            continue;
+        }
+        match frame.ty {
+            DATA => {
+                data.extend_from_slice(&frame.payload);
+                if frame.flags & END_STREAM != 0 {
+                    break;
+                }
+            }
+            HEADERS => {
+                block = Some(frame.payload);
+                if frame.flags & END_STREAM != 0 {
+                    break;
+                }
+            }
+            _ => {}
+        }
+    }
+    (block.unwrap(), data)
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|err| err.into_inner())
+}